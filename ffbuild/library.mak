@@ -173,6 +173,14 @@ ifdef CONFIG_RUST_UTIL_PARSE
 $(NAME)LINK_EXTRA += $(RUST_FFMPEG_UTIL_PARSE_LIB)
 endif
 
+ifdef CONFIG_RUST_DASH
+$(NAME)LINK_EXTRA += $(RUST_FFMPEG_DASH_LIB)
+endif
+
+ifdef CONFIG_RUST_HTMLSUBTITLES
+$(NAME)LINK_EXTRA += $(RUST_FFMPEG_HTMLSUBTITLES_LIB)
+endif
+
 
 $(TESTPROGS) $(TOOLS): %$(EXESUF): %.o
 	$$(call LINK,$$(call $(NAME)LINK_EXE_ARGS) $$(LD_O) $$(filter %.o,$$^) $$(THISLIB) $$(call $(NAME)LINK_EXTRA) $$(EXTRALIBS-$$(*F)) $$(ELIBS))
@@ -252,7 +260,7 @@ $(TOOLS):     $(DEP_LIBS) $(SUBDIR)$($(CONFIG_SHARED:yes=S)LIBNAME)
 $(TESTPROGS): $(DEP_LIBS) $(SUBDIR)$(LIBNAME)
 
 ifdef HAVE_FFMPEG_RUST
-ifeq ($(strip $(CONFIG_RUST_HLSWRITER)$(CONFIG_RUST_HLSPARSER)$(CONFIG_RUST_HLSDEMUX_PARSER)$(CONFIG_RUST_HLSDEMUX_APPLY)$(CONFIG_RUST_WEBVTT)$(CONFIG_RUST_SUBRIP)$(CONFIG_RUST_MICRODVD)$(CONFIG_RUST_TTML)$(CONFIG_RUST_MPL2)$(CONFIG_RUST_VPLAYER)$(CONFIG_RUST_JACOSUB)$(CONFIG_RUST_SUBVIEWER)$(CONFIG_RUST_SUBVIEWER1)$(CONFIG_RUST_SCC)$(CONFIG_RUST_STL)$(CONFIG_RUST_LRC)$(CONFIG_RUST_MPSUB)$(CONFIG_RUST_PJS)$(CONFIG_RUST_REALTEXT)$(CONFIG_RUST_AQTITLE)$(CONFIG_RUST_SAMI)$(CONFIG_RUST_ASS)$(CONFIG_RUST_MCC)$(CONFIG_RUST_ID3V2)$(CONFIG_RUST_CONCAT)$(CONFIG_RUST_DATA_URI)$(CONFIG_RUST_FFMETADATA)$(CONFIG_RUST_UTIL_PARSE)),)
+ifeq ($(strip $(CONFIG_RUST_HLSWRITER)$(CONFIG_RUST_HLSPARSER)$(CONFIG_RUST_HLSDEMUX_PARSER)$(CONFIG_RUST_HLSDEMUX_APPLY)$(CONFIG_RUST_WEBVTT)$(CONFIG_RUST_SUBRIP)$(CONFIG_RUST_MICRODVD)$(CONFIG_RUST_TTML)$(CONFIG_RUST_MPL2)$(CONFIG_RUST_VPLAYER)$(CONFIG_RUST_JACOSUB)$(CONFIG_RUST_SUBVIEWER)$(CONFIG_RUST_SUBVIEWER1)$(CONFIG_RUST_SCC)$(CONFIG_RUST_STL)$(CONFIG_RUST_LRC)$(CONFIG_RUST_MPSUB)$(CONFIG_RUST_PJS)$(CONFIG_RUST_REALTEXT)$(CONFIG_RUST_AQTITLE)$(CONFIG_RUST_SAMI)$(CONFIG_RUST_ASS)$(CONFIG_RUST_MCC)$(CONFIG_RUST_ID3V2)$(CONFIG_RUST_CONCAT)$(CONFIG_RUST_DATA_URI)$(CONFIG_RUST_FFMETADATA)$(CONFIG_RUST_UTIL_PARSE)$(CONFIG_RUST_DASH)$(CONFIG_RUST_HTMLSUBTITLES)),)
 else
 $(TESTPROGS) $(TOOLS): | rust-libs
 endif
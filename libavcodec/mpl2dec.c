@@ -29,7 +29,56 @@
 #include "ass.h"
 #include "codec_internal.h"
 #include "libavutil/bprint.h"
+#include "libavutil/mem.h"
 
+#if defined(HAVE_FFMPEG_RUST) && defined(CONFIG_RUST_MPL2)
+#include "../rust/ffmpeg-mpl2/include/ffmpeg_rs_mpl2.h"
+
+static int mpl2_event_to_ass(AVBPrint *buf, const char *p)
+{
+    FFmpegRsMpl2LinesResult res;
+    FFmpegRsMpl2Line lines_buf[16], *lines = lines_buf;
+    size_t i, j, len = strlen(p);
+
+    if (ffmpeg_rs_mpl2_split_payload((const uint8_t *)p, len, &res,
+                                     lines, FF_ARRAY_ELEMS(lines_buf)) < 0)
+        return AVERROR(EINVAL);
+    if (res.n_lines > FF_ARRAY_ELEMS(lines_buf)) {
+        lines = av_malloc_array(res.n_lines, sizeof(*lines));
+        if (!lines)
+            return AVERROR(ENOMEM);
+        if (ffmpeg_rs_mpl2_split_payload((const uint8_t *)p, len, &res,
+                                         lines, res.n_lines) < 0) {
+            av_free(lines);
+            return AVERROR(EINVAL);
+        }
+    }
+
+    for (i = 0; i < res.n_lines; i++) {
+        const FFmpegRsMpl2Line *l = &lines[i];
+
+        for (j = l->offset - l->prefix_len; j < l->offset; j++) {
+            if      (p[j] == '/')  av_bprintf(buf, "{\\i1}");
+            else if (p[j] == '\\') av_bprintf(buf, "{\\b1}");
+            else if (p[j] == '_')  av_bprintf(buf, "{\\u1}");
+        }
+
+        for (j = l->offset; j < l->offset + l->len; j++)
+            if (p[j] != '\r' && p[j] != '\n')
+                av_bprint_chars(buf, p[j], 1);
+
+        if (i + 1 < res.n_lines) {
+            if (l->flags)
+                av_bprintf(buf, "{\\r}");
+            av_bprintf(buf, "\\N");
+        }
+    }
+
+    if (lines != lines_buf)
+        av_free(lines);
+    return 0;
+}
+#else
 static int mpl2_event_to_ass(AVBPrint *buf, const char *p)
 {
     if (*p == ' ')
@@ -62,6 +111,7 @@ static int mpl2_event_to_ass(AVBPrint *buf, const char *p)
 
     return 0;
 }
+#endif
 
 static int mpl2_decode_frame(AVCodecContext *avctx, AVSubtitle *sub,
                              int *got_sub_ptr, const AVPacket *avpkt)
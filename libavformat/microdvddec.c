@@ -111,9 +111,21 @@ static int microdvd_read_header(AVFormatContext *s)
         if (!*p)
             continue;
         if (i++ < 3) {
+            char c;
+#if defined(HAVE_FFMPEG_RUST) && defined(CONFIG_RUST_MICRODVD)
+            FFmpegRsMicrodvdFrameRate rate;
+
+            ret = ffmpeg_rs_microdvd_parse_framerate((const uint8_t *)line, strlen(line), &rate);
+            if (ret < 0)
+                return AVERROR(EINVAL);
+            if (ret == 1) {
+                pts_info = (AVRational){ rate.num, rate.den };
+                has_real_fps = 1;
+                continue;
+            }
+#else
             int frame;
             double fps;
-            char c;
 
             if ((sscanf(line, "{%d}{}%6lf",    &frame, &fps) == 2 ||
                  sscanf(line, "{%d}{%*d}%6lf", &frame, &fps) == 2)
@@ -122,6 +134,7 @@ static int microdvd_read_header(AVFormatContext *s)
                 has_real_fps = 1;
                 continue;
             }
+#endif
             if (!st->codecpar->extradata && sscanf(line, "{DEFAULT}{}%c", &c) == 1) {
                 int size = strlen(line + 11);
                 ret = ff_alloc_extradata(st->codecpar, size);
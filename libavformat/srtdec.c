@@ -204,7 +204,7 @@ static int srt_read_header(AVFormatContext *s)
 
 #if defined(HAVE_FFMPEG_RUST) && defined(CONFIG_RUST_SUBRIP)
     // Optional Rust fast-path: parse the whole file and replace the queue.
-    // This is a subset parser and ignores positional metadata for now.
+    // This is a subset parser; the display rectangle is exported as side data.
     {
         int64_t start_pos = 0;
         uint8_t *buf = NULL;
@@ -262,6 +262,15 @@ static int srt_read_header(AVFormatContext *s)
                     }
                     sub->pts = e->start_ms;
                     sub->duration = e->duration_ms;
+                    if (e->has_rect) {
+                        uint8_t *p = av_packet_new_side_data(sub, AV_PKT_DATA_SUBTITLE_POSITION, 16);
+                        if (p) {
+                            AV_WL32(p,      e->x1);
+                            AV_WL32(p +  4, e->y1);
+                            AV_WL32(p +  8, e->x2);
+                            AV_WL32(p + 12, e->y2);
+                        }
+                    }
                 }
                 ff_subtitles_queue_finalize(s, &srt->q);
                 av_free(events);
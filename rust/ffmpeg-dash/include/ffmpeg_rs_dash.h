@@ -0,0 +1,340 @@
+#pragma once
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum FFmpegRsDashEventKind {
+    FFMPEG_RS_DASH_EVENT_MPD = 0,
+    FFMPEG_RS_DASH_EVENT_PERIOD = 1,
+    FFMPEG_RS_DASH_EVENT_ADAPTATION_SET = 2,
+    FFMPEG_RS_DASH_EVENT_REPRESENTATION = 3,
+    FFMPEG_RS_DASH_EVENT_BASE_URL = 4,
+    FFMPEG_RS_DASH_EVENT_SEGMENT_TEMPLATE = 5,
+    FFMPEG_RS_DASH_EVENT_SEGMENT_LIST = 6,
+    FFMPEG_RS_DASH_EVENT_SEGMENT_BASE = 7,
+    FFMPEG_RS_DASH_EVENT_SEGMENT_TIMELINE = 8,
+    FFMPEG_RS_DASH_EVENT_S = 9,
+    FFMPEG_RS_DASH_EVENT_SEGMENT_URL = 10,
+    FFMPEG_RS_DASH_EVENT_INITIALIZATION = 11,
+    FFMPEG_RS_DASH_EVENT_CONTENT_PROTECTION = 12,
+    FFMPEG_RS_DASH_EVENT_PSSH = 13,
+    FFMPEG_RS_DASH_EVENT_END = 14, /* closes a scope; i64_a = kind closed */
+} FFmpegRsDashEventKind;
+
+typedef struct FFmpegRsDashEvent {
+    uint32_t kind;
+    uint32_t line_no;
+    uint32_t depth; /* MPD root is 0 */
+
+    /* Primary slice into the input buffer: the raw attribute list for element
+     * events, the trimmed text content for BASE_URL and PSSH. */
+    size_t a_offset;
+    size_t a_len;
+
+    /* Secondary slice: attribute list for BASE_URL and PSSH, schemeIdUri
+     * value for CONTENT_PROTECTION. */
+    size_t b_offset;
+    size_t b_len;
+
+    /* Parsed numeric fields, -1 when the attribute is absent:
+     * MPD:                i64_a = 1 if type="dynamic"
+     * REPRESENTATION:     i64_a = bandwidth
+     * SEGMENT_TEMPLATE/LIST/BASE: i64_a = timescale, i64_b = startNumber
+     * S:                  i64_a = t, i64_b = d, i64_c = r (default 0) */
+    int64_t i64_a;
+    int64_t i64_b;
+    int64_t i64_c;
+} FFmpegRsDashEvent;
+
+typedef struct FFmpegRsDashParseEventsResult {
+    size_t n_events_total;
+    size_t n_events_written;
+    int truncated;
+    uint32_t error_line; /* line of the first syntax error, 0 if none */
+} FFmpegRsDashParseEventsResult;
+
+/*
+ * Parse an MPD into a flat stream of element events.
+ *
+ * - Returns 0 on success, -EINVAL on invalid arguments, AVERROR_INVALIDDATA
+ *   if the document is not well-formed XML or its root element is not MPD.
+ * - All returned slices are offsets/lengths into `text`; entities are left
+ *   encoded (see ffmpeg_rs_dash_unescape()).
+ * - If `events` is NULL or `events_cap` is 0, this performs a size-only pass
+ *   and reports `n_events_total`.
+ */
+int ffmpeg_rs_dash_parse_events(const uint8_t *text, size_t text_len,
+                                FFmpegRsDashParseEventsResult *out,
+                                FFmpegRsDashEvent *events, size_t events_cap);
+
+/* Look up attribute `name` (qualified, e.g. "cenc:default_KID") in an
+ * attribute list slice. Returns 1 and the raw value slice if found, 0 if
+ * absent, -EINVAL on invalid arguments. */
+int ffmpeg_rs_dash_find_attr(const uint8_t *text, size_t text_len,
+                             size_t attrs_offset, size_t attrs_len,
+                             const char *name, size_t name_len,
+                             size_t *out_offset, size_t *out_len);
+
+/* Decode XML character references of `src` into `dst` (NUL-terminated) and
+ * report the size needed including the NUL through `out_required`; pass
+ * dst == NULL to query it. Returns 0, -EINVAL or -ENOSPC. */
+int ffmpeg_rs_dash_unescape(const uint8_t *src, size_t src_len,
+                            char *dst, size_t dst_len, size_t *out_required);
+
+/*
+ * SegmentTemplate / SegmentTimeline expansion.
+ */
+typedef struct FFmpegRsDashTemplateVars {
+    const char *representation_id;
+    size_t representation_id_len;
+    int64_t bandwidth;
+    int64_t number;
+    int64_t time;
+} FFmpegRsDashTemplateVars;
+
+typedef struct FFmpegRsDashTimelineEntry {
+    int64_t t; /* S@t, or -1 to continue from the previous entry */
+    int64_t d;
+    int64_t r; /* -1 repeats up to the next S@t, the period end or the live edge */
+} FFmpegRsDashTimelineEntry;
+
+typedef struct FFmpegRsDashSegmentSource {
+    const char *media; /* SegmentTemplate@media */
+    size_t media_len;
+    const char *representation_id;
+    size_t representation_id_len;
+    int64_t bandwidth;
+    int64_t timescale;    /* <= 0 means 1 */
+    int64_t start_number; /* < 0 means 1 */
+    int64_t duration;     /* @duration, used when there is no timeline */
+    int64_t presentation_time_offset;
+    const FFmpegRsDashTimelineEntry *timeline;
+    size_t n_timeline;
+
+    int64_t period_start_us;
+    int64_t period_duration_us; /* < 0 if unknown */
+
+    /* Dynamic MPDs: availabilityStartTime and current wall-clock time (us
+     * since the epoch), timeShiftBufferDepth (< 0 for unbounded). */
+    int is_dynamic;
+    int64_t availability_start_us;
+    int64_t now_us;
+    int64_t time_shift_buffer_depth_us;
+} FFmpegRsDashSegmentSource;
+
+typedef struct FFmpegRsDashSegment {
+    int64_t number;
+    int64_t time;     /* timescale units */
+    int64_t duration; /* timescale units */
+    int64_t start_us; /* presentation timeline */
+    int64_t duration_us;
+    size_t url_offset; /* NUL-terminated URL inside url_buf */
+    size_t url_len;
+} FFmpegRsDashSegment;
+
+typedef struct FFmpegRsDashExpandResult {
+    size_t n_segments_total;
+    size_t n_segments_written;
+    int truncated;
+    size_t url_required; /* url_buf size needed for the records written */
+    /* Availability window (dynamic) or period range (static, end -1 if
+     * unknown) on the presentation timeline. */
+    int64_t window_start_us;
+    int64_t window_end_us;
+} FFmpegRsDashExpandResult;
+
+/* Expand one template ($$, $RepresentationID$, $Number$, $Bandwidth$,
+ * $Time$, with optional %0<width>d). Same buffer convention as
+ * ffmpeg_rs_dash_unescape(). */
+int ffmpeg_rs_dash_expand_template(const char *tmpl, size_t tmpl_len,
+                                   const FFmpegRsDashTemplateVars *vars,
+                                   char *dst, size_t dst_len, size_t *out_required);
+
+/* List the media segments of a Representation. Up to `segments_cap` records
+ * are written (NULL/0 for a count-only pass) and their URLs stored in
+ * `url_buf`. For dynamic MPDs only segments inside the availability window
+ * are listed. Returns 0, -EINVAL (also for times that overflow once
+ * scaled by the timescale), or -ENOSPC if `url_buf` is smaller than
+ * `out->url_required` (records are still written). */
+int ffmpeg_rs_dash_expand_segments(const FFmpegRsDashSegmentSource *source,
+                                   FFmpegRsDashSegment *segments, size_t segments_cap,
+                                   char *url_buf, size_t url_buf_len,
+                                   FFmpegRsDashExpandResult *out);
+
+/*
+ * ISO 8601 durations (PnYnMnWnDTnHnMnS, fractional last component; years
+ * and months count as 365 and 30 days) and xs:dateTime values
+ * ([-]YYYY-MM-DDThh:mm:ss[.s][Z|+hh:mm|-hh:mm], no zone = UTC).
+ * Return 0 and store microseconds (date-times: since the Unix epoch), or
+ * -EINVAL on malformed/out-of-range input leaving *out_us untouched.
+ */
+int ffmpeg_rs_dash_parse_duration(const char *s, size_t len, int64_t *out_us);
+int ffmpeg_rs_dash_parse_datetime(const char *s, size_t len, int64_t *out_us);
+
+/*
+ * MPD writer. Strings are (pointer, length) pairs and a zero length means
+ * absent; optional numbers are omitted when -1 (or 0 where noted).
+ */
+typedef struct FFmpegRsDashMpdSegmentTemplate {
+    int64_t timescale;
+    int64_t duration;                 /* 0 to omit */
+    int64_t start_number;
+    int64_t presentation_time_offset; /* 0 to omit */
+    const char *initialization;
+    size_t initialization_len;
+    const char *media;
+    size_t media_len;
+    const FFmpegRsDashTimelineEntry *timeline; /* S@t -1 and S@r 0 omitted */
+    size_t n_timeline;
+} FFmpegRsDashMpdSegmentTemplate;
+
+typedef struct FFmpegRsDashMpdRepresentation {
+    const char *id;
+    size_t id_len;
+    const char *mime_type;
+    size_t mime_type_len;
+    const char *codecs;
+    size_t codecs_len;
+    int64_t bandwidth;
+    /* Video; 0 to omit. */
+    int64_t width, height;
+    int64_t frame_rate_num, frame_rate_den;
+    int64_t sar_num, sar_den;
+    /* Audio; 0 to omit. */
+    int64_t audio_sampling_rate;
+    int64_t audio_channels;
+    const char *base_url;
+    size_t base_url_len;
+    const FFmpegRsDashMpdSegmentTemplate *segment_template; /* may be NULL */
+} FFmpegRsDashMpdRepresentation;
+
+typedef struct FFmpegRsDashMpdAdaptationSet {
+    int64_t id;
+    const char *content_type;
+    size_t content_type_len;
+    const char *mime_type;
+    size_t mime_type_len;
+    const char *lang;
+    size_t lang_len;
+    int segment_alignment;
+    /* 0 to omit. */
+    int64_t max_width, max_height;
+    int64_t max_frame_rate_num, max_frame_rate_den;
+    int64_t par_num, par_den;
+    const FFmpegRsDashMpdSegmentTemplate *segment_template; /* may be NULL */
+    const FFmpegRsDashMpdRepresentation *representations;
+    size_t n_representations;
+} FFmpegRsDashMpdAdaptationSet;
+
+typedef struct FFmpegRsDashMpdPeriod {
+    const char *id;
+    size_t id_len;
+    int64_t start_us;
+    int64_t duration_us;
+    const char *base_url;
+    size_t base_url_len;
+    const FFmpegRsDashMpdAdaptationSet *adaptation_sets;
+    size_t n_adaptation_sets;
+} FFmpegRsDashMpdPeriod;
+
+typedef struct FFmpegRsDashMpdUtcTiming {
+    const char *scheme_id_uri;
+    size_t scheme_id_uri_len;
+    const char *value;
+    size_t value_len;
+} FFmpegRsDashMpdUtcTiming;
+
+typedef struct FFmpegRsDashMpd {
+    int is_dynamic;
+    const char *profiles; /* default urn:mpeg:dash:profile:isoff-live:2011 */
+    size_t profiles_len;
+    int64_t media_presentation_duration_us;
+    int64_t min_buffer_time_us; /* required */
+    int64_t max_segment_duration_us;
+    /* Dynamic only; wall-clock values in us since the epoch,
+     * availability_start_us is required. */
+    int64_t availability_start_us;
+    int64_t publish_time_us;
+    int64_t minimum_update_period_us;
+    int64_t suggested_presentation_delay_us;
+    int64_t time_shift_buffer_depth_us;
+    /* ProgramInformation, written if any field is present. */
+    const char *title;
+    size_t title_len;
+    const char *source;
+    size_t source_len;
+    const char *copyright;
+    size_t copyright_len;
+    const char *more_information_url;
+    size_t more_information_url_len;
+    const char *base_url;
+    size_t base_url_len;
+    const FFmpegRsDashMpdPeriod *periods;
+    size_t n_periods;
+    const FFmpegRsDashMpdUtcTiming *utc_timings;
+    size_t n_utc_timings;
+} FFmpegRsDashMpd;
+
+/* Render an MPD. Same buffer convention as ffmpeg_rs_dash_unescape();
+ * -EINVAL also covers missing mandatory fields, live-only attributes in a
+ * static MPD and control characters in strings. */
+int ffmpeg_rs_dash_write_mpd(const FFmpegRsDashMpd *mpd,
+                             char *dst, size_t dst_len, size_t *out_required);
+
+/* ContentProtection: PSSH boxes and DRM system IDs. */
+
+typedef enum FFmpegRsDashSystem {
+    FFMPEG_RS_DASH_SYSTEM_UNKNOWN = 0,
+    FFMPEG_RS_DASH_SYSTEM_WIDEVINE = 1,
+    FFMPEG_RS_DASH_SYSTEM_PLAYREADY = 2,
+    FFMPEG_RS_DASH_SYSTEM_FAIRPLAY = 3,
+    FFMPEG_RS_DASH_SYSTEM_CLEARKEY = 4,
+    /* W3C common PSSH box format. */
+    FFMPEG_RS_DASH_SYSTEM_COMMON = 5,
+} FFmpegRsDashSystem;
+
+typedef struct FFmpegRsDashPssh {
+    uint32_t version;
+    uint32_t flags;
+    uint8_t system_id[16];
+    uint32_t system; /* FFmpegRsDashSystem */
+    /* Version 1 boxes: n_kids 16-byte KIDs at kids_offset in the decoded
+     * buffer. */
+    size_t n_kids;
+    size_t kids_offset;
+    size_t data_offset;
+    size_t data_len;
+    /* Size of the box, which may be followed by further boxes. */
+    size_t box_size;
+} FFmpegRsDashPssh;
+
+/* Decode a base64 cenc:pssh value into buf and parse the PSSH box at its
+ * start. *out_required receives the decoded size; offsets in *out point into
+ * buf. Returns 0, -EINVAL on bad base64 or a malformed box, or -ENOSPC if
+ * buf_len is too small (buf may be NULL to query). */
+int ffmpeg_rs_dash_decode_pssh(const char *b64, size_t b64_len,
+                               uint8_t *buf, size_t buf_len,
+                               size_t *out_required, FFmpegRsDashPssh *out);
+
+/* Parse a UUID such as a cenc:default_KID value (dashed or plain hex, any
+ * case, optional braces) into 16 bytes. Returns 0 or -EINVAL. */
+int ffmpeg_rs_dash_parse_uuid(const char *s, size_t len, uint8_t out[16]);
+
+/* Write the canonical lower-case 8-4-4-4-12 form of a UUID. Same buffer
+ * convention as ffmpeg_rs_dash_unescape(). */
+int ffmpeg_rs_dash_format_uuid(const uint8_t uuid[16],
+                               char *dst, size_t dst_len, size_t *out_required);
+
+/* Identify the DRM system of a urn:uuid: schemeIdUri and store the UUID in
+ * out_uuid (may be NULL). Returns an FFmpegRsDashSystem value or -EINVAL if
+ * the URI is not a UUID URN. */
+int ffmpeg_rs_dash_system_from_scheme(const char *uri, size_t len,
+                                      uint8_t *out_uuid);
+
+#ifdef __cplusplus
+}
+#endif
@@ -0,0 +1,21 @@
+#![no_std]
+
+mod manifest;
+mod mpd;
+mod protection;
+mod template;
+mod time;
+mod writer;
+mod xml;
+
+pub use manifest::*;
+pub use mpd::*;
+pub use protection::*;
+pub use template::*;
+pub use time::*;
+
+#[cfg(not(test))]
+#[panic_handler]
+fn panic_handler(_: &core::panic::PanicInfo) -> ! {
+    loop {}
+}
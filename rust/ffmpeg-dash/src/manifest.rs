@@ -0,0 +1,759 @@
+use core::ffi::{c_char, c_int};
+
+use crate::template::FFmpegRsDashTimelineEntry;
+use crate::time::civil_from_days;
+use crate::writer::{in_array, in_bytes, in_ref, out_buf, Writer, EINVAL};
+
+const DEFAULT_PROFILE: &[u8] = b"urn:mpeg:dash:profile:isoff-live:2011";
+const CHANNEL_CONFIG_SCHEME: &[u8] = b"urn:mpeg:dash:23003:3:audio_channel_configuration:2011";
+
+// Strings are (pointer, length) pairs; a zero length means "absent".
+// Optional numbers use -1 (or 0 where noted) for "absent".
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsDashMpdSegmentTemplate {
+    pub timescale: i64,
+    /// 0 to omit (timeline-based templates).
+    pub duration: i64,
+    pub start_number: i64,
+    /// 0 to omit.
+    pub presentation_time_offset: i64,
+    pub initialization: *const c_char,
+    pub initialization_len: usize,
+    pub media: *const c_char,
+    pub media_len: usize,
+    /// `S@t` -1 and `S@r` 0 are omitted.
+    pub timeline: *const FFmpegRsDashTimelineEntry,
+    pub n_timeline: usize,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsDashMpdRepresentation {
+    pub id: *const c_char,
+    pub id_len: usize,
+    pub mime_type: *const c_char,
+    pub mime_type_len: usize,
+    pub codecs: *const c_char,
+    pub codecs_len: usize,
+    pub bandwidth: i64,
+    /// Video; 0 to omit.
+    pub width: i64,
+    pub height: i64,
+    pub frame_rate_num: i64,
+    pub frame_rate_den: i64,
+    pub sar_num: i64,
+    pub sar_den: i64,
+    /// Audio; 0 to omit.
+    pub audio_sampling_rate: i64,
+    pub audio_channels: i64,
+    pub base_url: *const c_char,
+    pub base_url_len: usize,
+    /// Optional (NULL), overrides the adaptation set one.
+    pub segment_template: *const FFmpegRsDashMpdSegmentTemplate,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsDashMpdAdaptationSet {
+    pub id: i64,
+    pub content_type: *const c_char,
+    pub content_type_len: usize,
+    pub mime_type: *const c_char,
+    pub mime_type_len: usize,
+    pub lang: *const c_char,
+    pub lang_len: usize,
+    pub segment_alignment: c_int,
+    /// 0 to omit.
+    pub max_width: i64,
+    pub max_height: i64,
+    pub max_frame_rate_num: i64,
+    pub max_frame_rate_den: i64,
+    pub par_num: i64,
+    pub par_den: i64,
+    pub segment_template: *const FFmpegRsDashMpdSegmentTemplate,
+    pub representations: *const FFmpegRsDashMpdRepresentation,
+    pub n_representations: usize,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsDashMpdPeriod {
+    pub id: *const c_char,
+    pub id_len: usize,
+    pub start_us: i64,
+    pub duration_us: i64,
+    pub base_url: *const c_char,
+    pub base_url_len: usize,
+    pub adaptation_sets: *const FFmpegRsDashMpdAdaptationSet,
+    pub n_adaptation_sets: usize,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsDashMpdUtcTiming {
+    pub scheme_id_uri: *const c_char,
+    pub scheme_id_uri_len: usize,
+    pub value: *const c_char,
+    pub value_len: usize,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsDashMpd {
+    pub is_dynamic: c_int,
+    /// Comma-separated; defaults to the ISO live profile.
+    pub profiles: *const c_char,
+    pub profiles_len: usize,
+    pub media_presentation_duration_us: i64,
+    pub min_buffer_time_us: i64,
+    pub max_segment_duration_us: i64,
+    /// Dynamic only. Wall-clock values are µs since the epoch;
+    /// `availability_start_us` is required.
+    pub availability_start_us: i64,
+    pub publish_time_us: i64,
+    pub minimum_update_period_us: i64,
+    pub suggested_presentation_delay_us: i64,
+    pub time_shift_buffer_depth_us: i64,
+    /// ProgramInformation, written if any field is present.
+    pub title: *const c_char,
+    pub title_len: usize,
+    pub source: *const c_char,
+    pub source_len: usize,
+    pub copyright: *const c_char,
+    pub copyright_len: usize,
+    pub more_information_url: *const c_char,
+    pub more_information_url_len: usize,
+    pub base_url: *const c_char,
+    pub base_url_len: usize,
+    pub periods: *const FFmpegRsDashMpdPeriod,
+    pub n_periods: usize,
+    pub utc_timings: *const FFmpegRsDashMpdUtcTiming,
+    pub n_utc_timings: usize,
+}
+
+fn opt_str<'a>(p: *const c_char, len: usize) -> Result<Option<&'a [u8]>, c_int> {
+    if len == 0 {
+        return Ok(None);
+    }
+    in_bytes(p as *const u8, len).map(Some).ok_or(EINVAL)
+}
+
+fn indent(w: &mut Writer, level: usize) {
+    for _ in 0..level {
+        w.byte(b'\t');
+    }
+}
+
+// XML character data; attribute values additionally escape quotes and
+// whitespace that attribute normalization would fold.
+fn escaped(w: &mut Writer, s: &[u8], in_attr: bool) -> Result<(), c_int> {
+    for &b in s {
+        match b {
+            b'&' => w.bytes(b"&amp;"),
+            b'<' => w.bytes(b"&lt;"),
+            b'>' => w.bytes(b"&gt;"),
+            b'"' if in_attr => w.bytes(b"&quot;"),
+            b'\'' if in_attr => w.bytes(b"&apos;"),
+            b'\t' if in_attr => w.bytes(b"&#9;"),
+            b'\n' if in_attr => w.bytes(b"&#10;"),
+            b'\r' => w.bytes(b"&#13;"),
+            b'\t' | b'\n' => w.byte(b),
+            0..=0x1F | 0x7F => return Err(EINVAL),
+            _ => w.byte(b),
+        }
+    }
+    Ok(())
+}
+
+fn attr(w: &mut Writer, name: &[u8], value: &[u8]) -> Result<(), c_int> {
+    w.byte(b' ');
+    w.bytes(name);
+    w.bytes(b"=\"");
+    escaped(w, value, true)?;
+    w.byte(b'"');
+    Ok(())
+}
+
+fn attr_opt(w: &mut Writer, name: &[u8], p: *const c_char, len: usize) -> Result<(), c_int> {
+    match opt_str(p, len)? {
+        Some(v) => attr(w, name, v),
+        None => Ok(()),
+    }
+}
+
+fn attr_uint(w: &mut Writer, name: &[u8], v: i64) {
+    w.byte(b' ');
+    w.bytes(name);
+    w.bytes(b"=\"");
+    w.uint(v as u64);
+    w.byte(b'"');
+}
+
+fn attr_ratio(w: &mut Writer, name: &[u8], num: i64, den: i64, sep: u8) -> Result<(), c_int> {
+    if num == 0 && den == 0 {
+        return Ok(());
+    }
+    if num <= 0 || den <= 0 {
+        return Err(EINVAL);
+    }
+    w.byte(b' ');
+    w.bytes(name);
+    w.bytes(b"=\"");
+    w.uint(num as u64);
+    w.byte(sep);
+    w.uint(den as u64);
+    w.byte(b'"');
+    Ok(())
+}
+
+// `PT[nH][nM]s.fS` like dashenc's write_time(), with the fraction kept to µs
+// precision and at least one digit.
+fn duration(w: &mut Writer, us: i64) -> Result<(), c_int> {
+    if us < 0 {
+        return Err(EINVAL);
+    }
+    let us = us as u64;
+    let secs = us / 1_000_000;
+    let (hours, minutes) = (secs / 3600, secs / 60 % 60);
+    w.bytes(b"PT");
+    if hours != 0 {
+        w.uint(hours);
+        w.byte(b'H');
+    }
+    if hours != 0 || minutes != 0 {
+        w.uint(minutes);
+        w.byte(b'M');
+    }
+    w.uint(secs % 60);
+    w.byte(b'.');
+    let mut frac = us % 1_000_000;
+    let mut width = 6;
+    while width > 1 && frac.is_multiple_of(10) {
+        frac /= 10;
+        width -= 1;
+    }
+    w.uint_padded(frac, width);
+    w.byte(b'S');
+    Ok(())
+}
+
+// `YYYY-MM-DDThh:mm:ss.sssZ` like dashenc's format_date().
+fn datetime(w: &mut Writer, us: i64) -> Result<(), c_int> {
+    let ms = us.div_euclid(1000);
+    let secs = ms.div_euclid(1000);
+    let (y, m, d) = civil_from_days(secs.div_euclid(86_400));
+    if !(0..=9999).contains(&y) {
+        return Err(EINVAL);
+    }
+    let tod = secs.rem_euclid(86_400) as u64;
+    w.uint_padded(y as u64, 4);
+    w.byte(b'-');
+    w.uint_padded(m as u64, 2);
+    w.byte(b'-');
+    w.uint_padded(d as u64, 2);
+    w.byte(b'T');
+    w.uint_padded(tod / 3600, 2);
+    w.byte(b':');
+    w.uint_padded(tod / 60 % 60, 2);
+    w.byte(b':');
+    w.uint_padded(tod % 60, 2);
+    w.byte(b'.');
+    w.uint_padded(ms.rem_euclid(1000) as u64, 3);
+    w.byte(b'Z');
+    Ok(())
+}
+
+fn header_attr(w: &mut Writer, name: &[u8], us: i64, f: fn(&mut Writer, i64) -> Result<(), c_int>) -> Result<(), c_int> {
+    w.bytes(b"\n\t");
+    w.bytes(name);
+    w.bytes(b"=\"");
+    f(w, us)?;
+    w.byte(b'"');
+    Ok(())
+}
+
+fn text_element(w: &mut Writer, level: usize, name: &[u8], p: *const c_char, len: usize) -> Result<(), c_int> {
+    if let Some(v) = opt_str(p, len)? {
+        indent(w, level);
+        w.byte(b'<');
+        w.bytes(name);
+        w.byte(b'>');
+        escaped(w, v, false)?;
+        w.bytes(b"</");
+        w.bytes(name);
+        w.bytes(b">\n");
+    }
+    Ok(())
+}
+
+fn write_template(w: &mut Writer, level: usize, t: &FFmpegRsDashMpdSegmentTemplate) -> Result<(), c_int> {
+    if t.timescale <= 0 || t.duration < 0 || t.presentation_time_offset < 0 {
+        return Err(EINVAL);
+    }
+    let timeline = in_array(t.timeline, t.n_timeline).ok_or(EINVAL)?;
+    indent(w, level);
+    w.bytes(b"<SegmentTemplate");
+    attr_uint(w, b"timescale", t.timescale);
+    if t.duration > 0 {
+        attr_uint(w, b"duration", t.duration);
+    }
+    attr_opt(w, b"initialization", t.initialization, t.initialization_len)?;
+    attr_opt(w, b"media", t.media, t.media_len)?;
+    if t.start_number >= 0 {
+        attr_uint(w, b"startNumber", t.start_number);
+    }
+    if t.presentation_time_offset > 0 {
+        attr_uint(w, b"presentationTimeOffset", t.presentation_time_offset);
+    }
+    if timeline.is_empty() {
+        w.bytes(b"/>\n");
+        return Ok(());
+    }
+    w.bytes(b">\n");
+    indent(w, level + 1);
+    w.bytes(b"<SegmentTimeline>\n");
+    for s in timeline {
+        if s.d <= 0 || s.r < -1 || s.t < -1 {
+            return Err(EINVAL);
+        }
+        indent(w, level + 2);
+        w.bytes(b"<S");
+        if s.t >= 0 {
+            attr_uint(w, b"t", s.t);
+        }
+        attr_uint(w, b"d", s.d);
+        match s.r {
+            0 => {}
+            -1 => w.bytes(b" r=\"-1\""),
+            r => attr_uint(w, b"r", r),
+        }
+        w.bytes(b"/>\n");
+    }
+    indent(w, level + 1);
+    w.bytes(b"</SegmentTimeline>\n");
+    indent(w, level);
+    w.bytes(b"</SegmentTemplate>\n");
+    Ok(())
+}
+
+fn write_representation(w: &mut Writer, r: &FFmpegRsDashMpdRepresentation) -> Result<(), c_int> {
+    let id = opt_str(r.id, r.id_len)?.ok_or(EINVAL)?;
+    if r.bandwidth <= 0 || r.width < 0 || r.height < 0 || r.audio_sampling_rate < 0 || r.audio_channels < 0 {
+        return Err(EINVAL);
+    }
+    w.bytes(b"\t\t\t<Representation");
+    attr(w, b"id", id)?;
+    attr_opt(w, b"mimeType", r.mime_type, r.mime_type_len)?;
+    attr_opt(w, b"codecs", r.codecs, r.codecs_len)?;
+    attr_uint(w, b"bandwidth", r.bandwidth);
+    if r.width > 0 {
+        attr_uint(w, b"width", r.width);
+    }
+    if r.height > 0 {
+        attr_uint(w, b"height", r.height);
+    }
+    attr_ratio(w, b"frameRate", r.frame_rate_num, r.frame_rate_den, b'/')?;
+    attr_ratio(w, b"sar", r.sar_num, r.sar_den, b':')?;
+    if r.audio_sampling_rate > 0 {
+        attr_uint(w, b"audioSamplingRate", r.audio_sampling_rate);
+    }
+    w.bytes(b">\n");
+    if r.audio_channels > 0 {
+        w.bytes(b"\t\t\t\t<AudioChannelConfiguration");
+        attr(w, b"schemeIdUri", CHANNEL_CONFIG_SCHEME)?;
+        attr_uint(w, b"value", r.audio_channels);
+        w.bytes(b"/>\n");
+    }
+    text_element(w, 4, b"BaseURL", r.base_url, r.base_url_len)?;
+    if let Some(t) = in_ref(r.segment_template) {
+        write_template(w, 4, t)?;
+    }
+    w.bytes(b"\t\t\t</Representation>\n");
+    Ok(())
+}
+
+fn write_adaptation_set(w: &mut Writer, a: &FFmpegRsDashMpdAdaptationSet) -> Result<(), c_int> {
+    let reps = in_array(a.representations, a.n_representations).ok_or(EINVAL)?;
+    if reps.is_empty() || a.max_width < 0 || a.max_height < 0 {
+        return Err(EINVAL);
+    }
+    w.bytes(b"\t\t<AdaptationSet");
+    if a.id >= 0 {
+        attr_uint(w, b"id", a.id);
+    }
+    attr_opt(w, b"contentType", a.content_type, a.content_type_len)?;
+    attr_opt(w, b"mimeType", a.mime_type, a.mime_type_len)?;
+    if a.segment_alignment != 0 {
+        w.bytes(b" segmentAlignment=\"true\"");
+    }
+    if a.max_width > 0 {
+        attr_uint(w, b"maxWidth", a.max_width);
+    }
+    if a.max_height > 0 {
+        attr_uint(w, b"maxHeight", a.max_height);
+    }
+    attr_ratio(w, b"maxFrameRate", a.max_frame_rate_num, a.max_frame_rate_den, b'/')?;
+    attr_ratio(w, b"par", a.par_num, a.par_den, b':')?;
+    attr_opt(w, b"lang", a.lang, a.lang_len)?;
+    w.bytes(b">\n");
+    if let Some(t) = in_ref(a.segment_template) {
+        write_template(w, 3, t)?;
+    }
+    for r in reps {
+        write_representation(w, r)?;
+    }
+    w.bytes(b"\t\t</AdaptationSet>\n");
+    Ok(())
+}
+
+fn write_period(w: &mut Writer, p: &FFmpegRsDashMpdPeriod) -> Result<(), c_int> {
+    let sets = in_array(p.adaptation_sets, p.n_adaptation_sets).ok_or(EINVAL)?;
+    w.bytes(b"\t<Period");
+    attr_opt(w, b"id", p.id, p.id_len)?;
+    if p.start_us >= 0 {
+        w.bytes(b" start=\"");
+        duration(w, p.start_us)?;
+        w.byte(b'"');
+    }
+    if p.duration_us >= 0 {
+        w.bytes(b" duration=\"");
+        duration(w, p.duration_us)?;
+        w.byte(b'"');
+    }
+    w.bytes(b">\n");
+    text_element(w, 2, b"BaseURL", p.base_url, p.base_url_len)?;
+    for a in sets {
+        write_adaptation_set(w, a)?;
+    }
+    w.bytes(b"\t</Period>\n");
+    Ok(())
+}
+
+fn write_mpd(w: &mut Writer, m: &FFmpegRsDashMpd) -> Result<(), c_int> {
+    let periods = in_array(m.periods, m.n_periods).ok_or(EINVAL)?;
+    let timings = in_array(m.utc_timings, m.n_utc_timings).ok_or(EINVAL)?;
+    let dynamic = m.is_dynamic != 0;
+    if periods.is_empty() || m.min_buffer_time_us < 0 {
+        return Err(EINVAL);
+    }
+    // The live-only attributes are meaningless in a static MPD.
+    if !dynamic && m.minimum_update_period_us >= 0 {
+        return Err(EINVAL);
+    }
+    if dynamic && m.availability_start_us < 0 {
+        return Err(EINVAL);
+    }
+
+    w.bytes(b"<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    w.bytes(b"<MPD xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\"\n\
+\txmlns=\"urn:mpeg:dash:schema:mpd:2011\"\n\
+\txmlns:xlink=\"http://www.w3.org/1999/xlink\"\n\
+\txsi:schemaLocation=\"urn:mpeg:DASH:schema:MPD:2011 http://standards.iso.org/ittf/PubliclyAvailableStandards/MPEG-DASH_schema_files/DASH-MPD.xsd\"\n\
+\tprofiles=\"");
+    escaped(w, opt_str(m.profiles, m.profiles_len)?.unwrap_or(DEFAULT_PROFILE), true)?;
+    w.bytes(if dynamic { b"\"\n\ttype=\"dynamic\"" } else { b"\"\n\ttype=\"static\"" });
+    if m.media_presentation_duration_us >= 0 {
+        header_attr(w, b"mediaPresentationDuration", m.media_presentation_duration_us, duration)?;
+    }
+    if dynamic {
+        if m.minimum_update_period_us >= 0 {
+            header_attr(w, b"minimumUpdatePeriod", m.minimum_update_period_us, duration)?;
+        }
+        if m.suggested_presentation_delay_us >= 0 {
+            header_attr(w, b"suggestedPresentationDelay", m.suggested_presentation_delay_us, duration)?;
+        }
+        header_attr(w, b"availabilityStartTime", m.availability_start_us, datetime)?;
+        if m.publish_time_us >= 0 {
+            header_attr(w, b"publishTime", m.publish_time_us, datetime)?;
+        }
+        if m.time_shift_buffer_depth_us >= 0 {
+            header_attr(w, b"timeShiftBufferDepth", m.time_shift_buffer_depth_us, duration)?;
+        }
+    }
+    if m.max_segment_duration_us >= 0 {
+        header_attr(w, b"maxSegmentDuration", m.max_segment_duration_us, duration)?;
+    }
+    header_attr(w, b"minBufferTime", m.min_buffer_time_us, duration)?;
+    w.bytes(b">\n");
+
+    if m.title_len + m.source_len + m.copyright_len + m.more_information_url_len != 0 {
+        w.bytes(b"\t<ProgramInformation");
+        attr_opt(w, b"moreInformationURL", m.more_information_url, m.more_information_url_len)?;
+        w.bytes(b">\n");
+        text_element(w, 2, b"Title", m.title, m.title_len)?;
+        text_element(w, 2, b"Source", m.source, m.source_len)?;
+        text_element(w, 2, b"Copyright", m.copyright, m.copyright_len)?;
+        w.bytes(b"\t</ProgramInformation>\n");
+    }
+    text_element(w, 1, b"BaseURL", m.base_url, m.base_url_len)?;
+    for p in periods {
+        write_period(w, p)?;
+    }
+    for t in timings {
+        let scheme = opt_str(t.scheme_id_uri, t.scheme_id_uri_len)?.ok_or(EINVAL)?;
+        w.bytes(b"\t<UTCTiming");
+        attr(w, b"schemeIdUri", scheme)?;
+        attr_opt(w, b"value", t.value, t.value_len)?;
+        w.bytes(b"/>\n");
+    }
+    w.bytes(b"</MPD>\n");
+    Ok(())
+}
+
+// Renders an MPD. Returns 0 on success, -EINVAL on an invalid description
+// (missing mandatory field, live-only attribute in a static MPD, control
+// character in a string), -ENOSPC if `dst_len` is smaller than
+// `*out_required` (size including NUL).
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_dash_write_mpd(
+    mpd: *const FFmpegRsDashMpd,
+    dst: *mut c_char,
+    dst_len: usize,
+    out_required: *mut usize,
+) -> c_int {
+    let m = match in_ref(mpd) {
+        Some(m) => m,
+        None => return EINVAL,
+    };
+    let mut w = Writer::new(out_buf(dst, dst_len));
+    if let Err(e) = write_mpd(&mut w, m) {
+        return e;
+    }
+    w.finish(out_required)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::ptr::null;
+
+    macro_rules! s {
+        ($v:expr) => {
+            ($v.as_ptr() as *const c_char, $v.len())
+        };
+    }
+
+    const NONE: (*const c_char, usize) = (null(), 0);
+
+    fn template(timeline: &[FFmpegRsDashTimelineEntry]) -> FFmpegRsDashMpdSegmentTemplate {
+        let (init, init_len) = s!(b"init-$RepresentationID$.m4s");
+        let (media, media_len) = s!(b"chunk-$RepresentationID$-$Number%05d$.m4s");
+        FFmpegRsDashMpdSegmentTemplate {
+            timescale: 1000,
+            duration: if timeline.is_empty() { 4000 } else { 0 },
+            start_number: 1,
+            presentation_time_offset: 0,
+            initialization: init,
+            initialization_len: init_len,
+            media,
+            media_len,
+            timeline: timeline.as_ptr(),
+            n_timeline: timeline.len(),
+        }
+    }
+
+    fn representation(id: &'static [u8]) -> FFmpegRsDashMpdRepresentation {
+        FFmpegRsDashMpdRepresentation {
+            id: s!(id).0,
+            id_len: id.len(),
+            mime_type: NONE.0,
+            mime_type_len: 0,
+            codecs: s!(b"avc1.64001f").0,
+            codecs_len: 11,
+            bandwidth: 800000,
+            width: 1280,
+            height: 720,
+            frame_rate_num: 25,
+            frame_rate_den: 1,
+            sar_num: 1,
+            sar_den: 1,
+            audio_sampling_rate: 0,
+            audio_channels: 0,
+            base_url: NONE.0,
+            base_url_len: 0,
+            segment_template: null(),
+        }
+    }
+
+    fn adaptation_set(t: &FFmpegRsDashMpdSegmentTemplate, reps: &[FFmpegRsDashMpdRepresentation]) -> FFmpegRsDashMpdAdaptationSet {
+        FFmpegRsDashMpdAdaptationSet {
+            id: 0,
+            content_type: s!(b"video").0,
+            content_type_len: 5,
+            mime_type: s!(b"video/mp4").0,
+            mime_type_len: 9,
+            lang: NONE.0,
+            lang_len: 0,
+            segment_alignment: 1,
+            max_width: 0,
+            max_height: 0,
+            max_frame_rate_num: 0,
+            max_frame_rate_den: 0,
+            par_num: 0,
+            par_den: 0,
+            segment_template: t,
+            representations: reps.as_ptr(),
+            n_representations: reps.len(),
+        }
+    }
+
+    fn mpd(periods: &[FFmpegRsDashMpdPeriod]) -> FFmpegRsDashMpd {
+        FFmpegRsDashMpd {
+            is_dynamic: 0,
+            profiles: NONE.0,
+            profiles_len: 0,
+            media_presentation_duration_us: 8_500_000,
+            min_buffer_time_us: 8_000_000,
+            max_segment_duration_us: -1,
+            availability_start_us: -1,
+            publish_time_us: -1,
+            minimum_update_period_us: -1,
+            suggested_presentation_delay_us: -1,
+            time_shift_buffer_depth_us: -1,
+            title: NONE.0,
+            title_len: 0,
+            source: NONE.0,
+            source_len: 0,
+            copyright: NONE.0,
+            copyright_len: 0,
+            more_information_url: NONE.0,
+            more_information_url_len: 0,
+            base_url: NONE.0,
+            base_url_len: 0,
+            periods: periods.as_ptr(),
+            n_periods: periods.len(),
+            utc_timings: null(),
+            n_utc_timings: 0,
+        }
+    }
+
+    fn render(m: &FFmpegRsDashMpd, buf: &mut [u8]) -> Result<usize, c_int> {
+        let mut req = 0usize;
+        match ffmpeg_rs_dash_write_mpd(m, buf.as_mut_ptr() as *mut c_char, buf.len(), &mut req) {
+            0 => Ok(req - 1),
+            e => Err(e),
+        }
+    }
+
+    fn period(sets: &[FFmpegRsDashMpdAdaptationSet]) -> FFmpegRsDashMpdPeriod {
+        FFmpegRsDashMpdPeriod {
+            id: s!(b"0").0,
+            id_len: 1,
+            start_us: 0,
+            duration_us: -1,
+            base_url: NONE.0,
+            base_url_len: 0,
+            adaptation_sets: sets.as_ptr(),
+            n_adaptation_sets: sets.len(),
+        }
+    }
+
+    #[test]
+    fn writes_static_mpd() {
+        let t = template(&[]);
+        let reps = [representation(b"0")];
+        let sets = [adaptation_set(&t, &reps)];
+        let periods = [period(&sets)];
+        let mut m = mpd(&periods);
+        let title = b"Tom & \"Jerry\" <live>";
+        (m.title, m.title_len) = s!(title);
+        let mut buf = [0u8; 2048];
+        let n = render(&m, &mut buf).unwrap();
+        let expect: &[u8] = b"<?xml version=\"1.0\" encoding=\"utf-8\"?>
+<MPD xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\"
+\txmlns=\"urn:mpeg:dash:schema:mpd:2011\"
+\txmlns:xlink=\"http://www.w3.org/1999/xlink\"
+\txsi:schemaLocation=\"urn:mpeg:DASH:schema:MPD:2011 http://standards.iso.org/ittf/PubliclyAvailableStandards/MPEG-DASH_schema_files/DASH-MPD.xsd\"
+\tprofiles=\"urn:mpeg:dash:profile:isoff-live:2011\"
+\ttype=\"static\"
+\tmediaPresentationDuration=\"PT8.5S\"
+\tminBufferTime=\"PT8.0S\">
+\t<ProgramInformation>
+\t\t<Title>Tom &amp; \"Jerry\" &lt;live&gt;</Title>
+\t</ProgramInformation>
+\t<Period id=\"0\" start=\"PT0.0S\">
+\t\t<AdaptationSet id=\"0\" contentType=\"video\" mimeType=\"video/mp4\" segmentAlignment=\"true\">
+\t\t\t<SegmentTemplate timescale=\"1000\" duration=\"4000\" initialization=\"init-$RepresentationID$.m4s\" media=\"chunk-$RepresentationID$-$Number%05d$.m4s\" startNumber=\"1\"/>
+\t\t\t<Representation id=\"0\" codecs=\"avc1.64001f\" bandwidth=\"800000\" width=\"1280\" height=\"720\" frameRate=\"25/1\" sar=\"1:1\">
+\t\t\t</Representation>
+\t\t</AdaptationSet>
+\t</Period>
+</MPD>
+";
+        assert_eq!(core::str::from_utf8(&buf[..n]).unwrap(), core::str::from_utf8(expect).unwrap());
+
+        // Size query, then static MPDs reject live-only attributes.
+        let mut req = 0usize;
+        assert_eq!(ffmpeg_rs_dash_write_mpd(&m, core::ptr::null_mut(), 0, &mut req), crate::writer::ENOSPC);
+        assert_eq!(req, n + 1);
+        m.minimum_update_period_us = 2_000_000;
+        assert_eq!(render(&m, &mut buf), Err(EINVAL));
+    }
+
+    #[test]
+    fn writes_dynamic_mpd() {
+        let tl = [
+            FFmpegRsDashTimelineEntry { t: 0, d: 4000, r: 2 },
+            FFmpegRsDashTimelineEntry { t: -1, d: 3000, r: 0 },
+        ];
+        let t = template(&tl);
+        let mut audio = representation(b"a");
+        (audio.width, audio.height, audio.frame_rate_num, audio.frame_rate_den, audio.sar_num, audio.sar_den) = (0, 0, 0, 0, 0, 0);
+        (audio.codecs, audio.codecs_len) = s!(b"mp4a.40.2");
+        (audio.audio_sampling_rate, audio.audio_channels, audio.bandwidth) = (48000, 2, 128000);
+        (audio.base_url, audio.base_url_len) = s!(b"audio/?a=1&b=2");
+        audio.segment_template = &t;
+        let reps = [audio];
+        let mut set = adaptation_set(&t, &reps);
+        set.segment_template = null();
+        (set.content_type, set.content_type_len) = s!(b"audio");
+        (set.lang, set.lang_len) = s!(b"en");
+        let sets = [set];
+        let periods = [period(&sets)];
+        let timing = [FFmpegRsDashMpdUtcTiming {
+            scheme_id_uri: s!(b"urn:mpeg:dash:utc:http-xsdate:2014").0,
+            scheme_id_uri_len: 34,
+            value: s!(b"https://time.example.com/?iso").0,
+            value_len: 29,
+        }];
+        let mut m = mpd(&periods);
+        m.is_dynamic = 1;
+        m.media_presentation_duration_us = -1;
+        m.min_buffer_time_us = 4_000_000;
+        m.minimum_update_period_us = 3_723_000_000;
+        m.availability_start_us = 1_709_210_096_789_000;
+        m.time_shift_buffer_depth_us = 60_000_000;
+        (m.utc_timings, m.n_utc_timings) = (timing.as_ptr(), 1);
+        let mut buf = [0u8; 4096];
+        let n = render(&m, &mut buf).unwrap();
+        let out = core::str::from_utf8(&buf[..n]).unwrap();
+        let expect_tail = "\ttype=\"dynamic\"
+\tminimumUpdatePeriod=\"PT1H2M3.0S\"
+\tavailabilityStartTime=\"2024-02-29T12:34:56.789Z\"
+\ttimeShiftBufferDepth=\"PT1M0.0S\"
+\tminBufferTime=\"PT4.0S\">
+\t<Period id=\"0\" start=\"PT0.0S\">
+\t\t<AdaptationSet id=\"0\" contentType=\"audio\" mimeType=\"video/mp4\" segmentAlignment=\"true\" lang=\"en\">
+\t\t\t<Representation id=\"a\" codecs=\"mp4a.40.2\" bandwidth=\"128000\" audioSamplingRate=\"48000\">
+\t\t\t\t<AudioChannelConfiguration schemeIdUri=\"urn:mpeg:dash:23003:3:audio_channel_configuration:2011\" value=\"2\"/>
+\t\t\t\t<BaseURL>audio/?a=1&amp;b=2</BaseURL>
+\t\t\t\t<SegmentTemplate timescale=\"1000\" initialization=\"init-$RepresentationID$.m4s\" media=\"chunk-$RepresentationID$-$Number%05d$.m4s\" startNumber=\"1\">
+\t\t\t\t\t<SegmentTimeline>
+\t\t\t\t\t\t<S t=\"0\" d=\"4000\" r=\"2\"/>
+\t\t\t\t\t\t<S d=\"3000\"/>
+\t\t\t\t\t</SegmentTimeline>
+\t\t\t\t</SegmentTemplate>
+\t\t\t</Representation>
+\t\t</AdaptationSet>
+\t</Period>
+\t<UTCTiming schemeIdUri=\"urn:mpeg:dash:utc:http-xsdate:2014\" value=\"https://time.example.com/?iso\"/>
+</MPD>
+";
+        assert!(out.ends_with(expect_tail), "{}", out);
+
+        m.availability_start_us = -1;
+        assert_eq!(render(&m, &mut buf), Err(EINVAL));
+    }
+}
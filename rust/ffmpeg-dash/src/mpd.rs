@@ -0,0 +1,479 @@
+use core::ffi::{c_char, c_int};
+
+use crate::writer::{in_bytes, out_array, out_buf, out_ref, Writer, AVERROR_INVALIDDATA, EINVAL};
+use crate::xml::{attr, local_name, trim, unescape, Token, Tokenizer};
+
+/// Maximum element nesting accepted by the MPD parser.
+const MAX_DEPTH: usize = 32;
+
+#[repr(u32)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum FFmpegRsDashEventKind {
+    Mpd = 0,
+    Period = 1,
+    AdaptationSet = 2,
+    Representation = 3,
+    BaseUrl = 4,
+    SegmentTemplate = 5,
+    SegmentList = 6,
+    SegmentBase = 7,
+    SegmentTimeline = 8,
+    S = 9,
+    SegmentUrl = 10,
+    Initialization = 11,
+    ContentProtection = 12,
+    Pssh = 13,
+    End = 14,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsDashEvent {
+    pub kind: u32,
+    pub line_no: u32,
+    /// Nesting depth of the element, the MPD root being 0.
+    pub depth: u32,
+
+    /// Primary slice into the input: the raw attribute list for element
+    /// events, the trimmed text content for BaseURL and pssh.
+    pub a_offset: usize,
+    pub a_len: usize,
+
+    /// Secondary slice: the attribute list for BaseURL and pssh, the
+    /// schemeIdUri value for ContentProtection.
+    pub b_offset: usize,
+    pub b_len: usize,
+
+    /// Parsed numeric fields (meaning depends on kind, -1 when absent).
+    pub i64_a: i64,
+    pub i64_b: i64,
+    pub i64_c: i64,
+}
+
+#[repr(C)]
+pub struct FFmpegRsDashParseEventsResult {
+    pub n_events_total: usize,
+    pub n_events_written: usize,
+    pub truncated: c_int,
+    /// Line of the first syntax error, 0 if none.
+    pub error_line: u32,
+}
+
+#[derive(Copy, Clone)]
+struct Frame<'a> {
+    name: &'a [u8],
+    attrs: &'a [u8],
+    kind: Option<FFmpegRsDashEventKind>,
+    /// Offset just past the start tag.
+    content_start: usize,
+    line_no: u32,
+}
+
+fn parse_i64(s: &[u8]) -> Option<i64> {
+    let s = trim(s);
+    let (neg, digits) = match s.split_first() {
+        Some((b'-', d)) => (true, d),
+        Some((b'+', d)) => (false, d),
+        _ => (false, s),
+    };
+    if digits.is_empty() {
+        return None;
+    }
+    let mut v = 0i64;
+    for &b in digits {
+        if !b.is_ascii_digit() {
+            return None;
+        }
+        v = v.checked_mul(10)?.checked_add((b - b'0') as i64)?;
+    }
+    Some(if neg { -v } else { v })
+}
+
+fn attr_i64(attrs: &[u8], name: &[u8]) -> i64 {
+    attr(attrs, name).and_then(parse_i64).unwrap_or(-1)
+}
+
+// Maps an element to its event kind given the kind of its closest
+// recognized ancestor.
+fn classify(name: &[u8], parent: Option<FFmpegRsDashEventKind>) -> Option<FFmpegRsDashEventKind> {
+    use FFmpegRsDashEventKind as K;
+    let kind = match local_name(name) {
+        b"Period" => K::Period,
+        b"AdaptationSet" => K::AdaptationSet,
+        b"Representation" => K::Representation,
+        b"BaseURL" => K::BaseUrl,
+        b"SegmentTemplate" => K::SegmentTemplate,
+        b"SegmentList" => K::SegmentList,
+        b"SegmentBase" => K::SegmentBase,
+        b"SegmentTimeline" => K::SegmentTimeline,
+        b"S" => K::S,
+        b"SegmentURL" => K::SegmentUrl,
+        b"Initialization" => K::Initialization,
+        b"ContentProtection" => K::ContentProtection,
+        b"pssh" => K::Pssh,
+        _ => return None,
+    };
+    let ok = match kind {
+        K::S => parent == Some(K::SegmentTimeline),
+        K::SegmentUrl => parent == Some(K::SegmentList),
+        K::Initialization => matches!(parent, Some(K::SegmentBase | K::SegmentList | K::SegmentTemplate)),
+        K::Pssh => parent == Some(K::ContentProtection),
+        _ => true,
+    };
+    ok.then_some(kind)
+}
+
+// Kinds that open a scope closed by an End event.
+fn is_container(kind: FFmpegRsDashEventKind) -> bool {
+    use FFmpegRsDashEventKind as K;
+    !matches!(kind, K::BaseUrl | K::S | K::SegmentUrl | K::Initialization | K::Pssh)
+}
+
+struct Events<'a> {
+    events: &'a mut [FFmpegRsDashEvent],
+    total: usize,
+}
+
+impl Events<'_> {
+    fn push(&mut self, ev: FFmpegRsDashEvent) {
+        if let Some(slot) = self.events.get_mut(self.total) {
+            *slot = ev;
+        }
+        self.total += 1;
+    }
+}
+
+fn parse_events(data: &[u8], out: &mut Events) -> Result<(), u32> {
+    let base = data.as_ptr() as usize;
+    let span = |s: &[u8]| (s.as_ptr() as usize - base, s.len());
+
+    let mut tok = Tokenizer::new(data);
+    let mut stack = [Frame { name: &[], attrs: &[], kind: None, content_start: 0, line_no: 0 }; MAX_DEPTH];
+    let mut depth = 0usize;
+    let mut seen_root = false;
+
+    while let Some(t) = tok.next_token() {
+        let t = t.map_err(|e| e.line_no)?;
+        let line_no = tok.token_line;
+        match t {
+            Token::Text(s) => {
+                if depth == 0 && !trim(s).is_empty() {
+                    return Err(line_no);
+                }
+            }
+            Token::Start { name, attrs, empty } => {
+                let kind = if depth == 0 {
+                    if seen_root || local_name(name) != b"MPD" {
+                        return Err(line_no);
+                    }
+                    seen_root = true;
+                    Some(FFmpegRsDashEventKind::Mpd)
+                } else {
+                    let parent = stack[..depth].iter().rev().find_map(|f| f.kind);
+                    classify(name, parent)
+                };
+                if depth == MAX_DEPTH {
+                    return Err(line_no);
+                }
+                let content_start = span(attrs).0 + attrs.len();
+                let frame = Frame { name, attrs, kind, content_start, line_no };
+                stack[depth] = frame;
+                start_event(out, &frame, depth as u32, &span);
+                if empty {
+                    end_element(out, &frame, depth as u32, &[], &span);
+                } else {
+                    depth += 1;
+                }
+            }
+            Token::End { name } => {
+                if depth == 0 || stack[depth - 1].name != name {
+                    return Err(line_no);
+                }
+                depth -= 1;
+                let frame = stack[depth];
+                // The tokenizer only yields an end tag once it has consumed
+                // it, so the content ends at its `</`.
+                let end_tag = span(name).0 - 2;
+                let content = data.get(frame.content_start..end_tag).unwrap_or(&[]);
+                // Skip the rest of the start tag (`/`, `>`).
+                let content = match content.iter().position(|&b| b == b'>') {
+                    Some(i) => &content[i + 1..],
+                    None => content,
+                };
+                end_element(out, &frame, depth as u32, content, &span);
+            }
+        }
+    }
+    if depth != 0 || !seen_root {
+        return Err(tok.token_line);
+    }
+    Ok(())
+}
+
+fn event(kind: FFmpegRsDashEventKind, frame: &Frame, depth: u32) -> FFmpegRsDashEvent {
+    FFmpegRsDashEvent {
+        kind: kind as u32,
+        line_no: frame.line_no,
+        depth,
+        a_offset: 0,
+        a_len: 0,
+        b_offset: 0,
+        b_len: 0,
+        i64_a: -1,
+        i64_b: -1,
+        i64_c: -1,
+    }
+}
+
+fn start_event(out: &mut Events, frame: &Frame, depth: u32, span: &dyn Fn(&[u8]) -> (usize, usize)) {
+    use FFmpegRsDashEventKind as K;
+    let kind = match frame.kind {
+        Some(k) if !matches!(k, K::BaseUrl | K::Pssh) => k,
+        _ => return,
+    };
+    let attrs = frame.attrs;
+    let mut ev = event(kind, frame, depth);
+    (ev.a_offset, ev.a_len) = span(attrs);
+    match kind {
+        K::Mpd => ev.i64_a = (attr(attrs, b"type") == Some(b"dynamic")) as i64,
+        K::Representation => ev.i64_a = attr_i64(attrs, b"bandwidth"),
+        K::SegmentTemplate | K::SegmentList | K::SegmentBase => {
+            ev.i64_a = attr_i64(attrs, b"timescale");
+            ev.i64_b = attr_i64(attrs, b"startNumber");
+        }
+        K::S => {
+            ev.i64_a = attr_i64(attrs, b"t");
+            ev.i64_b = attr_i64(attrs, b"d");
+            ev.i64_c = attr(attrs, b"r").and_then(parse_i64).unwrap_or(0);
+        }
+        K::ContentProtection => {
+            if let Some(v) = attr(attrs, b"schemeIdUri") {
+                (ev.b_offset, ev.b_len) = span(v);
+            }
+        }
+        _ => {}
+    }
+    out.push(ev);
+}
+
+fn end_element(out: &mut Events, frame: &Frame, depth: u32, content: &[u8], span: &dyn Fn(&[u8]) -> (usize, usize)) {
+    use FFmpegRsDashEventKind as K;
+    let kind = match frame.kind {
+        Some(k) => k,
+        None => return,
+    };
+    if matches!(kind, K::BaseUrl | K::Pssh) {
+        let mut ev = event(kind, frame, depth);
+        let content = trim(content);
+        if !content.is_empty() {
+            (ev.a_offset, ev.a_len) = span(content);
+        }
+        (ev.b_offset, ev.b_len) = span(frame.attrs);
+        out.push(ev);
+    } else if is_container(kind) {
+        let mut ev = event(K::End, frame, depth);
+        ev.i64_a = kind as i64;
+        out.push(ev);
+    }
+}
+
+// Parses an MPD into a flat stream of element events (see
+// ffmpeg_rs_hls_demux_parse_events() for the buffer conventions).
+// Returns 0 on success, -EINVAL on bad arguments, AVERROR_INVALIDDATA if
+// the document is not well-formed or its root is not MPD; `out->error_line`
+// then holds the line.
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_dash_parse_events(
+    text: *const u8,
+    text_len: usize,
+    out: *mut FFmpegRsDashParseEventsResult,
+    events: *mut FFmpegRsDashEvent,
+    events_cap: usize,
+) -> c_int {
+    let (data, out) = match (in_bytes(text, text_len), out_ref(out)) {
+        (Some(d), Some(o)) => (d, o),
+        _ => return EINVAL,
+    };
+    let mut evs = Events { events: out_array(events, events_cap), total: 0 };
+    let r = parse_events(data, &mut evs);
+    out.n_events_total = evs.total;
+    out.n_events_written = evs.total.min(evs.events.len());
+    out.truncated = (evs.total > evs.events.len()) as c_int;
+    out.error_line = 0;
+    match r {
+        Ok(()) => 0,
+        Err(line_no) => {
+            out.error_line = line_no.max(1);
+            AVERROR_INVALIDDATA
+        }
+    }
+}
+
+// Looks up attribute `name` (qualified, e.g. `cenc:default_KID`) in an
+// attribute list span returned by ffmpeg_rs_dash_parse_events(). Returns 1
+// and the raw value span if found, 0 if absent, -EINVAL on bad arguments.
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_dash_find_attr(
+    text: *const u8,
+    text_len: usize,
+    attrs_offset: usize,
+    attrs_len: usize,
+    name: *const c_char,
+    name_len: usize,
+    out_offset: *mut usize,
+    out_len: *mut usize,
+) -> c_int {
+    let (data, name, out_offset, out_len) = match (
+        in_bytes(text, text_len),
+        in_bytes(name as *const u8, name_len),
+        out_ref(out_offset),
+        out_ref(out_len),
+    ) {
+        (Some(d), Some(n), Some(o), Some(l)) => (d, n, o, l),
+        _ => return EINVAL,
+    };
+    let attrs = match attrs_offset.checked_add(attrs_len).and_then(|end| data.get(attrs_offset..end)) {
+        Some(a) => a,
+        None => return EINVAL,
+    };
+    match attr(attrs, name) {
+        Some(v) => {
+            *out_offset = v.as_ptr() as usize - data.as_ptr() as usize;
+            *out_len = v.len();
+            1
+        }
+        None => 0,
+    }
+}
+
+// Decodes XML character references in an attribute value or text span
+// into `dst` (NUL-terminated). Returns 0 on success, -EINVAL on bad
+// arguments, -ENOSPC if `dst_len` is smaller than `*out_required`.
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_dash_unescape(
+    src: *const u8,
+    src_len: usize,
+    dst: *mut c_char,
+    dst_len: usize,
+    out_required: *mut usize,
+) -> c_int {
+    let src = match in_bytes(src, src_len) {
+        Some(s) => s,
+        None => return EINVAL,
+    };
+    let mut w = Writer::new(out_buf(dst, dst_len));
+    unescape(src, |s| w.bytes(s));
+    w.finish(out_required)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use FFmpegRsDashEventKind as K;
+
+    const MPD: &[u8] = br#"<?xml version="1.0" encoding="UTF-8"?>
+<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" xmlns:cenc="urn:mpeg:cenc:2013" type="dynamic">
+  <BaseURL>http://cdn.example.com/a&amp;b/</BaseURL>
+  <Period id="p0">
+    <AdaptationSet mimeType="video/mp4">
+      <ContentProtection schemeIdUri="urn:mpeg:dash:mp4protection:2011" cenc:default_KID="10000000-1000-1000-1000-100000000001"/>
+      <ContentProtection schemeIdUri="urn:uuid:edef8ba9-79d6-4ace-a3c8-27dcd51d21ed">
+        <cenc:pssh>AAAAOHBzc2g=</cenc:pssh>
+      </ContentProtection>
+      <SegmentTemplate timescale="90000" media="$RepresentationID$/$Time$.m4s" initialization="$RepresentationID$/init.mp4">
+        <SegmentTimeline>
+          <S t="0" d="180000" r="2"/>
+          <S d="90000"/>
+        </SegmentTimeline>
+      </SegmentTemplate>
+      <Representation id="v0" bandwidth="800000"/>
+    </AdaptationSet>
+    <AdaptationSet>
+      <Representation id="a0" bandwidth="64000">
+        <SegmentList duration="2"><Initialization sourceURL="init.mp4"/><SegmentURL media="s1.m4s"/></SegmentList>
+      </Representation>
+    </AdaptationSet>
+  </Period>
+</MPD>
+"#;
+
+    fn parse(text: &[u8], evs: &mut [FFmpegRsDashEvent]) -> (c_int, FFmpegRsDashParseEventsResult) {
+        let mut out = FFmpegRsDashParseEventsResult { n_events_total: 0, n_events_written: 0, truncated: 0, error_line: 0 };
+        let r = ffmpeg_rs_dash_parse_events(text.as_ptr(), text.len(), &mut out, evs.as_mut_ptr(), evs.len());
+        (r, out)
+    }
+
+    fn blank() -> FFmpegRsDashEvent {
+        FFmpegRsDashEvent { kind: 0, line_no: 0, depth: 0, a_offset: 0, a_len: 0, b_offset: 0, b_len: 0, i64_a: 0, i64_b: 0, i64_c: 0 }
+    }
+
+    #[test]
+    fn parses_mpd_events() {
+        let mut evs = [blank(); 64];
+        let (r, out) = parse(MPD, &mut evs);
+        assert_eq!(r, 0);
+        assert_eq!(out.truncated, 0);
+        let evs = &evs[..out.n_events_written];
+        let kinds: [u32; 28] = core::array::from_fn(|i| evs.get(i).map_or(99, |e| e.kind));
+        let expect = [
+            K::Mpd, K::BaseUrl, K::Period, K::AdaptationSet, K::ContentProtection, K::End, K::ContentProtection,
+            K::Pssh, K::End, K::SegmentTemplate, K::SegmentTimeline, K::S, K::S, K::End, K::End,
+            K::Representation, K::End, K::End, K::AdaptationSet, K::Representation, K::SegmentList,
+            K::Initialization, K::SegmentUrl, K::End, K::End, K::End, K::End, K::End,
+        ];
+        assert_eq!(kinds, expect.map(|k| k as u32));
+        assert_eq!(out.n_events_total, 28);
+
+        let text = |off: usize, len: usize| &MPD[off..off + len];
+        assert_eq!(evs[0].i64_a, 1);
+        assert_eq!(evs[1].depth, 1);
+        assert_eq!(text(evs[1].a_offset, evs[1].a_len), b"http://cdn.example.com/a&amp;b/");
+        assert_eq!(text(evs[4].b_offset, evs[4].b_len), b"urn:mpeg:dash:mp4protection:2011");
+        assert_eq!(text(evs[7].a_offset, evs[7].a_len), b"AAAAOHBzc2g=");
+        assert_eq!(evs[9].i64_a, 90000);
+        assert_eq!(evs[9].i64_b, -1);
+        assert_eq!((evs[11].i64_a, evs[11].i64_b, evs[11].i64_c), (0, 180000, 2));
+        assert_eq!((evs[12].i64_a, evs[12].i64_b, evs[12].i64_c), (-1, 90000, 0));
+        assert_eq!(evs[12].line_no, 13);
+        assert_eq!(evs[15].i64_a, 800000);
+        assert_eq!(evs[17].i64_a, K::AdaptationSet as i64);
+        assert_eq!(evs[27].i64_a, K::Mpd as i64);
+
+        let (mut off, mut len) = (0usize, 0usize);
+        let name = b"cenc:default_KID";
+        let r = ffmpeg_rs_dash_find_attr(MPD.as_ptr(), MPD.len(), evs[4].a_offset, evs[4].a_len,
+                                         name.as_ptr() as *const c_char, name.len(), &mut off, &mut len);
+        assert_eq!(r, 1);
+        assert_eq!(text(off, len), b"10000000-1000-1000-1000-100000000001");
+
+        let mut buf = [0i8; 64];
+        let mut req = 0usize;
+        let r = ffmpeg_rs_dash_unescape(MPD[evs[1].a_offset..].as_ptr(), evs[1].a_len, buf.as_mut_ptr(), buf.len(), &mut req);
+        assert_eq!(r, 0);
+        let s = unsafe { core::slice::from_raw_parts(buf.as_ptr() as *const u8, req - 1) };
+        assert_eq!(s, b"http://cdn.example.com/a&b/");
+    }
+
+    #[test]
+    fn size_only_and_errors() {
+        let (r, out) = parse(MPD, &mut []);
+        assert_eq!(r, 0);
+        assert_eq!(out.n_events_total, 28);
+        assert_eq!(out.n_events_written, 0);
+        assert_eq!(out.truncated, 1);
+
+        assert_eq!(parse(b"<Period/>", &mut []).0, AVERROR_INVALIDDATA);
+        assert_eq!(parse(b"<MPD><Period></MPD>", &mut []).0, AVERROR_INVALIDDATA);
+        let (r, out) = parse(b"<MPD>\n<Period>\n</Perio>\n</MPD>", &mut []);
+        assert_eq!((r, out.error_line), (AVERROR_INVALIDDATA, 3));
+        assert_eq!(parse(b"<MPD/><MPD/>", &mut []).0, AVERROR_INVALIDDATA);
+        assert_eq!(parse(b"<MPD>", &mut []).0, AVERROR_INVALIDDATA);
+
+        let mut deep = [0u8; 4 * 40 + 6];
+        deep[..5].copy_from_slice(b"<MPD>");
+        for i in 0..40 {
+            deep[5 + i * 4..9 + i * 4].copy_from_slice(b"<x>\n");
+        }
+        assert_eq!(parse(&deep[..deep.len() - 1], &mut []).0, AVERROR_INVALIDDATA);
+    }
+}
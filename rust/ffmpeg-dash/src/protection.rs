@@ -0,0 +1,386 @@
+use core::ffi::{c_char, c_int};
+
+use crate::writer::{in_bytes, out_array, out_buf, out_ref, Writer, EINVAL, ENOSPC};
+use crate::xml::trim;
+
+const UUID_LEN: usize = 16;
+
+#[repr(u32)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum FFmpegRsDashSystem {
+    Unknown = 0,
+    Widevine = 1,
+    PlayReady = 2,
+    FairPlay = 3,
+    ClearKey = 4,
+    /// W3C common PSSH box format.
+    Common = 5,
+}
+
+const SYSTEMS: [([u8; UUID_LEN], FFmpegRsDashSystem); 5] = [
+    (uuid(b"edef8ba979d64acea3c827dcd51d21ed"), FFmpegRsDashSystem::Widevine),
+    (uuid(b"9a04f07998404286ab92e65be0885f95"), FFmpegRsDashSystem::PlayReady),
+    (uuid(b"94ce86fb07ff4f43adb893d2fa968ca2"), FFmpegRsDashSystem::FairPlay),
+    (uuid(b"e2719d58a985b3c9781ab030af78d30e"), FFmpegRsDashSystem::ClearKey),
+    (uuid(b"1077efecc0b24d02ace33c1e52e2fb4b"), FFmpegRsDashSystem::Common),
+];
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsDashPssh {
+    pub version: u32,
+    pub flags: u32,
+    pub system_id: [u8; UUID_LEN],
+    /// FFmpegRsDashSystem.
+    pub system: u32,
+    /// Version 1 boxes: `n_kids` 16-byte KIDs at `kids_offset` in the
+    /// decoded buffer.
+    pub n_kids: usize,
+    pub kids_offset: usize,
+    pub data_offset: usize,
+    pub data_len: usize,
+    /// Size of the box, which may be followed by further boxes.
+    pub box_size: usize,
+}
+
+const fn hex_val(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
+
+const fn uuid(hex: &[u8; 32]) -> [u8; UUID_LEN] {
+    let mut out = [0u8; UUID_LEN];
+    let mut i = 0;
+    while i < UUID_LEN {
+        let (hi, lo) = match (hex_val(hex[2 * i]), hex_val(hex[2 * i + 1])) {
+            (Some(h), Some(l)) => (h, l),
+            _ => panic!("bad uuid literal"),
+        };
+        out[i] = (hi << 4) | lo;
+        i += 1;
+    }
+    out
+}
+
+fn system_of(id: &[u8; UUID_LEN]) -> FFmpegRsDashSystem {
+    SYSTEMS.iter().find(|(u, _)| u == id).map_or(FFmpegRsDashSystem::Unknown, |&(_, s)| s)
+}
+
+// 32 hex digits, optionally in 8-4-4-4-12 groups and/or braces.
+fn parse_uuid(s: &[u8]) -> Option<[u8; UUID_LEN]> {
+    let s = trim(s);
+    let s = match (s.first(), s.last()) {
+        (Some(b'{'), Some(b'}')) => &s[1..s.len() - 1],
+        _ => s,
+    };
+    let grouped = s.len() == 36;
+    if !grouped && s.len() != 32 {
+        return None;
+    }
+    let mut out = [0u8; UUID_LEN];
+    let mut n = 0usize;
+    for (i, &b) in s.iter().enumerate() {
+        if grouped && matches!(i, 8 | 13 | 18 | 23) {
+            if b != b'-' {
+                return None;
+            }
+            continue;
+        }
+        let v = hex_val(b)?;
+        out[n / 2] |= if n.is_multiple_of(2) { v << 4 } else { v };
+        n += 1;
+    }
+    Some(out)
+}
+
+fn b64_val(b: u8) -> Option<u32> {
+    Some(match b {
+        b'A'..=b'Z' => b - b'A',
+        b'a'..=b'z' => b - b'a' + 26,
+        b'0'..=b'9' => b - b'0' + 52,
+        b'+' | b'-' => 62,
+        b'/' | b'_' => 63,
+        _ => return None,
+    } as u32)
+}
+
+// Standard or URL-safe base64; whitespace is skipped and padding optional.
+fn base64_decode(s: &[u8], mut emit: impl FnMut(u8)) -> Option<usize> {
+    let (mut acc, mut bits, mut n, mut pad) = (0u32, 0u32, 0usize, 0usize);
+    for &b in s {
+        if matches!(b, b' ' | b'\t' | b'\r' | b'\n') {
+            continue;
+        }
+        if b == b'=' {
+            pad += 1;
+            continue;
+        }
+        if pad != 0 {
+            return None;
+        }
+        acc = (acc << 6) | b64_val(b)?;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            emit((acc >> bits) as u8);
+            n += 1;
+        }
+    }
+    // A single leftover sextet cannot encode a byte.
+    if bits >= 6 || pad > 2 {
+        return None;
+    }
+    Some(n)
+}
+
+fn be32(b: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(b.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn parse_pssh(b: &[u8]) -> Option<FFmpegRsDashPssh> {
+    let mut size = be32(b, 0)? as u64;
+    if b.get(4..8)? != b"pssh" {
+        return None;
+    }
+    let mut pos = 8usize;
+    if size == 1 {
+        size = u64::from_be_bytes(b.get(8..16)?.try_into().ok()?);
+        pos = 16;
+    } else if size == 0 {
+        size = b.len() as u64;
+    }
+    let size = usize::try_from(size).ok().filter(|&s| s <= b.len() && s >= pos)?;
+    let b = &b[..size];
+
+    let vf = be32(b, pos)?;
+    let version = vf >> 24;
+    if version > 1 {
+        return None;
+    }
+    pos += 4;
+    let system_id: [u8; UUID_LEN] = b.get(pos..pos + UUID_LEN)?.try_into().ok()?;
+    pos += UUID_LEN;
+    let (mut n_kids, mut kids_offset) = (0usize, 0usize);
+    if version == 1 {
+        n_kids = be32(b, pos)? as usize;
+        pos += 4;
+        kids_offset = pos;
+        pos = pos.checked_add(n_kids.checked_mul(UUID_LEN)?)?;
+        if pos > b.len() {
+            return None;
+        }
+    }
+    let data_len = be32(b, pos)? as usize;
+    pos += 4;
+    if data_len != b.len() - pos {
+        return None;
+    }
+    Some(FFmpegRsDashPssh {
+        version,
+        flags: vf & 0xFF_FFFF,
+        system_id,
+        system: system_of(&system_id) as u32,
+        n_kids,
+        kids_offset,
+        data_offset: pos,
+        data_len,
+        box_size: size,
+    })
+}
+
+// Decodes a base64 `cenc:pssh` value into `buf` and parses the PSSH box at
+// its start. `*out_required` receives the decoded size; offsets in `out`
+// point into `buf`. Returns 0 on success, -EINVAL on bad base64 or a
+// malformed box, -ENOSPC if `buf_len` is too small (pass NULL to query).
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_dash_decode_pssh(
+    b64: *const c_char,
+    b64_len: usize,
+    buf: *mut u8,
+    buf_len: usize,
+    out_required: *mut usize,
+    out: *mut FFmpegRsDashPssh,
+) -> c_int {
+    let (src, out) = match (in_bytes(b64 as *const u8, b64_len), out_ref(out)) {
+        (Some(s), Some(o)) => (s, o),
+        _ => return EINVAL,
+    };
+    let dst = out_array(buf, buf_len);
+    let mut n = 0usize;
+    let decoded = base64_decode(src, |b| {
+        if let Some(slot) = dst.get_mut(n) {
+            *slot = b;
+        }
+        n += 1;
+    });
+    let len = match decoded {
+        Some(len) => len,
+        None => return EINVAL,
+    };
+    if let Some(r) = out_ref(out_required) {
+        *r = len;
+    }
+    if len > dst.len() {
+        return ENOSPC;
+    }
+    match parse_pssh(&dst[..len]) {
+        Some(p) => {
+            *out = p;
+            0
+        }
+        None => EINVAL,
+    }
+}
+
+// Parses a UUID such as a `cenc:default_KID` value (dashed or plain hex,
+// any case, optional braces) into 16 bytes. Returns 0 or -EINVAL.
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_dash_parse_uuid(s: *const c_char, len: usize, out: *mut u8) -> c_int {
+    let s = match in_bytes(s as *const u8, len) {
+        Some(s) => s,
+        None => return EINVAL,
+    };
+    let out = out_array(out, UUID_LEN);
+    if out.len() != UUID_LEN {
+        return EINVAL;
+    }
+    match parse_uuid(s) {
+        Some(u) => {
+            out.copy_from_slice(&u);
+            0
+        }
+        None => EINVAL,
+    }
+}
+
+// Writes the canonical lower-case 8-4-4-4-12 form of a 16-byte UUID. Same
+// size query then fill convention as ffmpeg_rs_dash_unescape().
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_dash_format_uuid(
+    uuid: *const u8,
+    dst: *mut c_char,
+    dst_len: usize,
+    out_required: *mut usize,
+) -> c_int {
+    let uuid = match in_bytes(uuid, UUID_LEN) {
+        Some(u) => u,
+        None => return EINVAL,
+    };
+    let mut w = Writer::new(out_buf(dst, dst_len));
+    for (i, &b) in uuid.iter().enumerate() {
+        if matches!(i, 4 | 6 | 8 | 10) {
+            w.byte(b'-');
+        }
+        w.byte(b"0123456789abcdef"[(b >> 4) as usize]);
+        w.byte(b"0123456789abcdef"[(b & 15) as usize]);
+    }
+    w.finish(out_required)
+}
+
+// Identifies the DRM system of a ContentProtection@schemeIdUri of the form
+// `urn:uuid:<uuid>` and stores the UUID in `out_uuid` (may be NULL).
+// Returns the FFmpegRsDashSystem value (Unknown for unrecognized UUIDs) or
+// -EINVAL if the URI is not a UUID URN.
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_dash_system_from_scheme(uri: *const c_char, len: usize, out_uuid: *mut u8) -> c_int {
+    let uri = match in_bytes(uri as *const u8, len) {
+        Some(u) => trim(u),
+        None => return EINVAL,
+    };
+    const PREFIX: &[u8] = b"urn:uuid:";
+    if uri.len() < PREFIX.len() || !uri[..PREFIX.len()].eq_ignore_ascii_case(PREFIX) {
+        return EINVAL;
+    }
+    let id = match parse_uuid(&uri[PREFIX.len()..]) {
+        Some(id) => id,
+        None => return EINVAL,
+    };
+    let out = out_array(out_uuid, UUID_LEN);
+    if out.len() == UUID_LEN {
+        out.copy_from_slice(&id);
+    }
+    system_of(&id) as c_int
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // v1 Widevine box with two KIDs and 3 bytes of data.
+    const PSSH_V1: &[u8] = b"AAAAR3Bzc2gBAAAA7e+LqXnWSs6jyCfc1R0h7QAAAAIQAAAAAAAAAAAAAAAAAAAAIAAAAAAAAAAAAAAAAAAAAAAAAAMBAgM=";
+
+    fn decode(b64: &[u8], buf: &mut [u8]) -> (c_int, usize, FFmpegRsDashPssh) {
+        let mut out = FFmpegRsDashPssh {
+            version: 9,
+            flags: 0,
+            system_id: [0; 16],
+            system: 0,
+            n_kids: 0,
+            kids_offset: 0,
+            data_offset: 0,
+            data_len: 0,
+            box_size: 0,
+        };
+        let mut req = 0usize;
+        let buf_ptr = if buf.is_empty() { core::ptr::null_mut() } else { buf.as_mut_ptr() };
+        let r = ffmpeg_rs_dash_decode_pssh(b64.as_ptr() as *const c_char, b64.len(), buf_ptr, buf.len(), &mut req, &mut out);
+        (r, req, out)
+    }
+
+    #[test]
+    fn decodes_pssh() {
+        let (r, req, _) = decode(PSSH_V1, &mut []);
+        assert_eq!((r, req), (ENOSPC, 71));
+        let mut buf = [0u8; 128];
+        let (r, _, p) = decode(PSSH_V1, &mut buf);
+        assert_eq!(r, 0);
+        assert_eq!((p.version, p.flags, p.box_size), (1, 0, 71));
+        assert_eq!(p.system, FFmpegRsDashSystem::Widevine as u32);
+        assert_eq!(p.n_kids, 2);
+        assert_eq!(buf[p.kids_offset], 0x10);
+        assert_eq!(buf[p.kids_offset + 16], 0x20);
+        assert_eq!(&buf[p.data_offset..][..p.data_len], &[1, 2, 3]);
+
+        // v0 PlayReady box with empty data, no padding and embedded newline.
+        let (r, _, p) = decode(b"AAAAIHBzc2gAAAAAmgTweZhAQoarkuZb4IhflQ\nAAAAA", &mut buf);
+        assert_eq!(r, 0);
+        assert_eq!((p.version, p.n_kids, p.data_len), (0, 0, 0));
+        assert_eq!(p.system, FFmpegRsDashSystem::PlayReady as u32);
+
+        assert_eq!(decode(b"AAAAIHBzc2gAAAAAmgTweZhAQoarkuZb4IhflQAAAAE=", &mut buf).0, EINVAL);
+        assert_eq!(decode(b"AAAAIHBzc2g*", &mut buf).0, EINVAL);
+        assert_eq!(decode(b"AAAACG1vb3Y=", &mut buf).0, EINVAL);
+    }
+
+    #[test]
+    fn normalizes_uuids() {
+        let mut id = [0u8; 16];
+        for s in [&b"10000000-1000-1000-1000-100000000001"[..], b" {10000000-1000-1000-1000-100000000001} ", b"10000000100010001000100000000001"] {
+            assert_eq!(ffmpeg_rs_dash_parse_uuid(s.as_ptr() as *const c_char, s.len(), id.as_mut_ptr()), 0);
+            assert_eq!(id[0], 0x10);
+            assert_eq!(id[15], 0x01);
+        }
+        let bad = b"10000000-1000-1000-1000-10000000000g";
+        assert_eq!(ffmpeg_rs_dash_parse_uuid(bad.as_ptr() as *const c_char, bad.len(), id.as_mut_ptr()), EINVAL);
+
+        let mut buf = [0u8; 37];
+        let mut req = 0usize;
+        let kid = b"EDEF8BA9-79D6-4ACE-A3C8-27DCD51D21ED";
+        assert_eq!(ffmpeg_rs_dash_parse_uuid(kid.as_ptr() as *const c_char, kid.len(), id.as_mut_ptr()), 0);
+        assert_eq!(ffmpeg_rs_dash_format_uuid(id.as_ptr(), buf.as_mut_ptr() as *mut c_char, buf.len(), &mut req), 0);
+        assert_eq!(&buf[..36], b"edef8ba9-79d6-4ace-a3c8-27dcd51d21ed");
+
+        let uri = b"urn:uuid:9A04F079-9840-4286-AB92-E65BE0885F95";
+        assert_eq!(ffmpeg_rs_dash_system_from_scheme(uri.as_ptr() as *const c_char, uri.len(), id.as_mut_ptr()),
+                   FFmpegRsDashSystem::PlayReady as c_int);
+        assert_eq!(id[0], 0x9a);
+        let uri = b"urn:mpeg:dash:mp4protection:2011";
+        assert_eq!(ffmpeg_rs_dash_system_from_scheme(uri.as_ptr() as *const c_char, uri.len(), core::ptr::null_mut()), EINVAL);
+        let uri = b"urn:uuid:00000000-0000-0000-0000-000000000000";
+        assert_eq!(ffmpeg_rs_dash_system_from_scheme(uri.as_ptr() as *const c_char, uri.len(), core::ptr::null_mut()), 0);
+    }
+}
@@ -0,0 +1,580 @@
+use core::ffi::{c_char, c_int};
+
+use crate::writer::{in_array, in_bytes, in_ref, out_array, out_buf, out_ref, Writer, EINVAL, ENOSPC};
+
+/// Largest `%0<width>d` accepted in a template identifier.
+const MAX_WIDTH: usize = 64;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsDashTemplateVars {
+    pub representation_id: *const c_char,
+    pub representation_id_len: usize,
+    pub bandwidth: i64,
+    pub number: i64,
+    pub time: i64,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsDashTimelineEntry {
+    /// `S@t`, or -1 to continue from the previous entry.
+    pub t: i64,
+    pub d: i64,
+    /// `S@r`; -1 repeats up to the next entry, the period end or, for
+    /// dynamic MPDs, the live edge.
+    pub r: i64,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsDashSegmentSource {
+    /// `SegmentTemplate@media`.
+    pub media: *const c_char,
+    pub media_len: usize,
+    pub representation_id: *const c_char,
+    pub representation_id_len: usize,
+    pub bandwidth: i64,
+    /// Inherited SegmentTemplate attributes; `timescale` <= 0 means 1 and
+    /// `start_number` < 0 means 1.
+    pub timescale: i64,
+    pub start_number: i64,
+    /// `@duration`, used when there is no timeline.
+    pub duration: i64,
+    pub presentation_time_offset: i64,
+    pub timeline: *const FFmpegRsDashTimelineEntry,
+    pub n_timeline: usize,
+
+    /// Period position on the presentation timeline; a negative duration
+    /// means unknown.
+    pub period_start_us: i64,
+    pub period_duration_us: i64,
+
+    /// Dynamic MPDs only: wall-clock `availabilityStartTime` and current
+    /// time (both µs since the epoch) and `timeShiftBufferDepth` (negative
+    /// for an unbounded buffer).
+    pub is_dynamic: c_int,
+    pub availability_start_us: i64,
+    pub now_us: i64,
+    pub time_shift_buffer_depth_us: i64,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsDashSegment {
+    pub number: i64,
+    /// Media time and duration in timescale units.
+    pub time: i64,
+    pub duration: i64,
+    /// Same, in µs on the presentation timeline.
+    pub start_us: i64,
+    pub duration_us: i64,
+    /// NUL-terminated expanded URL inside the caller's URL buffer.
+    pub url_offset: usize,
+    pub url_len: usize,
+}
+
+#[repr(C)]
+pub struct FFmpegRsDashExpandResult {
+    pub n_segments_total: usize,
+    pub n_segments_written: usize,
+    pub truncated: c_int,
+    /// URL buffer size needed for the records written.
+    pub url_required: usize,
+    /// Presentation time range whose segments are listed: the availability
+    /// window for dynamic MPDs, the period otherwise (end -1 if unknown).
+    pub window_start_us: i64,
+    pub window_end_us: i64,
+}
+
+pub(crate) struct Vars<'a> {
+    pub representation_id: &'a [u8],
+    pub bandwidth: i64,
+    pub number: i64,
+    pub time: i64,
+}
+
+fn parse_format(fmt: &[u8]) -> Option<usize> {
+    let digits = fmt.strip_prefix(b"%")?.strip_suffix(b"d")?;
+    let digits = digits.strip_prefix(b"0").unwrap_or(digits);
+    let mut width = 0usize;
+    for &b in digits {
+        if !b.is_ascii_digit() {
+            return None;
+        }
+        width = width * 10 + (b - b'0') as usize;
+        if width > MAX_WIDTH {
+            return None;
+        }
+    }
+    Some(width)
+}
+
+// Expands `$$`, `$RepresentationID$` and `$Number$`, `$Bandwidth$`, `$Time$`
+// with an optional `%0<width>d` format tag (ISO/IEC 23009-1 5.3.9.4.4).
+pub(crate) fn expand(w: &mut Writer, tmpl: &[u8], v: &Vars) -> Result<(), c_int> {
+    let mut rest = tmpl;
+    while let Some(i) = rest.iter().position(|&b| b == b'$') {
+        w.bytes(&rest[..i]);
+        let after = &rest[i + 1..];
+        let end = after.iter().position(|&b| b == b'$').ok_or(EINVAL)?;
+        let ident = &after[..end];
+        rest = &after[end + 1..];
+        if ident.is_empty() {
+            w.byte(b'$');
+            continue;
+        }
+        let (name, fmt) = match ident.iter().position(|&b| b == b'%') {
+            Some(p) => (&ident[..p], Some(&ident[p..])),
+            None => (ident, None),
+        };
+        let value = match name {
+            b"RepresentationID" if fmt.is_none() => {
+                w.bytes(v.representation_id);
+                continue;
+            }
+            b"Number" => v.number,
+            b"Bandwidth" => v.bandwidth,
+            b"Time" => v.time,
+            _ => return Err(EINVAL),
+        };
+        let width = match fmt {
+            Some(f) => parse_format(f).ok_or(EINVAL)?,
+            None => 0,
+        };
+        if value < 0 {
+            return Err(EINVAL);
+        }
+        w.uint_padded(value as u64, width);
+    }
+    w.bytes(rest);
+    Ok(())
+}
+
+fn div_floor(a: i128, b: i128) -> i128 {
+    let q = a / b;
+    if (a % b != 0) && ((a < 0) != (b < 0)) {
+        q - 1
+    } else {
+        q
+    }
+}
+
+fn div_ceil(a: i128, b: i128) -> i128 {
+    -div_floor(-a, b)
+}
+
+// MPD-controlled timescales and times can push i128 products out of range;
+// such inputs are rejected rather than wrapped.
+fn mul(a: i128, b: i128) -> Result<i128, c_int> {
+    a.checked_mul(b).ok_or(EINVAL)
+}
+
+fn add(a: i128, b: i128) -> Result<i128, c_int> {
+    a.checked_add(b).ok_or(EINVAL)
+}
+
+fn sub(a: i128, b: i128) -> Result<i128, c_int> {
+    a.checked_sub(b).ok_or(EINVAL)
+}
+
+fn clamp_i64(v: i128) -> i64 {
+    v.clamp(i64::MIN as i128, i64::MAX as i128) as i64
+}
+
+struct Expander<'a> {
+    src: &'a FFmpegRsDashSegmentSource,
+    media: &'a [u8],
+    rep_id: &'a [u8],
+    timescale: i128,
+    segs: &'a mut [FFmpegRsDashSegment],
+    urls: Writer<'a>,
+    total: usize,
+    written: usize,
+}
+
+impl Expander<'_> {
+    // Presentation time of media time `t`, in µs.
+    fn to_us(&self, t: i128) -> Result<i64, c_int> {
+        let rel = div_floor(mul(t - self.src.presentation_time_offset as i128, 1_000_000)?, self.timescale);
+        Ok(clamp_i64(self.src.period_start_us as i128 + rel))
+    }
+
+    // Emits segments `k_lo..k_hi` of a run starting at `t0`.
+    fn run(&mut self, number: i128, t0: i128, d: i128, k_lo: i128, k_hi: i128) -> Result<(), c_int> {
+        if k_hi <= k_lo {
+            return Ok(());
+        }
+        let room = (self.segs.len() - self.written) as i128;
+        for k in k_lo..k_hi.min(k_lo + room) {
+            let t = add(t0, mul(k, d)?)?;
+            let n = clamp_i64(add(number, k)?);
+            let url_offset = self.urls.len();
+            let vars = Vars { representation_id: self.rep_id, bandwidth: self.src.bandwidth, number: n, time: clamp_i64(t) };
+            expand(&mut self.urls, self.media, &vars)?;
+            let url_len = self.urls.len() - url_offset;
+            self.urls.byte(0);
+            let start_us = self.to_us(t)?;
+            self.segs[self.written] = FFmpegRsDashSegment {
+                number: n,
+                time: clamp_i64(t),
+                duration: clamp_i64(d),
+                start_us,
+                duration_us: self.to_us(add(t, d)?)?.saturating_sub(start_us),
+                url_offset,
+                url_len,
+            };
+            self.written += 1;
+        }
+        self.total = self.total.saturating_add(usize::try_from(k_hi - k_lo).unwrap_or(usize::MAX));
+        Ok(())
+    }
+}
+
+fn expand_segments(ex: &mut Expander, out: &mut FFmpegRsDashExpandResult) -> Result<(), c_int> {
+    let src = ex.src;
+    let ts = ex.timescale;
+    let pto = src.presentation_time_offset as i128;
+    let to_media = |us: i128| Ok::<_, c_int>(pto + div_floor(mul(us, ts)?, 1_000_000));
+
+    let period_end = match src.period_duration_us {
+        d if d >= 0 => Some(to_media(d as i128)?),
+        _ => None,
+    };
+    out.window_start_us = src.period_start_us;
+    out.window_end_us = match src.period_duration_us {
+        d if d >= 0 => clamp_i64(src.period_start_us as i128 + d as i128),
+        _ => -1,
+    };
+
+    // Dynamic MPDs list the segments whose end lies in
+    // [now - timeShiftBufferDepth, now].
+    let mut avail: Option<(i128, i128)> = None;
+    if src.is_dynamic != 0 {
+        let now_rel = src.now_us as i128 - src.availability_start_us as i128 - src.period_start_us as i128;
+        let hi = to_media(now_rel)?;
+        let lo = match src.time_shift_buffer_depth_us {
+            d if d >= 0 => to_media(now_rel - d as i128)?,
+            _ => i128::MIN / 4,
+        };
+        out.window_end_us = clamp_i64(src.period_start_us as i128 + now_rel);
+        out.window_start_us = match src.time_shift_buffer_depth_us {
+            d if d >= 0 => clamp_i64(src.period_start_us as i128 + now_rel - d as i128),
+            _ => src.period_start_us,
+        };
+        avail = Some((lo, hi));
+    }
+    // Bound for open-ended runs.
+    let horizon = match (period_end, avail) {
+        (Some(e), Some((_, hi))) => Some(e.min(hi)),
+        (e, None) => e,
+        (None, Some((_, hi))) => Some(hi),
+    };
+
+    let clip = |t0: i128, d: i128, count: i128| -> Result<(i128, i128), c_int> {
+        match avail {
+            Some((lo, hi)) => {
+                // end(k) = t0 + (k + 1) * d
+                let k_lo = (div_ceil(sub(lo, t0)?, d) - 1).max(0);
+                let k_hi = div_floor(sub(hi, t0)?, d).min(count);
+                Ok((k_lo, k_hi))
+            }
+            None => Ok((0, count)),
+        }
+    };
+
+    let start_number = if src.start_number < 0 { 1 } else { src.start_number as i128 };
+    let timeline = in_array(src.timeline, src.n_timeline).ok_or(EINVAL)?;
+    if timeline.is_empty() {
+        let d = src.duration as i128;
+        if d <= 0 {
+            return Err(EINVAL);
+        }
+        let end = horizon.ok_or(EINVAL)?;
+        let count = div_ceil(end - pto, d).max(0);
+        let (k_lo, k_hi) = clip(pto, d, count)?;
+        return ex.run(start_number, pto, d, k_lo, k_hi);
+    }
+
+    let mut number = start_number;
+    let mut t = 0i128;
+    for (i, s) in timeline.iter().enumerate() {
+        if s.t >= 0 {
+            t = s.t as i128;
+        }
+        let d = s.d as i128;
+        if d <= 0 || s.r < -1 {
+            return Err(EINVAL);
+        }
+        let count = if s.r == -1 {
+            let end = match timeline.get(i + 1) {
+                Some(next) if next.t >= 0 => next.t as i128,
+                _ => horizon.ok_or(EINVAL)?,
+            };
+            div_ceil(sub(end, t)?, d).max(0)
+        } else {
+            s.r as i128 + 1
+        };
+        let (k_lo, k_hi) = clip(t, d, count)?;
+        ex.run(number, t, d, k_lo, k_hi)?;
+        number = add(number, count)?;
+        t = add(t, mul(count, d)?)?;
+    }
+    Ok(())
+}
+
+// Expands one SegmentTemplate string. Returns 0 on success, -EINVAL on an
+// unknown identifier, bad format tag or negative value, -ENOSPC if
+// `dst_len` is smaller than `*out_required` (size including NUL).
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_dash_expand_template(
+    tmpl: *const c_char,
+    tmpl_len: usize,
+    vars: *const FFmpegRsDashTemplateVars,
+    dst: *mut c_char,
+    dst_len: usize,
+    out_required: *mut usize,
+) -> c_int {
+    let (tmpl, v) = match (in_bytes(tmpl as *const u8, tmpl_len), in_ref(vars)) {
+        (Some(t), Some(v)) => (t, v),
+        _ => return EINVAL,
+    };
+    let rep_id = match v.representation_id_len {
+        0 => &[][..],
+        n => match in_bytes(v.representation_id as *const u8, n) {
+            Some(r) => r,
+            None => return EINVAL,
+        },
+    };
+    let vars = Vars { representation_id: rep_id, bandwidth: v.bandwidth, number: v.number, time: v.time };
+    let mut w = Writer::new(out_buf(dst, dst_len));
+    if let Err(e) = expand(&mut w, tmpl, &vars) {
+        return e;
+    }
+    w.finish(out_required)
+}
+
+// Lists the media segments of a Representation from its SegmentTemplate
+// (with or without SegmentTimeline). At most `segments_cap` records are
+// written; `out->n_segments_total` counts all of them. URLs of the written
+// records are stored NUL-terminated in `url_buf`. Returns 0 on success,
+// -EINVAL on bad arguments or times that overflow, -ENOSPC if `url_buf_len` is smaller than
+// `out->url_required` (records are still written).
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_dash_expand_segments(
+    source: *const FFmpegRsDashSegmentSource,
+    segments: *mut FFmpegRsDashSegment,
+    segments_cap: usize,
+    url_buf: *mut c_char,
+    url_buf_len: usize,
+    out: *mut FFmpegRsDashExpandResult,
+) -> c_int {
+    let (src, out) = match (in_ref(source), out_ref(out)) {
+        (Some(s), Some(o)) => (s, o),
+        _ => return EINVAL,
+    };
+    let media = in_bytes(src.media as *const u8, src.media_len);
+    let rep_id = match src.representation_id_len {
+        0 => Some(&[][..]),
+        n => in_bytes(src.representation_id as *const u8, n),
+    };
+    let (media, rep_id) = match (media, rep_id) {
+        (Some(m), Some(r)) => (m, r),
+        _ => return EINVAL,
+    };
+    let mut ex = Expander {
+        src,
+        media,
+        rep_id,
+        timescale: if src.timescale > 0 { src.timescale as i128 } else { 1 },
+        segs: out_array(segments, segments_cap),
+        urls: Writer::new(out_buf(url_buf, url_buf_len)),
+        total: 0,
+        written: 0,
+    };
+    *out = FFmpegRsDashExpandResult {
+        n_segments_total: 0,
+        n_segments_written: 0,
+        truncated: 0,
+        url_required: 0,
+        window_start_us: 0,
+        window_end_us: 0,
+    };
+    if let Err(e) = expand_segments(&mut ex, out) {
+        return e;
+    }
+    out.n_segments_total = ex.total;
+    out.n_segments_written = ex.written;
+    out.truncated = (ex.total > ex.written) as c_int;
+    out.url_required = ex.urls.len();
+    if ex.urls.len() > url_buf_len {
+        return ENOSPC;
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_str(tmpl: &[u8], number: i64, time: i64) -> Result<([u8; 64], usize), c_int> {
+        let rep = b"v0";
+        let vars = FFmpegRsDashTemplateVars {
+            representation_id: rep.as_ptr() as *const c_char,
+            representation_id_len: rep.len(),
+            bandwidth: 800000,
+            number,
+            time,
+        };
+        let mut buf = [0u8; 64];
+        let mut req = 0usize;
+        match ffmpeg_rs_dash_expand_template(tmpl.as_ptr() as *const c_char, tmpl.len(), &vars,
+                                             buf.as_mut_ptr() as *mut c_char, buf.len(), &mut req) {
+            0 => Ok((buf, req - 1)),
+            e => Err(e),
+        }
+    }
+
+    #[test]
+    fn expands_templates() {
+        let (buf, n) = expand_str(b"$RepresentationID$/$Bandwidth$/seg-$Number%05d$-$Time$$$.m4s", 42, 180000).unwrap();
+        assert_eq!(&buf[..n], b"v0/800000/seg-00042-180000$.m4s");
+        let (buf, n) = expand_str(b"$Number%d$", 7, 0).unwrap();
+        assert_eq!(&buf[..n], b"7");
+        assert_eq!(expand_str(b"$Foo$", 0, 0).err(), Some(EINVAL));
+        assert_eq!(expand_str(b"$Number", 0, 0).err(), Some(EINVAL));
+        assert_eq!(expand_str(b"$Number%05x$", 0, 0).err(), Some(EINVAL));
+        assert_eq!(expand_str(b"$RepresentationID%02d$", 0, 0).err(), Some(EINVAL));
+    }
+
+    fn source(media: &'static [u8], timeline: &[FFmpegRsDashTimelineEntry]) -> FFmpegRsDashSegmentSource {
+        FFmpegRsDashSegmentSource {
+            media: media.as_ptr() as *const c_char,
+            media_len: media.len(),
+            representation_id: core::ptr::null(),
+            representation_id_len: 0,
+            bandwidth: 0,
+            timescale: 1000,
+            start_number: -1,
+            duration: 0,
+            presentation_time_offset: 0,
+            timeline: timeline.as_ptr(),
+            n_timeline: timeline.len(),
+            period_start_us: 0,
+            period_duration_us: -1,
+            is_dynamic: 0,
+            availability_start_us: 0,
+            now_us: 0,
+            time_shift_buffer_depth_us: -1,
+        }
+    }
+
+    fn run(src: &FFmpegRsDashSegmentSource, segs: &mut [FFmpegRsDashSegment], urls: &mut [u8]) -> (c_int, FFmpegRsDashExpandResult) {
+        let mut out = FFmpegRsDashExpandResult {
+            n_segments_total: 0,
+            n_segments_written: 0,
+            truncated: 0,
+            url_required: 0,
+            window_start_us: 0,
+            window_end_us: 0,
+        };
+        let r = ffmpeg_rs_dash_expand_segments(src, segs.as_mut_ptr(), segs.len(),
+                                               urls.as_mut_ptr() as *mut c_char, urls.len(), &mut out);
+        (r, out)
+    }
+
+    const BLANK: FFmpegRsDashSegment =
+        FFmpegRsDashSegment { number: 0, time: 0, duration: 0, start_us: 0, duration_us: 0, url_offset: 0, url_len: 0 };
+
+    #[test]
+    fn unrolls_timeline() {
+        let tl = [
+            FFmpegRsDashTimelineEntry { t: 1000, d: 2000, r: 1 },
+            FFmpegRsDashTimelineEntry { t: -1, d: 1000, r: -1 },
+            FFmpegRsDashTimelineEntry { t: 8000, d: 1500, r: -1 },
+        ];
+        let mut src = source(b"s-$Time$-$Number$.m4s", &tl);
+        src.presentation_time_offset = 1000;
+        src.period_start_us = 10_000_000;
+        src.period_duration_us = 10_000_000;
+        let mut segs = [BLANK; 16];
+        let mut urls = [0u8; 256];
+        let (r, out) = run(&src, &mut segs, &mut urls);
+        assert_eq!(r, 0);
+        // 2 x 2000, 3 x 1000 (up to t=8000), 2 x 1500 (up to 11000 = period end)
+        assert_eq!(out.n_segments_total, 7);
+        assert_eq!(out.truncated, 0);
+        let times = segs[..7].iter().map(|s| s.time);
+        assert!(times.eq([1000, 3000, 5000, 6000, 7000, 8000, 9500]));
+        assert_eq!(segs[6].number, 7);
+        assert_eq!(segs[1].start_us, 12_000_000);
+        assert_eq!(segs[5].duration_us, 1_500_000);
+        let url = &urls[segs[6].url_offset..][..segs[6].url_len];
+        assert_eq!(url, b"s-9500-7.m4s");
+        assert_eq!(urls[segs[6].url_offset + segs[6].url_len], 0);
+
+        // Size-only pass, then a URL buffer that is too small.
+        let (r, all) = run(&src, &mut [], &mut []);
+        assert_eq!((r, all.n_segments_total, all.truncated, all.url_required), (0, 7, 1, 0));
+        let (r, out) = run(&src, &mut segs[..2], &mut urls[..4]);
+        assert_eq!((r, out.n_segments_written, out.url_required), (ENOSPC, 2, 26));
+
+        // r=-1 as last entry needs a known period end.
+        src.period_duration_us = -1;
+        assert_eq!(run(&src, &mut segs, &mut urls).0, EINVAL);
+    }
+
+    #[test]
+    fn applies_availability_window() {
+        let mut src = source(b"$Number%03d$.ts", &[]);
+        src.timescale = 1;
+        src.duration = 2;
+        src.start_number = 1;
+        src.is_dynamic = 1;
+        src.availability_start_us = 1_000_000_000;
+        src.now_us = 1_000_000_000 + 61_000_000;
+        src.time_shift_buffer_depth_us = 10_000_000;
+        let mut segs = [BLANK; 16];
+        let mut urls = [0u8; 256];
+        let (r, out) = run(&src, &mut segs, &mut urls);
+        assert_eq!(r, 0);
+        // Segments ending in [51 s, 61 s]: [50,52) .. [58,60).
+        assert_eq!(out.n_segments_total, 5);
+        assert_eq!((segs[0].number, segs[0].time), (26, 50));
+        assert_eq!((segs[4].number, segs[4].time), (30, 58));
+        assert_eq!(&urls[segs[4].url_offset..][..segs[4].url_len], b"030.ts");
+        assert_eq!((out.window_start_us, out.window_end_us), (51_000_000, 61_000_000));
+
+        // Live timeline with an open last entry stops at the live edge.
+        let tl = [FFmpegRsDashTimelineEntry { t: 0, d: 4, r: -1 }];
+        let mut src2 = source(b"$Time$", &tl);
+        src2.timescale = 1;
+        src2.is_dynamic = 1;
+        src2.now_us = 17_000_000;
+        let (r, out) = run(&src2, &mut segs, &mut urls);
+        assert_eq!((r, out.n_segments_total), (0, 4));
+        assert_eq!(segs[3].time, 12);
+    }
+
+    #[test]
+    fn rejects_overflowing_times() {
+        let mut segs = [BLANK; 4];
+        let mut urls = [0u8; 64];
+
+        // Live edge far from availabilityStartTime with a huge timescale.
+        let mut src = source(b"$Number$", &[]);
+        src.timescale = i64::MAX;
+        src.duration = 1;
+        src.is_dynamic = 1;
+        src.availability_start_us = i64::MIN;
+        src.period_start_us = i64::MIN;
+        src.now_us = i64::MAX;
+        assert_eq!(run(&src, &mut segs, &mut urls).0, EINVAL);
+
+        // Timeline runs whose total length exceeds the i128 range.
+        let big = FFmpegRsDashTimelineEntry { t: -1, d: i64::MAX, r: i64::MAX - 1 };
+        let tl = [FFmpegRsDashTimelineEntry { t: i64::MAX, ..big }, big, big, big];
+        let src = source(b"$Time$", &tl);
+        assert_eq!(run(&src, &mut segs, &mut urls).0, EINVAL);
+    }
+}
@@ -0,0 +1,296 @@
+use core::ffi::{c_char, c_int};
+
+use crate::writer::{in_bytes, out_ref, EINVAL};
+
+const US_PER_SEC: i128 = 1_000_000;
+
+/// Digits kept from a fractional component; later ones are truncated.
+const MAX_FRAC_DIGITS: u32 = 18;
+
+/// i64 microseconds span about 292 277 years either side of 1970; longer
+/// years are rejected before days_from_civil() could overflow.
+const MAX_YEAR: i64 = 300_000;
+
+// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(y: i64, m: u32, d: u32) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = (m as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+// Inverse of days_from_civil().
+pub(crate) fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let y = yoe + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    (if m <= 2 { y + 1 } else { y }, m, d)
+}
+
+fn days_in_month(y: i64, m: u32) -> u32 {
+    match m {
+        2 if y % 4 == 0 && (y % 100 != 0 || y % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+struct Cursor<'a> {
+    s: &'a [u8],
+}
+
+impl Cursor<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.s.first().copied()
+    }
+
+    fn eat(&mut self, b: u8) -> bool {
+        if self.peek() == Some(b) {
+            self.s = &self.s[1..];
+            true
+        } else {
+            false
+        }
+    }
+
+    // Unsigned decimal; returns the value and the number of digits.
+    fn digits(&mut self) -> Option<(u64, usize)> {
+        let n = self.s.iter().take_while(|b| b.is_ascii_digit()).count();
+        if n == 0 {
+            return None;
+        }
+        let mut v = 0u64;
+        for &b in &self.s[..n] {
+            v = v.checked_mul(10)?.checked_add((b - b'0') as u64)?;
+        }
+        self.s = &self.s[n..];
+        Some((v, n))
+    }
+
+    fn fixed(&mut self, width: usize) -> Option<u32> {
+        match self.digits()? {
+            (v, n) if n == width => Some(v as u32),
+            _ => None,
+        }
+    }
+
+    // Optional `.ddd` / `,ddd` as (numerator, 10^digits).
+    fn fraction(&mut self) -> Option<(i128, i128)> {
+        if !(self.eat(b'.') || self.eat(b',')) {
+            return Some((0, 1));
+        }
+        let n = self.s.iter().take_while(|b| b.is_ascii_digit()).count();
+        if n == 0 {
+            return None;
+        }
+        let (mut num, mut den) = (0i128, 1i128);
+        for &b in self.s[..n].iter().take(MAX_FRAC_DIGITS as usize) {
+            num = num * 10 + (b - b'0') as i128;
+            den *= 10;
+        }
+        self.s = &self.s[n..];
+        Some((num, den))
+    }
+}
+
+// PnYnMnWnDTnHnMnS. Years and months use their nominal lengths (365 and 30
+// days); only the last component may carry a fraction.
+fn parse_duration(s: &[u8]) -> Option<i64> {
+    const DAY: i128 = 86_400 * US_PER_SEC;
+    let mut c = Cursor { s };
+    let neg = c.eat(b'-');
+    if !c.eat(b'P') {
+        return None;
+    }
+    let date_units: [(u8, i128); 4] = [(b'Y', 365 * DAY), (b'M', 30 * DAY), (b'W', 7 * DAY), (b'D', DAY)];
+    let time_units: [(u8, i128); 3] = [(b'H', 3600 * US_PER_SEC), (b'M', 60 * US_PER_SEC), (b'S', US_PER_SEC)];
+
+    let mut total = 0i128;
+    let mut n_components = 0;
+    let mut fractional = false;
+    let mut in_time = false;
+    let mut next_unit = 0usize;
+    loop {
+        if c.peek().is_none() {
+            break;
+        }
+        if fractional {
+            return None;
+        }
+        if !in_time && c.eat(b'T') {
+            in_time = true;
+            next_unit = 0;
+            c.peek()?;
+            continue;
+        }
+        let (int, _) = c.digits()?;
+        let (num, den) = c.fraction()?;
+        fractional = num != 0 || den != 1;
+        let units: &[(u8, i128)] = if in_time { &time_units } else { &date_units };
+        let designator = c.peek()?;
+        let i = units[next_unit..].iter().position(|&(d, _)| d == designator)? + next_unit;
+        c.eat(designator);
+        next_unit = i + 1;
+        let unit = units[i].1;
+        total = total.checked_add((int as i128).checked_mul(unit)?)?.checked_add(num * unit / den)?;
+        n_components += 1;
+    }
+    if n_components == 0 {
+        return None;
+    }
+    let total = if neg { -total } else { total };
+    i64::try_from(total).ok()
+}
+
+// [-]YYYY-MM-DDThh:mm:ss[.s+][Z|(+|-)hh:mm]; no zone designator is taken
+// as UTC.
+fn parse_datetime(s: &[u8]) -> Option<i64> {
+    let mut c = Cursor { s };
+    let neg = c.eat(b'-');
+    let (year, n) = c.digits()?;
+    if n < 4 || (n > 4 && s[neg as usize] == b'0') {
+        return None;
+    }
+    let year = i64::try_from(year).ok()?;
+    let year = if neg { -year } else { year };
+    if year.abs() > MAX_YEAR {
+        return None;
+    }
+    let ok = c.eat(b'-');
+    let month = c.fixed(2)?;
+    let ok = ok && c.eat(b'-');
+    let day = c.fixed(2)?;
+    if !ok || !c.eat(b'T') || !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+    let hour = c.fixed(2)?;
+    let ok = c.eat(b':');
+    let min = c.fixed(2)?;
+    let ok = ok && c.eat(b':');
+    let sec = c.fixed(2)?;
+    let (num, den) = c.fraction()?;
+    // 24:00:00 is the end of the day.
+    let end_of_day = hour == 24 && min == 0 && sec == 0 && num == 0;
+    if !ok || (hour > 23 && !end_of_day) || min > 59 || sec > 59 {
+        return None;
+    }
+
+    let mut offset_min = 0i64;
+    match c.peek() {
+        None => {}
+        Some(b'Z') => {
+            c.eat(b'Z');
+        }
+        Some(sign @ (b'+' | b'-')) => {
+            c.eat(sign);
+            let oh = c.fixed(2)? as i64;
+            if !c.eat(b':') {
+                return None;
+            }
+            let om = c.fixed(2)? as i64;
+            if om > 59 || oh * 60 + om > 14 * 60 {
+                return None;
+            }
+            offset_min = if sign == b'-' { -(oh * 60 + om) } else { oh * 60 + om };
+        }
+        Some(_) => return None,
+    }
+    if c.peek().is_some() {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day) as i128;
+    let secs = days * 86_400 + (hour as i128 * 60 + min as i128 - offset_min as i128) * 60 + sec as i128;
+    let us = secs * US_PER_SEC + num * US_PER_SEC / den;
+    i64::try_from(us).ok()
+}
+
+fn parse_with(s: *const c_char, len: usize, out_us: *mut i64, f: fn(&[u8]) -> Option<i64>) -> c_int {
+    let (s, out) = match (in_bytes(s as *const u8, len), out_ref(out_us)) {
+        (Some(s), Some(o)) => (s, o),
+        _ => return EINVAL,
+    };
+    match f(s) {
+        Some(v) => {
+            *out = v;
+            0
+        }
+        None => EINVAL,
+    }
+}
+
+// Parses an ISO 8601 / xs:duration value such as `PT1H2M3.5S` into µs.
+// Years and months count as 365 and 30 days. Returns 0 on success, -EINVAL
+// on a malformed or out-of-range value (`*out_us` is then left untouched).
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_dash_parse_duration(s: *const c_char, len: usize, out_us: *mut i64) -> c_int {
+    parse_with(s, len, out_us, parse_duration)
+}
+
+// Parses an xs:dateTime such as `2024-03-01T12:00:00.5+01:00` into µs since
+// the Unix epoch. A value without zone designator is taken as UTC. Same
+// return convention as ffmpeg_rs_dash_parse_duration().
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_dash_parse_datetime(s: *const c_char, len: usize, out_us: *mut i64) -> c_int {
+    parse_with(s, len, out_us, parse_datetime)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration(b"PT1H2M3.5S"), Some(3_723_500_000));
+        assert_eq!(parse_duration(b"PT0S"), Some(0));
+        assert_eq!(parse_duration(b"P1DT12H"), Some(129_600_000_000));
+        assert_eq!(parse_duration(b"P1Y2M"), Some((365 + 60) * 86_400_000_000));
+        assert_eq!(parse_duration(b"P2W"), Some(14 * 86_400_000_000));
+        assert_eq!(parse_duration(b"PT1.5M"), Some(90_000_000));
+        assert_eq!(parse_duration(b"PT0.0000019S"), Some(1));
+        assert_eq!(parse_duration(b"-PT10S"), Some(-10_000_000));
+        assert_eq!(parse_duration(b"PT1M"), Some(60_000_000));
+        assert_eq!(parse_duration(b"P1M"), Some(30 * 86_400_000_000));
+
+        for bad in [&b""[..], b"P", b"PT", b"T1S", b"P1S", b"PT1D", b"PT1S2M", b"PT1.5M3S", b"P1.5DT1H", b"PT1.S", b"P1H", b"PT1S ", b"PT99999999999999999999S"] {
+            assert_eq!(parse_duration(bad), None, "{:?}", core::str::from_utf8(bad));
+        }
+    }
+
+    #[test]
+    fn parses_datetimes() {
+        assert_eq!(parse_datetime(b"1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_datetime(b"2024-02-29T12:34:56.789Z"), Some(1_709_210_096_789_000));
+        assert_eq!(parse_datetime(b"2024-02-29T13:34:56.789+01:00"), Some(1_709_210_096_789_000));
+        assert_eq!(parse_datetime(b"2024-02-29T07:04:56.789-05:30"), Some(1_709_210_096_789_000));
+        assert_eq!(parse_datetime(b"2024-02-29T12:34:56.789"), Some(1_709_210_096_789_000));
+        assert_eq!(parse_datetime(b"1969-12-31T23:59:59.5Z"), Some(-500_000));
+        assert_eq!(parse_datetime(b"2000-01-01T24:00:00Z"), parse_datetime(b"2000-01-02T00:00:00Z"));
+
+        for bad in [
+            &b"2023-02-29T00:00:00Z"[..], b"2024-13-01T00:00:00Z", b"2024-01-01 00:00:00Z", b"2024-01-01T00:60:00Z",
+            b"2024-01-01T00:00:60Z", b"2024-01-01T24:00:01Z", b"2024-01-01T00:00:00+15:00", b"2024-01-01T00:00:00+0100",
+            b"24-01-01T00:00:00Z", b"2024-1-01T00:00:00Z", b"2024-01-01", b"2024-01-01T00:00:00ZZ",
+            b"999999999999999999-01-01T00:00:00Z", b"-999999999999999999-01-01T00:00:00Z", b"294247-01-11T00:00:00Z",
+        ] {
+            assert_eq!(parse_datetime(bad), None, "{:?}", core::str::from_utf8(bad));
+        }
+
+        let s = b"PT2S";
+        let mut us = -1i64;
+        assert_eq!(ffmpeg_rs_dash_parse_duration(s.as_ptr() as *const c_char, s.len(), &mut us), 0);
+        assert_eq!(us, 2_000_000);
+        assert_eq!(ffmpeg_rs_dash_parse_datetime(s.as_ptr() as *const c_char, s.len(), &mut us), EINVAL);
+        assert_eq!(us, 2_000_000);
+    }
+}
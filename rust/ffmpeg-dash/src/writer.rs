@@ -0,0 +1,127 @@
+use core::ffi::{c_char, c_int};
+
+pub(crate) const EINVAL: c_int = -22;
+pub(crate) const ENOSPC: c_int = -28;
+/// FFERRTAG('I', 'N', 'D', 'A'), libavutil's AVERROR_INVALIDDATA.
+pub(crate) const AVERROR_INVALIDDATA: c_int = -0x4144_4E49;
+
+/// Bounded output cursor. Bytes past the end of `buf` are counted but not
+/// stored, so a single pass yields both the output and the required size.
+pub(crate) struct Writer<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+impl<'a> Writer<'a> {
+    pub(crate) fn new(buf: &'a mut [u8]) -> Self {
+        Writer { buf, pos: 0 }
+    }
+
+    pub(crate) fn byte(&mut self, b: u8) {
+        if self.pos < self.buf.len() {
+            self.buf[self.pos] = b;
+        }
+        self.pos = self.pos.saturating_add(1);
+    }
+
+    pub(crate) fn bytes(&mut self, s: &[u8]) {
+        for &b in s {
+            self.byte(b);
+        }
+    }
+
+    /// Number of bytes produced so far, stored or not.
+    pub(crate) fn len(&self) -> usize {
+        self.pos
+    }
+
+    pub(crate) fn uint(&mut self, v: u64) {
+        self.uint_padded(v, 0);
+    }
+
+    /// Decimal with at least `width` digits, zero padded.
+    pub(crate) fn uint_padded(&mut self, mut v: u64, width: usize) {
+        let mut tmp = [0u8; 20];
+        let mut i = 0usize;
+        loop {
+            tmp[i] = (v % 10) as u8 + b'0';
+            i += 1;
+            v /= 10;
+            if v == 0 {
+                break;
+            }
+        }
+        let mut pad = width.saturating_sub(i);
+        while pad > 0 {
+            self.byte(b'0');
+            pad -= 1;
+        }
+        while i > 0 {
+            i -= 1;
+            self.byte(tmp[i]);
+        }
+    }
+
+    /// NUL-terminates the output and reports the required size (including the
+    /// terminator) through `out_required`.
+    pub(crate) fn finish(mut self, out_required: *mut usize) -> c_int {
+        let len = self.pos;
+        self.byte(0);
+        let required = self.pos;
+        if let Some(r) = out_ref(out_required) {
+            *r = required;
+        }
+        if len >= self.buf.len() {
+            return ENOSPC;
+        }
+        0
+    }
+}
+
+/// Borrows a caller-owned output buffer. A NULL pointer yields an empty
+/// buffer, which turns the call into a size query.
+pub(crate) fn out_buf<'a>(dst: *mut c_char, dst_len: usize) -> &'a mut [u8] {
+    out_array(dst as *mut u8, dst_len)
+}
+
+pub(crate) fn in_bytes<'a>(p: *const u8, len: usize) -> Option<&'a [u8]> {
+    if p.is_null() {
+        return None;
+    }
+    Some(unsafe { core::slice::from_raw_parts(p, len) })
+}
+
+/// Borrows a caller-owned array of POD records.
+pub(crate) fn in_array<'a, T>(p: *const T, n: usize) -> Option<&'a [T]> {
+    if n == 0 {
+        return Some(&[]);
+    }
+    if p.is_null() {
+        return None;
+    }
+    Some(unsafe { core::slice::from_raw_parts(p, n) })
+}
+
+/// Borrows a single caller-owned POD record.
+pub(crate) fn in_ref<'a, T>(p: *const T) -> Option<&'a T> {
+    if p.is_null() {
+        return None;
+    }
+    Some(unsafe { &*p })
+}
+
+/// Borrows a caller-owned result struct.
+pub(crate) fn out_ref<'a, T>(p: *mut T) -> Option<&'a mut T> {
+    if p.is_null() {
+        return None;
+    }
+    Some(unsafe { &mut *p })
+}
+
+/// Borrows a caller-owned output array; NULL yields an empty array.
+pub(crate) fn out_array<'a, T>(p: *mut T, n: usize) -> &'a mut [T] {
+    if p.is_null() || n == 0 {
+        return &mut [];
+    }
+    unsafe { core::slice::from_raw_parts_mut(p, n) }
+}
@@ -0,0 +1,342 @@
+// Minimal, allocation-free XML tokenizer covering what MPDs use: elements,
+// attributes, text, CDATA, comments, processing instructions and a DOCTYPE.
+// Names and values are returned as spans of the input; entities are left
+// encoded (see `unescape`).
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub(crate) enum Token<'a> {
+    /// `<name attrs>` or `<name attrs/>`; `attrs` is the raw attribute list.
+    Start {
+        name: &'a [u8],
+        attrs: &'a [u8],
+        empty: bool,
+    },
+    End {
+        name: &'a [u8],
+    },
+    /// Character data between tags, or the body of a CDATA section.
+    Text(&'a [u8]),
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub(crate) struct SyntaxError {
+    pub line_no: u32,
+}
+
+pub(crate) struct Tokenizer<'a> {
+    data: &'a [u8],
+    pos: usize,
+    line_no: u32,
+    /// Line of the token returned last.
+    pub token_line: u32,
+}
+
+fn is_ws(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\r' | b'\n')
+}
+
+fn is_name_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || matches!(b, b'_' | b':' | b'-' | b'.') || b >= 0x80
+}
+
+fn find(hay: &[u8], needle: &[u8]) -> Option<usize> {
+    hay.windows(needle.len()).position(|w| w == needle)
+}
+
+pub(crate) fn trim(mut s: &[u8]) -> &[u8] {
+    while let [first, rest @ ..] = s {
+        if !is_ws(*first) {
+            break;
+        }
+        s = rest;
+    }
+    while let [rest @ .., last] = s {
+        if !is_ws(*last) {
+            break;
+        }
+        s = rest;
+    }
+    s
+}
+
+/// Name without its namespace prefix (`cenc:pssh` -> `pssh`).
+pub(crate) fn local_name(name: &[u8]) -> &[u8] {
+    match name.iter().rposition(|&b| b == b':') {
+        Some(i) => &name[i + 1..],
+        None => name,
+    }
+}
+
+impl<'a> Tokenizer<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        let pos = if data.starts_with(b"\xEF\xBB\xBF") { 3 } else { 0 };
+        Tokenizer { data, pos, line_no: 1, token_line: 1 }
+    }
+
+    fn advance(&mut self, n: usize) {
+        let end = (self.pos + n).min(self.data.len());
+        self.line_no = self
+            .line_no
+            .saturating_add(self.data[self.pos..end].iter().filter(|&&b| b == b'\n').count() as u32);
+        self.pos = end;
+    }
+
+    fn err(&self) -> SyntaxError {
+        SyntaxError { line_no: self.line_no }
+    }
+
+    // Skips past `close`, which must occur in the remaining input.
+    fn skip_past(&mut self, close: &[u8]) -> Result<&'a [u8], SyntaxError> {
+        let rest = &self.data[self.pos..];
+        let i = find(rest, close).ok_or_else(|| self.err())?;
+        self.advance(i + close.len());
+        Ok(&rest[..i])
+    }
+
+    fn tag(&mut self) -> Result<Token<'a>, SyntaxError> {
+        let rest = &self.data[self.pos..];
+        let closing = rest.get(1) == Some(&b'/');
+        let name_start = if closing { 2 } else { 1 };
+        let name_len = rest[name_start..].iter().take_while(|&&b| is_name_byte(b)).count();
+        if name_len == 0 {
+            return Err(self.err());
+        }
+        let name = &rest[name_start..name_start + name_len];
+
+        // Find the closing '>' outside quoted attribute values.
+        let mut quote = 0u8;
+        let mut end = None;
+        for (i, &b) in rest.iter().enumerate().skip(name_start + name_len) {
+            if quote != 0 {
+                if b == quote {
+                    quote = 0;
+                }
+            } else if b == b'"' || b == b'\'' {
+                quote = b;
+            } else if b == b'<' {
+                break;
+            } else if b == b'>' {
+                end = Some(i);
+                break;
+            }
+        }
+        let end = end.ok_or_else(|| self.err())?;
+        let mut inner = &rest[name_start + name_len..end];
+        let empty = inner.last() == Some(&b'/');
+        if empty {
+            inner = &inner[..inner.len() - 1];
+        }
+        if !inner.is_empty() && !is_ws(inner[0]) {
+            return Err(self.err());
+        }
+        let attrs = trim(inner);
+        if closing && (empty || !attrs.is_empty()) {
+            return Err(self.err());
+        }
+        if Attrs::new(attrs).any(|a| a.is_err()) {
+            return Err(self.err());
+        }
+        self.advance(end + 1);
+        if closing {
+            Ok(Token::End { name })
+        } else {
+            Ok(Token::Start { name, attrs, empty })
+        }
+    }
+
+    pub(crate) fn next_token(&mut self) -> Option<Result<Token<'a>, SyntaxError>> {
+        loop {
+            let rest = &self.data[self.pos..];
+            if rest.is_empty() {
+                return None;
+            }
+            self.token_line = self.line_no;
+            if rest[0] != b'<' {
+                let n = rest.iter().position(|&b| b == b'<').unwrap_or(rest.len());
+                self.advance(n);
+                return Some(Ok(Token::Text(&rest[..n])));
+            }
+            if rest.starts_with(b"<!--") {
+                self.advance(4);
+                if let Err(e) = self.skip_past(b"-->") {
+                    return Some(Err(e));
+                }
+            } else if rest.starts_with(b"<![CDATA[") {
+                self.advance(9);
+                return Some(self.skip_past(b"]]>").map(Token::Text));
+            } else if rest.starts_with(b"<?") {
+                self.advance(2);
+                if let Err(e) = self.skip_past(b"?>") {
+                    return Some(Err(e));
+                }
+            } else if rest.starts_with(b"<!") {
+                // DOCTYPE; an internal subset is bracketed.
+                let mut depth = 0i32;
+                let n = rest.iter().position(|&b| {
+                    match b {
+                        b'[' => depth += 1,
+                        b']' => depth -= 1,
+                        b'>' if depth <= 0 => return true,
+                        _ => {}
+                    }
+                    false
+                });
+                match n {
+                    Some(n) => self.advance(n + 1),
+                    None => return Some(Err(self.err())),
+                }
+            } else {
+                return Some(self.tag());
+            }
+        }
+    }
+}
+
+/// Iterates `name="value"` pairs of a raw attribute list.
+pub(crate) struct Attrs<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Attrs<'a> {
+    pub(crate) fn new(attrs: &'a [u8]) -> Self {
+        Attrs { rest: attrs }
+    }
+}
+
+impl<'a> Iterator for Attrs<'a> {
+    type Item = Result<(&'a [u8], &'a [u8]), ()>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let s = trim(self.rest);
+        if s.is_empty() {
+            return None;
+        }
+        // Any error ends the iteration.
+        self.rest = &[];
+        let name_len = s.iter().take_while(|&&b| is_name_byte(b)).count();
+        if name_len == 0 {
+            return Some(Err(()));
+        }
+        let (name, s) = s.split_at(name_len);
+        let s = trim(s);
+        let s = match s.split_first() {
+            Some((b'=', s)) => trim(s),
+            _ => return Some(Err(())),
+        };
+        let quote = match s.first() {
+            Some(&q @ (b'"' | b'\'')) => q,
+            _ => return Some(Err(())),
+        };
+        let end = match s[1..].iter().position(|&b| b == quote) {
+            Some(i) => i + 1,
+            None => return Some(Err(())),
+        };
+        let value = &s[1..end];
+        let after = &s[end + 1..];
+        if !after.is_empty() && !is_ws(after[0]) {
+            return Some(Err(()));
+        }
+        self.rest = after;
+        Some(Ok((name, value)))
+    }
+}
+
+/// Looks up an attribute by qualified name.
+pub(crate) fn attr<'a>(attrs: &'a [u8], name: &[u8]) -> Option<&'a [u8]> {
+    Attrs::new(attrs).filter_map(Result::ok).find(|(n, _)| *n == name).map(|(_, v)| v)
+}
+
+fn decode_entity(s: &[u8]) -> Option<(u32, usize)> {
+    let end = s.iter().take(12).position(|&b| b == b';')?;
+    let body = &s[1..end];
+    let cp = match body {
+        b"lt" => '<' as u32,
+        b"gt" => '>' as u32,
+        b"amp" => '&' as u32,
+        b"quot" => '"' as u32,
+        b"apos" => '\'' as u32,
+        [b'#', b'x' | b'X', hex @ ..] if !hex.is_empty() => {
+            let mut v = 0u32;
+            for &b in hex {
+                v = v.checked_mul(16)?.checked_add((b as char).to_digit(16)?)?;
+            }
+            v
+        }
+        [b'#', dec @ ..] if !dec.is_empty() => {
+            let mut v = 0u32;
+            for &b in dec {
+                v = v.checked_mul(10)?.checked_add((b as char).to_digit(10)?)?;
+            }
+            v
+        }
+        _ => return None,
+    };
+    char::from_u32(cp).map(|_| (cp, end + 1))
+}
+
+/// Decodes the predefined and numeric character references of `s` through
+/// `emit`. Unknown or malformed references are passed through unchanged.
+pub(crate) fn unescape(s: &[u8], mut emit: impl FnMut(&[u8])) {
+    let mut i = 0;
+    while i < s.len() {
+        let n = s[i..].iter().position(|&b| b == b'&').unwrap_or(s.len() - i);
+        emit(&s[i..i + n]);
+        i += n;
+        if i == s.len() {
+            break;
+        }
+        match decode_entity(&s[i..]) {
+            Some((cp, used)) => {
+                let mut buf = [0u8; 4];
+                let c = char::from_u32(cp).unwrap_or('\u{FFFD}');
+                emit(c.encode_utf8(&mut buf).as_bytes());
+                i += used;
+            }
+            None => {
+                emit(b"&");
+                i += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenizes_markup() {
+        let doc = b"<?xml version=\"1.0\"?>\n<!-- c -->\n<a x='1' y = \"2 > 1\"><b/>t&amp;<![CDATA[<raw>]]></a>";
+        let mut t = Tokenizer::new(doc);
+        let mut next = || t.next_token().unwrap().unwrap();
+        assert_eq!(next(), Token::Text(b"\n"));
+        assert_eq!(next(), Token::Text(b"\n"));
+        assert_eq!(next(), Token::Start { name: b"a", attrs: b"x='1' y = \"2 > 1\"", empty: false });
+        assert_eq!(next(), Token::Start { name: b"b", attrs: b"", empty: true });
+        assert_eq!(next(), Token::Text(b"t&amp;"));
+        assert_eq!(next(), Token::Text(b"<raw>"));
+        assert_eq!(next(), Token::End { name: b"a" });
+        assert!(t.next_token().is_none());
+        assert_eq!(t.token_line, 3);
+
+        assert_eq!(attr(b"x='1' y = \"2 > 1\"", b"y"), Some(&b"2 > 1"[..]));
+        assert_eq!(attr(b"x='1'", b"y"), None);
+
+        let mut t = Tokenizer::new(b"<a\nx=1>");
+        assert_eq!(t.next_token(), Some(Err(SyntaxError { line_no: 1 })));
+        let mut t = Tokenizer::new(b"\n<a");
+        assert_eq!(t.next_token().unwrap().unwrap(), Token::Text(b"\n"));
+        assert_eq!(t.next_token(), Some(Err(SyntaxError { line_no: 2 })));
+    }
+
+    #[test]
+    fn unescapes_references() {
+        let mut out = [0u8; 32];
+        let mut n = 0;
+        unescape(b"a&amp;b&#x41;&#66;&bogus;&", |s| {
+            out[n..n + s.len()].copy_from_slice(s);
+            n += s.len();
+        });
+        assert_eq!(&out[..n], b"a&bAB&bogus;&");
+    }
+}
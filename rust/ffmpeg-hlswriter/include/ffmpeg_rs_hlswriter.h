@@ -1,6 +1,7 @@
 #pragma once
 
 #include <stddef.h>
+#include <stdint.h>
 
 #ifdef __cplusplus
 extern "C" {
@@ -9,6 +10,257 @@ extern "C" {
 /* Returns bytes written (excluding NUL), or <0 on error. */
 ptrdiff_t ffmpeg_rs_hls_write_playlist_version(char *dst, size_t dst_len, int version);
 
+/*
+ * Functions below share a "size query then fill" convention:
+ * - Return 0 on success, -EINVAL on invalid arguments, -ENOSPC if `dst_len`
+ *   is too small.
+ * - `*out_required` (if non-NULL) always receives the size needed for the
+ *   full output including the terminating NUL. Pass dst=NULL, dst_len=0 to
+ *   query it.
+ */
+
+/* Formats epoch microseconds as ISO 8601 (`2014-05-13T16:53:20.000Z`, or with
+ * a `+hh:mm` suffix when tz_offset_min != 0). */
+int ffmpeg_rs_hls_format_iso8601(int64_t epoch_us, int tz_offset_min,
+                                 char *dst, size_t dst_len, size_t *out_required);
+
+typedef enum FFmpegRsHlsAttrType {
+    FFMPEG_RS_HLS_ATTR_QUOTED  = 0, /* value as quoted-string */
+    FFMPEG_RS_HLS_ATTR_HEX     = 1, /* value bytes as 0x... hexadecimal-sequence */
+    FFMPEG_RS_HLS_ATTR_DECIMAL = 2, /* number_us / 1e6 as decimal-floating-point */
+} FFmpegRsHlsAttrType;
+
+/* Client-defined `X-` attribute. Names must match X-[A-Z0-9-]+. */
+typedef struct FFmpegRsHlsAttr {
+    const char *name;
+    size_t name_len;
+    uint32_t type;
+    const uint8_t *value;
+    size_t value_len;
+    int64_t number_us;
+} FFmpegRsHlsAttr;
+
+typedef struct FFmpegRsHlsDateRange {
+    const char *id;             /* required */
+    size_t id_len;
+    const char *class_name;     /* NULL if absent */
+    size_t class_len;
+
+    int64_t start_us;           /* epoch microseconds */
+    int has_end;
+    int64_t end_us;
+    int64_t duration_us;         /* <0 if absent */
+    int64_t planned_duration_us; /* <0 if absent */
+    int tz_offset_min;
+    int end_on_next;
+
+    /* Raw splice_info_section bytes; NULL if absent. */
+    const uint8_t *scte35_cmd;
+    size_t scte35_cmd_len;
+    const uint8_t *scte35_out;
+    size_t scte35_out_len;
+    const uint8_t *scte35_in;
+    size_t scte35_in_len;
+
+    const FFmpegRsHlsAttr *attrs;
+    size_t n_attrs;
+} FFmpegRsHlsDateRange;
+
+/* Writes one `#EXT-X-DATERANGE:...\n` line. */
+int ffmpeg_rs_hls_write_daterange(const FFmpegRsHlsDateRange *daterange,
+                                  char *dst, size_t dst_len, size_t *out_required);
+
+typedef enum FFmpegRsHlsCueKind {
+    FFMPEG_RS_HLS_CUE_OUT      = 0, /* #EXT-X-CUE-OUT[:<duration>] */
+    FFMPEG_RS_HLS_CUE_OUT_CONT = 1, /* #EXT-X-CUE-OUT-CONT:ElapsedTime=<e>[,Duration=<d>] */
+    FFMPEG_RS_HLS_CUE_IN       = 2, /* #EXT-X-CUE-IN */
+} FFmpegRsHlsCueKind;
+
+/* Writes one cue marker line. Negative duration_us omits the duration. */
+int ffmpeg_rs_hls_write_cue(uint32_t kind, int64_t duration_us, int64_t elapsed_us,
+                            char *dst, size_t dst_len, size_t *out_required);
+
+typedef struct FFmpegRsHlsKeyframe {
+    int64_t pts_us;
+    int64_t offset;     /* byte offset of the keyframe inside `uri` */
+    int64_t size;       /* byte size of the keyframe */
+    const char *uri;
+    size_t uri_len;
+} FFmpegRsHlsKeyframe;
+
+typedef struct FFmpegRsHlsIframePlaylist {
+    int version;           /* >= 4 */
+    int64_t media_sequence;
+    int64_t end_pts_us;    /* end of the last keyframe's interval */
+    uint32_t playlist_type; /* 0 = none, 1 = EVENT, 2 = VOD */
+    int endlist;
+} FFmpegRsHlsIframePlaylist;
+
+/* Writes an `#EXT-X-I-FRAMES-ONLY` media playlist. Each keyframe lasts until
+ * the next keyframe's pts (or `end_pts_us`); keyframes must be in pts order. */
+int ffmpeg_rs_hls_write_iframe_playlist(const FFmpegRsHlsIframePlaylist *playlist,
+                                        const FFmpegRsHlsKeyframe *keyframes,
+                                        size_t n_keyframes,
+                                        char *dst, size_t dst_len, size_t *out_required);
+
+typedef struct FFmpegRsHlsIframeBandwidth {
+    int64_t peak;    /* bits per second, saturating at INT64_MAX */
+    int64_t average; /* bits per second, saturating at INT64_MAX */
+} FFmpegRsHlsIframeBandwidth;
+
+/* Computes BANDWIDTH/AVERAGE-BANDWIDTH values for an I-frame playlist.
+ * Returns 0 on success, -EINVAL on invalid arguments. */
+int ffmpeg_rs_hls_iframe_bandwidth(const FFmpegRsHlsKeyframe *keyframes, size_t n_keyframes,
+                                   int64_t end_pts_us, FFmpegRsHlsIframeBandwidth *out);
+
+typedef struct FFmpegRsHlsIframeStreamInf {
+    int64_t bandwidth;
+    int64_t average_bandwidth; /* 0 if absent */
+    int width;                 /* 0 if absent */
+    int height;
+    const char *codecs;        /* NULL if absent */
+    size_t codecs_len;
+    const char *uri;
+    size_t uri_len;
+} FFmpegRsHlsIframeStreamInf;
+
+/* Writes one `#EXT-X-I-FRAME-STREAM-INF:...\n` master playlist line. */
+int ffmpeg_rs_hls_write_iframe_stream_inf(const FFmpegRsHlsIframeStreamInf *inf,
+                                          char *dst, size_t dst_len, size_t *out_required);
+
+/* Segment boundary planner mirroring hlsenc's hls_time / hls_init_time /
+ * split_by_time cut rules, plus an optional byte-size cap. */
+
+#define FFMPEG_RS_HLS_CUT_TIME 1 /* cumulative hls_time target reached */
+#define FFMPEG_RS_HLS_CUT_SIZE 2 /* next packet would exceed max_seg_size */
+#define FFMPEG_RS_HLS_CUT_END  4 /* closed by flush */
+
+typedef struct FFmpegRsHlsPlannerConfig {
+    int tb_num;               /* time base of packet pts */
+    int tb_den;
+    int64_t time_us;          /* hls_time */
+    int64_t init_time_us;     /* hls_init_time, 0 to disable */
+    int64_t init_segments;    /* segments using init_time_us (hls_list_size) */
+    int split_by_time;        /* allow cuts on non-keyframes */
+    int64_t max_seg_size;     /* bytes, 0 to disable */
+} FFmpegRsHlsPlannerConfig;
+
+typedef struct FFmpegRsHlsPlannerPacket {
+    int64_t pts;
+    int is_keyframe;
+    int64_t size;
+} FFmpegRsHlsPlannerPacket;
+
+typedef struct FFmpegRsHlsSegmentBoundary {
+    int64_t index;            /* 0-based */
+    int64_t start_pts;
+    int64_t end_pts;
+    int64_t duration_us;      /* rounded to nearest */
+    int64_t size;
+    int64_t first_packet;
+    int64_t n_packets;
+    uint32_t reason;          /* FFMPEG_RS_HLS_CUT_* flags */
+} FFmpegRsHlsSegmentBoundary;
+
+/* Caller-allocated state; treat the fields as private. */
+typedef struct FFmpegRsHlsSegmentPlanner {
+    FFmpegRsHlsPlannerConfig cfg;
+    int64_t first_pts;
+    int64_t seg_index;
+    int64_t seg_start_pts;
+    int64_t seg_size;
+    int64_t seg_first_packet;
+    int64_t seg_packets;
+    int64_t n_packets;
+    int started;
+} FFmpegRsHlsSegmentPlanner;
+
+typedef struct FFmpegRsHlsPlanResult {
+    size_t n_segments_total;
+    size_t n_segments_written;
+    int truncated;
+} FFmpegRsHlsPlanResult;
+
+/* Returns 0 on success, -EINVAL on invalid configuration. */
+int ffmpeg_rs_hls_planner_init(FFmpegRsHlsSegmentPlanner *planner,
+                               const FFmpegRsHlsPlannerConfig *cfg);
+
+/* Feeds one packet in decode order. Returns 1 if a segment was closed right
+ * before this packet (described in `*out`), 0 otherwise, <0 on error. */
+int ffmpeg_rs_hls_planner_push(FFmpegRsHlsSegmentPlanner *planner,
+                               const FFmpegRsHlsPlannerPacket *pkt,
+                               FFmpegRsHlsSegmentBoundary *out);
+
+/* Closes the pending segment at `end_pts`. Returns 1 if a segment was
+ * emitted, 0 if nothing was pending, <0 on error. */
+int ffmpeg_rs_hls_planner_flush(FFmpegRsHlsSegmentPlanner *planner, int64_t end_pts,
+                                FFmpegRsHlsSegmentBoundary *out);
+
+/* Plans a complete recorded trace (e.g. for offline tests). If `segments` is
+ * NULL or `segments_cap` is 0 this performs a size-only pass. */
+int ffmpeg_rs_hls_plan_segments(const FFmpegRsHlsPlannerConfig *cfg,
+                                const FFmpegRsHlsPlannerPacket *packets, size_t n_packets,
+                                int64_t end_pts, FFmpegRsHlsPlanResult *out,
+                                FFmpegRsHlsSegmentBoundary *segments, size_t segments_cap);
+
+/* Segment filename template expansion shared by hlsenc, dashenc and segment. */
+
+#define FFMPEG_RS_HLS_TEMPLATE_STRFTIME           1 /* expand strftime conversions */
+#define FFMPEG_RS_HLS_TEMPLATE_SECOND_LEVEL_INDEX 2 /* strftime mode: %%0Nd -> index */
+#define FFMPEG_RS_HLS_TEMPLATE_MULTIPLE           4 /* allow several index placeholders */
+#define FFMPEG_RS_HLS_TEMPLATE_REQUIRE_INDEX      8 /* fail without an index placeholder */
+
+typedef struct FFmpegRsHlsTemplateParams {
+    uint32_t flags;            /* FFMPEG_RS_HLS_TEMPLATE_* */
+    int64_t index;             /* %d, %0Nd */
+    int64_t variant_index;     /* %v, %0Nv when variant_name is NULL; <0 forbids %v */
+    const char *variant_name;  /* %v replacement, NULL if unnamed */
+    size_t variant_name_len;
+    int64_t epoch_us;          /* time for strftime conversions */
+    int tz_offset_min;
+} FFmpegRsHlsTemplateParams;
+
+/*
+ * Expands a filename template. Supported placeholders:
+ * - `%d`, `%Nd`, `%0Nd`: index, zero padded to N digits (not in strftime mode)
+ * - `%v`, `%0Nv`: variant name or index
+ * - `%Y %y %m %d %e %j %H %M %S %s %F %T %z`: strftime mode only
+ * - `%%0Nd`: index, strftime + second-level mode only
+ * - `%%`: literal percent
+ * Unsupported placeholders, widths above 64 and repeated index placeholders
+ * (without FFMPEG_RS_HLS_TEMPLATE_MULTIPLE) return -EINVAL.
+ */
+int ffmpeg_rs_hls_expand_template(const char *tmpl, size_t tmpl_len,
+                                  const FFmpegRsHlsTemplateParams *params,
+                                  char *dst, size_t dst_len, size_t *out_required);
+
+/* hls_key_info_file support. */
+
+typedef struct FFmpegRsHlsKeyInfo {
+    size_t key_uri_offset;   /* line 1, offsets into the parsed text */
+    size_t key_uri_len;
+    size_t key_file_offset;  /* line 2 */
+    size_t key_file_len;
+    int has_iv;              /* line 3 present */
+    uint8_t iv[16];
+} FFmpegRsHlsKeyInfo;
+
+/* Parses a key info file. The optional IV line must hold 32 hex digits
+ * (optionally prefixed with 0x). Returns 0 on success, -EINVAL otherwise. */
+int ffmpeg_rs_hls_parse_key_info(const uint8_t *text, size_t text_len,
+                                 FFmpegRsHlsKeyInfo *out);
+
+/* Validates key file contents (exactly 16 bytes) and writes them as 32
+ * upper-case hex digits. */
+int ffmpeg_rs_hls_key_to_hex(const uint8_t *key, size_t key_len,
+                             char *dst, size_t dst_len, size_t *out_required);
+
+/* Writes the 16 byte `iv` as 32 upper-case hex digits for
+ * `#EXT-X-KEY:...,IV=0x`, or, if `iv` is NULL, `media_sequence` as a
+ * big-endian 128-bit number in lower case like hlsenc's "%032"PRIx64. */
+int ffmpeg_rs_hls_iv_to_hex(const uint8_t *iv, int64_t media_sequence,
+                            char *dst, size_t dst_len, size_t *out_required);
+
 #ifdef __cplusplus
 }
 #endif
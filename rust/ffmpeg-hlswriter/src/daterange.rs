@@ -0,0 +1,439 @@
+use core::ffi::{c_char, c_int};
+
+use crate::writer::{in_array, in_bytes, in_ref, in_str, out_buf, Writer, EINVAL};
+
+#[repr(u32)]
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum FFmpegRsHlsAttrType {
+    /// `value` is written as a quoted-string.
+    Quoted = 0,
+    /// `value` is raw bytes written as a hexadecimal-sequence (`0x...`).
+    Hex = 1,
+    /// `number_us` is written as a decimal-floating-point (`number_us / 1e6`).
+    Decimal = 2,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsHlsAttr {
+    pub name: *const c_char,
+    pub name_len: usize,
+    pub type_: u32,
+    pub value: *const u8,
+    pub value_len: usize,
+    pub number_us: i64,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsHlsDateRange {
+    pub id: *const c_char,
+    pub id_len: usize,
+    pub class_name: *const c_char,
+    pub class_len: usize,
+    pub start_us: i64,
+    pub has_end: c_int,
+    pub end_us: i64,
+    /// Negative when absent.
+    pub duration_us: i64,
+    /// Negative when absent.
+    pub planned_duration_us: i64,
+    /// Offset from UTC used for START-DATE/END-DATE, in minutes.
+    pub tz_offset_min: c_int,
+    pub end_on_next: c_int,
+    pub scte35_cmd: *const u8,
+    pub scte35_cmd_len: usize,
+    pub scte35_out: *const u8,
+    pub scte35_out_len: usize,
+    pub scte35_in: *const u8,
+    pub scte35_in_len: usize,
+    pub attrs: *const FFmpegRsHlsAttr,
+    pub n_attrs: usize,
+}
+
+#[repr(u32)]
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum FFmpegRsHlsCueKind {
+    Out = 0,
+    OutCont = 1,
+    In = 2,
+}
+
+// Days since 1970-01-01 to (year, month, day) in the proleptic Gregorian
+// calendar.
+pub(crate) fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let y = yoe + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    (if m <= 2 { y + 1 } else { y }, m, d)
+}
+
+/// `YYYY-MM-DDThh:mm:ss.sss` followed by `Z` or `+hh:mm`/`-hh:mm`.
+pub(crate) fn write_iso8601(w: &mut Writer, epoch_us: i64, tz_offset_min: c_int) -> Result<(), c_int> {
+    if !(-24 * 60 < tz_offset_min && tz_offset_min < 24 * 60) {
+        return Err(EINVAL);
+    }
+    let local_us = epoch_us
+        .checked_add(tz_offset_min as i64 * 60_000_000)
+        .ok_or(EINVAL)?;
+    let secs = local_us.div_euclid(1_000_000);
+    let ms = local_us.rem_euclid(1_000_000) / 1000;
+    let days = secs.div_euclid(86_400);
+    let tod = secs.rem_euclid(86_400);
+    let (y, m, d) = civil_from_days(days);
+    if !(0..=9999).contains(&y) {
+        return Err(EINVAL);
+    }
+
+    w.uint_padded(y as u64, 4);
+    w.byte(b'-');
+    w.uint_padded(m as u64, 2);
+    w.byte(b'-');
+    w.uint_padded(d as u64, 2);
+    w.byte(b'T');
+    w.uint_padded((tod / 3600) as u64, 2);
+    w.byte(b':');
+    w.uint_padded((tod / 60 % 60) as u64, 2);
+    w.byte(b':');
+    w.uint_padded((tod % 60) as u64, 2);
+    w.byte(b'.');
+    w.uint_padded(ms as u64, 3);
+    if tz_offset_min == 0 {
+        w.byte(b'Z');
+    } else {
+        w.byte(if tz_offset_min < 0 { b'-' } else { b'+' });
+        let off = tz_offset_min.unsigned_abs();
+        w.uint_padded((off / 60) as u64, 2);
+        w.byte(b':');
+        w.uint_padded((off % 60) as u64, 2);
+    }
+    Ok(())
+}
+
+fn is_client_attr_name(name: &[u8]) -> bool {
+    name.len() > 2
+        && name.starts_with(b"X-")
+        && name
+            .iter()
+            .all(|&b| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'-')
+}
+
+fn write_attr(w: &mut Writer, a: &FFmpegRsHlsAttr) -> Result<(), c_int> {
+    let name = in_str(a.name, a.name_len).ok_or(EINVAL)?;
+    if !is_client_attr_name(name) {
+        return Err(EINVAL);
+    }
+    w.byte(b',');
+    w.bytes(name);
+    w.byte(b'=');
+    match a.type_ {
+        t if t == FFmpegRsHlsAttrType::Quoted as u32 => {
+            w.quoted(in_bytes(a.value, a.value_len).unwrap_or(&[]))?;
+        }
+        t if t == FFmpegRsHlsAttrType::Hex as u32 => {
+            let v = in_bytes(a.value, a.value_len).ok_or(EINVAL)?;
+            if v.is_empty() {
+                return Err(EINVAL);
+            }
+            w.hex_sequence(v);
+        }
+        t if t == FFmpegRsHlsAttrType::Decimal as u32 => w.seconds_us(a.number_us),
+        _ => return Err(EINVAL),
+    }
+    Ok(())
+}
+
+fn write_scte35(w: &mut Writer, name: &[u8], p: *const u8, len: usize) -> Result<(), c_int> {
+    if let Some(v) = in_bytes(p, len) {
+        if v.is_empty() {
+            return Err(EINVAL);
+        }
+        w.byte(b',');
+        w.bytes(name);
+        w.byte(b'=');
+        w.hex_sequence(v);
+    }
+    Ok(())
+}
+
+fn write_daterange(w: &mut Writer, dr: &FFmpegRsHlsDateRange) -> Result<(), c_int> {
+    let id = in_str(dr.id, dr.id_len).ok_or(EINVAL)?;
+    if id.is_empty() {
+        return Err(EINVAL);
+    }
+    let class = in_str(dr.class_name, dr.class_len);
+    // END-ON-NEXT needs a CLASS and excludes an explicit end.
+    if dr.end_on_next != 0 && (class.is_none() || dr.duration_us >= 0 || dr.has_end != 0) {
+        return Err(EINVAL);
+    }
+    if dr.has_end != 0 && dr.end_us < dr.start_us {
+        return Err(EINVAL);
+    }
+    let attrs = in_array(dr.attrs, dr.n_attrs).ok_or(EINVAL)?;
+
+    w.bytes(b"#EXT-X-DATERANGE:ID=");
+    w.quoted(id)?;
+    if let Some(class) = class {
+        w.bytes(b",CLASS=");
+        w.quoted(class)?;
+    }
+    w.bytes(b",START-DATE=\"");
+    write_iso8601(w, dr.start_us, dr.tz_offset_min)?;
+    w.byte(b'"');
+    if dr.has_end != 0 {
+        w.bytes(b",END-DATE=\"");
+        write_iso8601(w, dr.end_us, dr.tz_offset_min)?;
+        w.byte(b'"');
+    }
+    if dr.duration_us >= 0 {
+        w.bytes(b",DURATION=");
+        w.seconds_us(dr.duration_us);
+    }
+    if dr.planned_duration_us >= 0 {
+        w.bytes(b",PLANNED-DURATION=");
+        w.seconds_us(dr.planned_duration_us);
+    }
+    for a in attrs {
+        write_attr(w, a)?;
+    }
+    write_scte35(w, b"SCTE35-CMD", dr.scte35_cmd, dr.scte35_cmd_len)?;
+    write_scte35(w, b"SCTE35-OUT", dr.scte35_out, dr.scte35_out_len)?;
+    write_scte35(w, b"SCTE35-IN", dr.scte35_in, dr.scte35_in_len)?;
+    if dr.end_on_next != 0 {
+        w.bytes(b",END-ON-NEXT=YES");
+    }
+    w.byte(b'\n');
+    Ok(())
+}
+
+fn write_cue(w: &mut Writer, kind: u32, duration_us: i64, elapsed_us: i64) -> Result<(), c_int> {
+    match kind {
+        k if k == FFmpegRsHlsCueKind::Out as u32 => {
+            w.bytes(b"#EXT-X-CUE-OUT");
+            if duration_us >= 0 {
+                w.byte(b':');
+                w.seconds_us(duration_us);
+            }
+        }
+        k if k == FFmpegRsHlsCueKind::OutCont as u32 => {
+            if elapsed_us < 0 {
+                return Err(EINVAL);
+            }
+            w.bytes(b"#EXT-X-CUE-OUT-CONT:ElapsedTime=");
+            w.seconds_us(elapsed_us);
+            if duration_us >= 0 {
+                w.bytes(b",Duration=");
+                w.seconds_us(duration_us);
+            }
+        }
+        k if k == FFmpegRsHlsCueKind::In as u32 => w.bytes(b"#EXT-X-CUE-IN"),
+        _ => return Err(EINVAL),
+    }
+    w.byte(b'\n');
+    Ok(())
+}
+
+// Formats `epoch_us` as an ISO 8601 date-time with millisecond precision.
+// Returns 0 on success, -EINVAL on bad arguments, -ENOSPC if `dst_len` is
+// smaller than `*out_required` (which always receives the size including NUL).
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_hls_format_iso8601(
+    epoch_us: i64,
+    tz_offset_min: c_int,
+    dst: *mut c_char,
+    dst_len: usize,
+    out_required: *mut usize,
+) -> c_int {
+    let mut w = Writer::new(out_buf(dst, dst_len));
+    if let Err(e) = write_iso8601(&mut w, epoch_us, tz_offset_min) {
+        return e;
+    }
+    w.finish(out_required)
+}
+
+// Writes one `#EXT-X-DATERANGE` line. Same return convention as
+// ffmpeg_rs_hls_format_iso8601().
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_hls_write_daterange(
+    daterange: *const FFmpegRsHlsDateRange,
+    dst: *mut c_char,
+    dst_len: usize,
+    out_required: *mut usize,
+) -> c_int {
+    let dr = match in_ref(daterange) {
+        Some(dr) => dr,
+        None => return EINVAL,
+    };
+    let mut w = Writer::new(out_buf(dst, dst_len));
+    if let Err(e) = write_daterange(&mut w, dr) {
+        return e;
+    }
+    w.finish(out_required)
+}
+
+// Writes one `#EXT-X-CUE-OUT`, `#EXT-X-CUE-OUT-CONT` or `#EXT-X-CUE-IN` line.
+// Negative `duration_us` omits the duration. Same return convention as
+// ffmpeg_rs_hls_format_iso8601().
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_hls_write_cue(
+    kind: u32,
+    duration_us: i64,
+    elapsed_us: i64,
+    dst: *mut c_char,
+    dst_len: usize,
+    out_required: *mut usize,
+) -> c_int {
+    let mut w = Writer::new(out_buf(dst, dst_len));
+    if let Err(e) = write_cue(&mut w, kind, duration_us, elapsed_us) {
+        return e;
+    }
+    w.finish(out_required)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::writer::ENOSPC;
+
+    fn empty_daterange() -> FFmpegRsHlsDateRange {
+        FFmpegRsHlsDateRange {
+            id: core::ptr::null(),
+            id_len: 0,
+            class_name: core::ptr::null(),
+            class_len: 0,
+            start_us: 0,
+            has_end: 0,
+            end_us: 0,
+            duration_us: -1,
+            planned_duration_us: -1,
+            tz_offset_min: 0,
+            end_on_next: 0,
+            scte35_cmd: core::ptr::null(),
+            scte35_cmd_len: 0,
+            scte35_out: core::ptr::null(),
+            scte35_out_len: 0,
+            scte35_in: core::ptr::null(),
+            scte35_in_len: 0,
+            attrs: core::ptr::null(),
+            n_attrs: 0,
+        }
+    }
+
+    #[test]
+    fn formats_iso8601() {
+        let mut buf = [0i8; 64];
+        let mut req = 0usize;
+        // 2021-03-04T05:06:07.089Z
+        let t = 1_614_834_367_089_000i64;
+        let r = ffmpeg_rs_hls_format_iso8601(t, 0, buf.as_mut_ptr(), buf.len(), &mut req);
+        assert_eq!(r, 0);
+        let s = unsafe { core::slice::from_raw_parts(buf.as_ptr() as *const u8, req - 1) };
+        assert_eq!(s, b"2021-03-04T05:06:07.089Z");
+
+        let r = ffmpeg_rs_hls_format_iso8601(t, -90, buf.as_mut_ptr(), buf.len(), &mut req);
+        assert_eq!(r, 0);
+        let s = unsafe { core::slice::from_raw_parts(buf.as_ptr() as *const u8, req - 1) };
+        assert_eq!(s, b"2021-03-04T03:36:07.089-01:30");
+    }
+
+    #[test]
+    fn writes_daterange_with_scte35() {
+        let id = b"splice-6FFFFFF0";
+        let class = b"com.example.ad";
+        let out = [0xFCu8, 0x30, 0x21];
+        let attr_name = b"X-COM-EXAMPLE-BEACON";
+        let attr_value = b"https://x/y";
+        let attrs = [FFmpegRsHlsAttr {
+            name: attr_name.as_ptr() as *const c_char,
+            name_len: attr_name.len(),
+            type_: FFmpegRsHlsAttrType::Quoted as u32,
+            value: attr_value.as_ptr(),
+            value_len: attr_value.len(),
+            number_us: 0,
+        }];
+        let dr = FFmpegRsHlsDateRange {
+            id: id.as_ptr() as *const c_char,
+            id_len: id.len(),
+            class_name: class.as_ptr() as *const c_char,
+            class_len: class.len(),
+            start_us: 1_400_000_000_000_000,
+            planned_duration_us: 59_993_000,
+            scte35_out: out.as_ptr(),
+            scte35_out_len: out.len(),
+            attrs: attrs.as_ptr(),
+            n_attrs: attrs.len(),
+            ..empty_daterange()
+        };
+
+        let mut req = 0usize;
+        let r = ffmpeg_rs_hls_write_daterange(&dr, core::ptr::null_mut(), 0, &mut req);
+        assert_eq!(r, ENOSPC);
+
+        let mut buf = [0i8; 256];
+        let r = ffmpeg_rs_hls_write_daterange(&dr, buf.as_mut_ptr(), buf.len(), &mut req);
+        assert_eq!(r, 0);
+        let s = unsafe { core::slice::from_raw_parts(buf.as_ptr() as *const u8, req - 1) };
+        assert_eq!(
+            s,
+            &b"#EXT-X-DATERANGE:ID=\"splice-6FFFFFF0\",CLASS=\"com.example.ad\",\
+START-DATE=\"2014-05-13T16:53:20.000Z\",PLANNED-DURATION=59.993,\
+X-COM-EXAMPLE-BEACON=\"https://x/y\",SCTE35-OUT=0xFC3021\n"[..]
+        );
+    }
+
+    #[test]
+    fn rejects_bad_daterange() {
+        let id = b"a\"b";
+        let mut dr = FFmpegRsHlsDateRange {
+            id: id.as_ptr() as *const c_char,
+            id_len: id.len(),
+            ..empty_daterange()
+        };
+        let mut req = 0usize;
+        assert_eq!(ffmpeg_rs_hls_write_daterange(&dr, core::ptr::null_mut(), 0, &mut req), EINVAL);
+
+        dr.id_len = 1;
+        dr.end_on_next = 1;
+        assert_eq!(ffmpeg_rs_hls_write_daterange(&dr, core::ptr::null_mut(), 0, &mut req), EINVAL);
+
+        let name = b"X-lower";
+        let attrs = [FFmpegRsHlsAttr {
+            name: name.as_ptr() as *const c_char,
+            name_len: name.len(),
+            type_: FFmpegRsHlsAttrType::Decimal as u32,
+            value: core::ptr::null(),
+            value_len: 0,
+            number_us: 1,
+        }];
+        dr.end_on_next = 0;
+        dr.attrs = attrs.as_ptr();
+        dr.n_attrs = 1;
+        assert_eq!(ffmpeg_rs_hls_write_daterange(&dr, core::ptr::null_mut(), 0, &mut req), EINVAL);
+    }
+
+    #[test]
+    fn writes_cues() {
+        let mut buf = [0i8; 64];
+        let mut req = 0usize;
+        let r = ffmpeg_rs_hls_write_cue(FFmpegRsHlsCueKind::Out as u32, 30_000_000, 0, buf.as_mut_ptr(), buf.len(), &mut req);
+        assert_eq!(r, 0);
+        let s = unsafe { core::slice::from_raw_parts(buf.as_ptr() as *const u8, req - 1) };
+        assert_eq!(s, b"#EXT-X-CUE-OUT:30\n");
+
+        let r = ffmpeg_rs_hls_write_cue(FFmpegRsHlsCueKind::OutCont as u32, 30_000_000, 12_500_000, buf.as_mut_ptr(), buf.len(), &mut req);
+        assert_eq!(r, 0);
+        let s = unsafe { core::slice::from_raw_parts(buf.as_ptr() as *const u8, req - 1) };
+        assert_eq!(s, b"#EXT-X-CUE-OUT-CONT:ElapsedTime=12.5,Duration=30\n");
+
+        let r = ffmpeg_rs_hls_write_cue(FFmpegRsHlsCueKind::In as u32, -1, 0, buf.as_mut_ptr(), buf.len(), &mut req);
+        assert_eq!(r, 0);
+        let s = unsafe { core::slice::from_raw_parts(buf.as_ptr() as *const u8, req - 1) };
+        assert_eq!(s, b"#EXT-X-CUE-IN\n");
+    }
+}
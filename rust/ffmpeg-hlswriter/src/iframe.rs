@@ -0,0 +1,335 @@
+use core::ffi::{c_char, c_int};
+
+use crate::writer::{in_array, in_ref, in_str, out_buf, out_ref, Writer, EINVAL};
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsHlsKeyframe {
+    pub pts_us: i64,
+    /// Byte offset of the keyframe inside `uri`.
+    pub offset: i64,
+    /// Byte size of the keyframe (including any container overhead to read).
+    pub size: i64,
+    pub uri: *const c_char,
+    pub uri_len: usize,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsHlsIframePlaylist {
+    /// Must be at least 4 (EXT-X-BYTERANGE / EXT-X-I-FRAMES-ONLY).
+    pub version: c_int,
+    pub media_sequence: i64,
+    /// End of the last keyframe's interval; the last keyframe lasts until here.
+    pub end_pts_us: i64,
+    /// 0 = none, 1 = EVENT, 2 = VOD (same values as hlsenc's PlaylistType).
+    pub playlist_type: u32,
+    pub endlist: c_int,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsHlsIframeStreamInf {
+    pub bandwidth: i64,
+    /// 0 if absent.
+    pub average_bandwidth: i64,
+    /// 0 if absent.
+    pub width: c_int,
+    pub height: c_int,
+    /// NULL if absent.
+    pub codecs: *const c_char,
+    pub codecs_len: usize,
+    pub uri: *const c_char,
+    pub uri_len: usize,
+}
+
+#[repr(C)]
+pub struct FFmpegRsHlsIframeBandwidth {
+    pub peak: i64,
+    pub average: i64,
+}
+
+fn keyframe_duration(kfs: &[FFmpegRsHlsKeyframe], i: usize, end_pts_us: i64) -> i64 {
+    let next = kfs.get(i + 1).map(|k| k.pts_us).unwrap_or(end_pts_us);
+    next.saturating_sub(kfs[i].pts_us)
+}
+
+// Bits over a duration in microseconds as bits per second, saturating at
+// i64::MAX when a large keyframe spans only a few microseconds.
+fn bits_per_second(bits: i64, us: i64) -> i64 {
+    i64::try_from(bits as i128 * 1_000_000 / us as i128).unwrap_or(i64::MAX)
+}
+
+fn validate(kfs: &[FFmpegRsHlsKeyframe], end_pts_us: i64) -> Result<(), c_int> {
+    for (i, k) in kfs.iter().enumerate() {
+        if k.offset < 0 || k.size <= 0 || k.uri.is_null() || k.uri_len == 0 {
+            return Err(EINVAL);
+        }
+        if keyframe_duration(kfs, i, end_pts_us) < 0 {
+            return Err(EINVAL);
+        }
+    }
+    Ok(())
+}
+
+fn write_iframe_playlist(
+    w: &mut Writer,
+    pl: &FFmpegRsHlsIframePlaylist,
+    kfs: &[FFmpegRsHlsKeyframe],
+) -> Result<(), c_int> {
+    if pl.version < 4 || pl.media_sequence < 0 || pl.playlist_type > 2 {
+        return Err(EINVAL);
+    }
+    validate(kfs, pl.end_pts_us)?;
+
+    let max_us = (0..kfs.len())
+        .map(|i| keyframe_duration(kfs, i, pl.end_pts_us))
+        .max()
+        .unwrap_or(0);
+    // EXT-X-TARGETDURATION must not be below any rounded EXTINF duration.
+    let target = (max_us as u64).div_ceil(1_000_000);
+
+    w.bytes(b"#EXTM3U\n#EXT-X-VERSION:");
+    w.uint(pl.version as u64);
+    w.bytes(b"\n#EXT-X-TARGETDURATION:");
+    w.uint(target);
+    w.bytes(b"\n#EXT-X-MEDIA-SEQUENCE:");
+    w.uint(pl.media_sequence as u64);
+    w.byte(b'\n');
+    match pl.playlist_type {
+        1 => w.bytes(b"#EXT-X-PLAYLIST-TYPE:EVENT\n"),
+        2 => w.bytes(b"#EXT-X-PLAYLIST-TYPE:VOD\n"),
+        _ => {}
+    }
+    w.bytes(b"#EXT-X-I-FRAMES-ONLY\n");
+
+    for (i, k) in kfs.iter().enumerate() {
+        w.bytes(b"#EXTINF:");
+        w.seconds_fixed6(keyframe_duration(kfs, i, pl.end_pts_us));
+        w.bytes(b",\n#EXT-X-BYTERANGE:");
+        w.uint(k.size as u64);
+        w.byte(b'@');
+        w.uint(k.offset as u64);
+        w.byte(b'\n');
+        w.bytes(in_str(k.uri, k.uri_len).ok_or(EINVAL)?);
+        w.byte(b'\n');
+    }
+    if pl.endlist != 0 {
+        w.bytes(b"#EXT-X-ENDLIST\n");
+    }
+    Ok(())
+}
+
+fn write_iframe_stream_inf(w: &mut Writer, inf: &FFmpegRsHlsIframeStreamInf) -> Result<(), c_int> {
+    let uri = in_str(inf.uri, inf.uri_len).ok_or(EINVAL)?;
+    if inf.bandwidth <= 0 || inf.average_bandwidth < 0 || uri.is_empty() {
+        return Err(EINVAL);
+    }
+    w.bytes(b"#EXT-X-I-FRAME-STREAM-INF:BANDWIDTH=");
+    w.uint(inf.bandwidth as u64);
+    if inf.average_bandwidth > 0 {
+        w.bytes(b",AVERAGE-BANDWIDTH=");
+        w.uint(inf.average_bandwidth as u64);
+    }
+    if inf.width > 0 && inf.height > 0 {
+        w.bytes(b",RESOLUTION=");
+        w.uint(inf.width as u64);
+        w.byte(b'x');
+        w.uint(inf.height as u64);
+    }
+    if let Some(codecs) = in_str(inf.codecs, inf.codecs_len) {
+        if !codecs.is_empty() {
+            w.bytes(b",CODECS=");
+            w.quoted(codecs)?;
+        }
+    }
+    w.bytes(b",URI=");
+    w.quoted(uri)?;
+    w.byte(b'\n');
+    Ok(())
+}
+
+// Computes peak and average bits per second of an I-frame playlist, i.e. the
+// per-keyframe `size * 8 / duration` maximum and the overall total. Zero
+// duration keyframes are excluded from the peak.
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_hls_iframe_bandwidth(
+    keyframes: *const FFmpegRsHlsKeyframe,
+    n_keyframes: usize,
+    end_pts_us: i64,
+    out: *mut FFmpegRsHlsIframeBandwidth,
+) -> c_int {
+    let kfs = match in_array(keyframes, n_keyframes) {
+        Some(k) => k,
+        None => return EINVAL,
+    };
+    let out = match out_ref(out) {
+        Some(out) => out,
+        None => return EINVAL,
+    };
+    if let Err(e) = validate(kfs, end_pts_us) {
+        return e;
+    }
+    let mut peak = 0i64;
+    let mut total_bits = 0i64;
+    for (i, k) in kfs.iter().enumerate() {
+        let bits = k.size.saturating_mul(8);
+        total_bits = total_bits.saturating_add(bits);
+        let d = keyframe_duration(kfs, i, end_pts_us);
+        if d > 0 {
+            peak = peak.max(bits_per_second(bits, d));
+        }
+    }
+    let span = kfs
+        .first()
+        .map(|k| end_pts_us.saturating_sub(k.pts_us))
+        .unwrap_or(0);
+    let average = if span > 0 {
+        bits_per_second(total_bits, span)
+    } else {
+        0
+    };
+    out.peak = peak;
+    out.average = average;
+    0
+}
+
+// Writes an EXT-X-I-FRAMES-ONLY media playlist with one EXT-X-BYTERANGE entry
+// per keyframe. Uses the size query then fill convention of
+// ffmpeg_rs_hls_write_daterange().
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_hls_write_iframe_playlist(
+    playlist: *const FFmpegRsHlsIframePlaylist,
+    keyframes: *const FFmpegRsHlsKeyframe,
+    n_keyframes: usize,
+    dst: *mut c_char,
+    dst_len: usize,
+    out_required: *mut usize,
+) -> c_int {
+    let (pl, kfs) = match (in_ref(playlist), in_array(keyframes, n_keyframes)) {
+        (Some(pl), Some(kfs)) => (pl, kfs),
+        _ => return EINVAL,
+    };
+    let mut w = Writer::new(out_buf(dst, dst_len));
+    if let Err(e) = write_iframe_playlist(&mut w, pl, kfs) {
+        return e;
+    }
+    w.finish(out_required)
+}
+
+// Writes the master playlist `#EXT-X-I-FRAME-STREAM-INF` line.
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_hls_write_iframe_stream_inf(
+    inf: *const FFmpegRsHlsIframeStreamInf,
+    dst: *mut c_char,
+    dst_len: usize,
+    out_required: *mut usize,
+) -> c_int {
+    let inf = match in_ref(inf) {
+        Some(inf) => inf,
+        None => return EINVAL,
+    };
+    let mut w = Writer::new(out_buf(dst, dst_len));
+    if let Err(e) = write_iframe_stream_inf(&mut w, inf) {
+        return e;
+    }
+    w.finish(out_required)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kf(pts_us: i64, offset: i64, size: i64, uri: &'static [u8]) -> FFmpegRsHlsKeyframe {
+        FFmpegRsHlsKeyframe {
+            pts_us,
+            offset,
+            size,
+            uri: uri.as_ptr() as *const c_char,
+            uri_len: uri.len(),
+        }
+    }
+
+    #[test]
+    fn writes_iframe_playlist() {
+        let kfs = [
+            kf(0, 376, 18000, b"seg0.ts"),
+            kf(2_002_000, 94000, 17000, b"seg0.ts"),
+            kf(4_004_000, 564, 19000, b"seg1.ts"),
+        ];
+        let pl = FFmpegRsHlsIframePlaylist {
+            version: 4,
+            media_sequence: 0,
+            end_pts_us: 6_000_000,
+            playlist_type: 2,
+            endlist: 1,
+        };
+        let mut req = 0usize;
+        let r = ffmpeg_rs_hls_write_iframe_playlist(&pl, kfs.as_ptr(), kfs.len(), core::ptr::null_mut(), 0, &mut req);
+        assert_eq!(r, crate::writer::ENOSPC);
+
+        let mut buf = [0i8; 512];
+        let r = ffmpeg_rs_hls_write_iframe_playlist(&pl, kfs.as_ptr(), kfs.len(), buf.as_mut_ptr(), buf.len(), &mut req);
+        assert_eq!(r, 0);
+        let s = unsafe { core::slice::from_raw_parts(buf.as_ptr() as *const u8, req - 1) };
+        assert_eq!(
+            s,
+            &b"#EXTM3U\n#EXT-X-VERSION:4\n#EXT-X-TARGETDURATION:3\n#EXT-X-MEDIA-SEQUENCE:0\n\
+#EXT-X-PLAYLIST-TYPE:VOD\n#EXT-X-I-FRAMES-ONLY\n\
+#EXTINF:2.002000,\n#EXT-X-BYTERANGE:18000@376\nseg0.ts\n\
+#EXTINF:2.002000,\n#EXT-X-BYTERANGE:17000@94000\nseg0.ts\n\
+#EXTINF:1.996000,\n#EXT-X-BYTERANGE:19000@564\nseg1.ts\n\
+#EXT-X-ENDLIST\n"[..]
+        );
+
+        let mut bw = FFmpegRsHlsIframeBandwidth { peak: 0, average: 0 };
+        assert_eq!(ffmpeg_rs_hls_iframe_bandwidth(kfs.as_ptr(), kfs.len(), 6_000_000, &mut bw), 0);
+        assert_eq!(bw.peak, 19000 * 8 * 1_000_000 / 1_996_000);
+        assert_eq!(bw.average, 54000 * 8 / 6);
+
+        // A huge keyframe over one microsecond saturates instead of wrapping.
+        let kfs = [kf(0, 0, i64::MAX / 8, b"a.ts")];
+        assert_eq!(ffmpeg_rs_hls_iframe_bandwidth(kfs.as_ptr(), 1, 1, &mut bw), 0);
+        assert_eq!((bw.peak, bw.average), (i64::MAX, i64::MAX));
+    }
+
+    #[test]
+    fn rejects_unordered_keyframes() {
+        let kfs = [kf(2_000_000, 0, 10, b"a.ts"), kf(1_000_000, 10, 10, b"a.ts")];
+        let pl = FFmpegRsHlsIframePlaylist {
+            version: 4,
+            media_sequence: 0,
+            end_pts_us: 3_000_000,
+            playlist_type: 0,
+            endlist: 0,
+        };
+        let mut req = 0usize;
+        let r = ffmpeg_rs_hls_write_iframe_playlist(&pl, kfs.as_ptr(), kfs.len(), core::ptr::null_mut(), 0, &mut req);
+        assert_eq!(r, EINVAL);
+    }
+
+    #[test]
+    fn writes_iframe_stream_inf() {
+        let codecs = b"avc1.4d401f";
+        let uri = b"iframes.m3u8";
+        let inf = FFmpegRsHlsIframeStreamInf {
+            bandwidth: 76000,
+            average_bandwidth: 0,
+            width: 1280,
+            height: 720,
+            codecs: codecs.as_ptr() as *const c_char,
+            codecs_len: codecs.len(),
+            uri: uri.as_ptr() as *const c_char,
+            uri_len: uri.len(),
+        };
+        let mut buf = [0i8; 128];
+        let mut req = 0usize;
+        let r = ffmpeg_rs_hls_write_iframe_stream_inf(&inf, buf.as_mut_ptr(), buf.len(), &mut req);
+        assert_eq!(r, 0);
+        let s = unsafe { core::slice::from_raw_parts(buf.as_ptr() as *const u8, req - 1) };
+        assert_eq!(
+            s,
+            &b"#EXT-X-I-FRAME-STREAM-INF:BANDWIDTH=76000,RESOLUTION=1280x720,CODECS=\"avc1.4d401f\",URI=\"iframes.m3u8\"\n"[..]
+        );
+    }
+}
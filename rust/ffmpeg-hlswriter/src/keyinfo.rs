@@ -0,0 +1,220 @@
+use core::ffi::{c_char, c_int};
+
+use crate::writer::{in_bytes, out_buf, out_ref, Writer, EINVAL};
+
+/// AES-128 key and IV size.
+const KEYSIZE: usize = 16;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsHlsKeyInfo {
+    /// Line 1: URI written into `#EXT-X-KEY`.
+    pub key_uri_offset: usize,
+    pub key_uri_len: usize,
+    /// Line 2: path of the file holding the 16 byte key.
+    pub key_file_offset: usize,
+    pub key_file_len: usize,
+    /// Line 3 (optional): explicit IV.
+    pub has_iv: c_int,
+    pub iv: [u8; KEYSIZE],
+}
+
+fn hex_val(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
+
+fn trim_ws(mut s: &[u8]) -> &[u8] {
+    while let Some((&b, rest)) = s.split_first() {
+        if b != b' ' && b != b'\t' {
+            break;
+        }
+        s = rest;
+    }
+    while let Some((&b, rest)) = s.split_last() {
+        if b != b' ' && b != b'\t' && b != b'\r' {
+            break;
+        }
+        s = rest;
+    }
+    s
+}
+
+// 32 hex digits, optionally prefixed with 0x like the `IV=` attribute.
+fn parse_iv(s: &[u8]) -> Option<[u8; KEYSIZE]> {
+    let s = s
+        .strip_prefix(b"0x")
+        .or_else(|| s.strip_prefix(b"0X"))
+        .unwrap_or(s);
+    if s.len() != KEYSIZE * 2 {
+        return None;
+    }
+    let mut iv = [0u8; KEYSIZE];
+    for (i, pair) in s.chunks_exact(2).enumerate() {
+        iv[i] = (hex_val(pair[0])? << 4) | hex_val(pair[1])?;
+    }
+    Some(iv)
+}
+
+fn parse_key_info(data: &[u8]) -> Result<FFmpegRsHlsKeyInfo, c_int> {
+    let mut lines = data.split(|&b| b == b'\n').map(trim_ws);
+    let base = data.as_ptr() as usize;
+    let span = |s: &[u8]| (s.as_ptr() as usize - base, s.len());
+
+    let key_uri = lines.next().unwrap_or(&[]);
+    let key_file = lines.next().unwrap_or(&[]);
+    if key_uri.is_empty() || key_file.is_empty() {
+        return Err(EINVAL);
+    }
+    let (key_uri_offset, key_uri_len) = span(key_uri);
+    let (key_file_offset, key_file_len) = span(key_file);
+
+    let mut info = FFmpegRsHlsKeyInfo {
+        key_uri_offset,
+        key_uri_len,
+        key_file_offset,
+        key_file_len,
+        has_iv: 0,
+        iv: [0; KEYSIZE],
+    };
+    if let Some(iv) = lines.next().filter(|l| !l.is_empty()) {
+        info.iv = parse_iv(iv).ok_or(EINVAL)?;
+        info.has_iv = 1;
+    }
+    Ok(info)
+}
+
+// Parses an `hls_key_info_file` (key URI, key file path, optional IV; one per
+// line). Returns 0 on success, -EINVAL if a mandatory line is missing or the
+// IV is not 32 hex digits. Offsets point into `text`.
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_hls_parse_key_info(
+    text: *const u8,
+    text_len: usize,
+    out: *mut FFmpegRsHlsKeyInfo,
+) -> c_int {
+    let (data, out) = match (in_bytes(text, text_len), out_ref(out)) {
+        (Some(d), Some(o)) => (d, o),
+        _ => return EINVAL,
+    };
+    match parse_key_info(data) {
+        Ok(info) => {
+            *out = info;
+            0
+        }
+        Err(e) => e,
+    }
+}
+
+// Validates the contents of a key file (exactly 16 bytes) and writes its
+// upper-case hex form. Same size query then fill convention as
+// ffmpeg_rs_hls_write_daterange().
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_hls_key_to_hex(
+    key: *const u8,
+    key_len: usize,
+    dst: *mut c_char,
+    dst_len: usize,
+    out_required: *mut usize,
+) -> c_int {
+    let key = match in_bytes(key, key_len) {
+        Some(k) if k.len() == KEYSIZE => k,
+        _ => return EINVAL,
+    };
+    let mut w = Writer::new(out_buf(dst, dst_len));
+    w.hex(key, true);
+    w.finish(out_required)
+}
+
+// Writes the IV for a segment as 32 hex digits (no 0x prefix): `iv` in
+// upper case if non-NULL, otherwise the media sequence number as a
+// big-endian 128-bit integer in lower case, matching hlsenc's "%032"PRIx64.
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_hls_iv_to_hex(
+    iv: *const u8,
+    media_sequence: i64,
+    dst: *mut c_char,
+    dst_len: usize,
+    out_required: *mut usize,
+) -> c_int {
+    let mut buf = [0u8; KEYSIZE];
+    let explicit = match in_bytes(iv, KEYSIZE) {
+        Some(iv) => {
+            buf.copy_from_slice(iv);
+            true
+        }
+        None => {
+            if media_sequence < 0 {
+                return EINVAL;
+            }
+            buf[8..].copy_from_slice(&(media_sequence as u64).to_be_bytes());
+            false
+        }
+    };
+    let mut w = Writer::new(out_buf(dst, dst_len));
+    w.hex(&buf, explicit);
+    w.finish(out_required)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &[u8]) -> Result<FFmpegRsHlsKeyInfo, c_int> {
+        let mut info = FFmpegRsHlsKeyInfo {
+            key_uri_offset: 0,
+            key_uri_len: 0,
+            key_file_offset: 0,
+            key_file_len: 0,
+            has_iv: 0,
+            iv: [0; KEYSIZE],
+        };
+        match ffmpeg_rs_hls_parse_key_info(text.as_ptr(), text.len(), &mut info) {
+            0 => Ok(info),
+            e => Err(e),
+        }
+    }
+
+    #[test]
+    fn parses_key_info() {
+        let text = b"https://example.com/enc.key\r\n/tmp/enc.key\r\n0x0123456789abcdef0123456789ABCDEF\r\n";
+        let info = parse(text).unwrap();
+        assert_eq!(&text[info.key_uri_offset..][..info.key_uri_len], b"https://example.com/enc.key");
+        assert_eq!(&text[info.key_file_offset..][..info.key_file_len], b"/tmp/enc.key");
+        assert_eq!(info.has_iv, 1);
+        assert_eq!(info.iv[0], 0x01);
+        assert_eq!(info.iv[15], 0xEF);
+
+        let info = parse(b"enc.key\nenc.key").unwrap();
+        assert_eq!(info.has_iv, 0);
+
+        assert_eq!(parse(b"enc.key\n\n").err(), Some(EINVAL));
+        assert_eq!(parse(b"enc.key\nenc.key\n0123\n").err(), Some(EINVAL));
+        assert_eq!(parse(b"enc.key\nenc.key\nzz23456789abcdef0123456789abcdef\n").err(), Some(EINVAL));
+    }
+
+    #[test]
+    fn formats_key_and_iv() {
+        let mut buf = [0i8; 33];
+        let mut req = 0usize;
+        let hex = |buf: &[i8]| unsafe { core::slice::from_raw_parts(buf.as_ptr() as *const u8, 32) };
+
+        assert_eq!(ffmpeg_rs_hls_iv_to_hex(core::ptr::null(), 0xabcd, buf.as_mut_ptr(), buf.len(), &mut req), 0);
+        assert_eq!(req, 33);
+        assert_eq!(hex(&buf), b"0000000000000000000000000000abcd");
+
+        let iv = [0xABu8; 16];
+        assert_eq!(ffmpeg_rs_hls_iv_to_hex(iv.as_ptr(), 7, buf.as_mut_ptr(), buf.len(), &mut req), 0);
+        assert_eq!(hex(&buf), b"ABABABABABABABABABABABABABABABAB");
+
+        let key = [0u8; 15];
+        assert_eq!(ffmpeg_rs_hls_key_to_hex(key.as_ptr(), key.len(), buf.as_mut_ptr(), buf.len(), &mut req), EINVAL);
+        let key: [u8; 16] = core::array::from_fn(|i| i as u8);
+        assert_eq!(ffmpeg_rs_hls_key_to_hex(key.as_ptr(), key.len(), buf.as_mut_ptr(), buf.len(), &mut req), 0);
+        assert_eq!(hex(&buf), b"000102030405060708090A0B0C0D0E0F");
+    }
+}
@@ -2,6 +2,19 @@
 
 use core::ffi::{c_char, c_int};
 
+mod daterange;
+mod iframe;
+mod keyinfo;
+mod planner;
+mod template;
+mod writer;
+
+pub use daterange::*;
+pub use iframe::*;
+pub use keyinfo::*;
+pub use planner::*;
+pub use template::*;
+
 #[cfg(not(test))]
 #[panic_handler]
 fn panic_handler(_: &core::panic::PanicInfo) -> ! {
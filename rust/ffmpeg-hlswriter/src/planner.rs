@@ -0,0 +1,409 @@
+use core::ffi::c_int;
+
+use crate::writer::{in_array, in_ref, out_array, out_ref, EINVAL};
+
+pub const FFMPEG_RS_HLS_CUT_TIME: u32 = 1;
+pub const FFMPEG_RS_HLS_CUT_SIZE: u32 = 2;
+pub const FFMPEG_RS_HLS_CUT_END: u32 = 4;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsHlsPlannerConfig {
+    /// Time base of packet pts values.
+    pub tb_num: c_int,
+    pub tb_den: c_int,
+    /// `hls_time`, in microseconds.
+    pub time_us: i64,
+    /// `hls_init_time`, in microseconds; 0 disables it.
+    pub init_time_us: i64,
+    /// Number of leading segments using `init_time_us` (hlsenc uses
+    /// `hls_list_size`).
+    pub init_segments: i64,
+    /// Allow cuts on non-keyframes (`hls_flags split_by_time`).
+    pub split_by_time: c_int,
+    /// Cut early once a segment would exceed this many bytes; 0 disables it.
+    pub max_seg_size: i64,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsHlsPlannerPacket {
+    pub pts: i64,
+    pub is_keyframe: c_int,
+    pub size: i64,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsHlsSegmentBoundary {
+    /// 0-based segment index.
+    pub index: i64,
+    pub start_pts: i64,
+    pub end_pts: i64,
+    /// `end_pts - start_pts` in microseconds, rounded to nearest.
+    pub duration_us: i64,
+    pub size: i64,
+    pub first_packet: i64,
+    pub n_packets: i64,
+    /// FFMPEG_RS_HLS_CUT_* flags explaining why the segment was closed.
+    pub reason: u32,
+}
+
+/// Caller-allocated planner state; fields are private to the implementation.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsHlsSegmentPlanner {
+    pub cfg: FFmpegRsHlsPlannerConfig,
+    pub first_pts: i64,
+    pub seg_index: i64,
+    pub seg_start_pts: i64,
+    pub seg_size: i64,
+    pub seg_first_packet: i64,
+    pub seg_packets: i64,
+    pub n_packets: i64,
+    pub started: c_int,
+}
+
+#[repr(C)]
+pub struct FFmpegRsHlsPlanResult {
+    pub n_segments_total: usize,
+    pub n_segments_written: usize,
+    pub truncated: c_int,
+}
+
+fn rescale_rnd(v: i64, num: i64, den: i64) -> i64 {
+    let n = v as i128 * num as i128;
+    let d = den as i128;
+    let r = if n >= 0 { (n + d / 2) / d } else { (n - d / 2) / d };
+    r.clamp(i64::MIN as i128, i64::MAX as i128) as i64
+}
+
+impl FFmpegRsHlsSegmentPlanner {
+    fn new(cfg: &FFmpegRsHlsPlannerConfig) -> Result<Self, c_int> {
+        if cfg.tb_num <= 0
+            || cfg.tb_den <= 0
+            || cfg.time_us <= 0
+            || cfg.init_time_us < 0
+            || cfg.init_segments < 0
+            || cfg.max_seg_size < 0
+        {
+            return Err(EINVAL);
+        }
+        Ok(FFmpegRsHlsSegmentPlanner {
+            cfg: *cfg,
+            first_pts: 0,
+            seg_index: 0,
+            seg_start_pts: 0,
+            seg_size: 0,
+            seg_first_packet: 0,
+            seg_packets: 0,
+            n_packets: 0,
+            started: 0,
+        })
+    }
+
+    // Cumulative target end of segment `index`, relative to the first pts,
+    // mirroring hlsenc's `recording_time * number` with the init list rule.
+    fn target_end_us(&self, index: i64) -> i64 {
+        let n = index.saturating_add(1);
+        let c = &self.cfg;
+        if c.init_time_us > 0 && c.init_segments > 0 {
+            if n <= c.init_segments {
+                return c.init_time_us.saturating_mul(n);
+            }
+            return c
+                .init_time_us
+                .saturating_mul(c.init_segments)
+                .saturating_add(c.time_us.saturating_mul(n - c.init_segments));
+        }
+        c.time_us.saturating_mul(n)
+    }
+
+    // Compares `pts - first_pts` (stream time base) against `us` like
+    // av_compare_ts().
+    fn elapsed_reaches(&self, pts: i64, us: i64) -> bool {
+        let elapsed = pts as i128 - self.first_pts as i128;
+        elapsed * self.cfg.tb_num as i128 * 1_000_000 >= us as i128 * self.cfg.tb_den as i128
+    }
+
+    fn close(&mut self, end_pts: i64, reason: u32) -> FFmpegRsHlsSegmentBoundary {
+        let b = FFmpegRsHlsSegmentBoundary {
+            index: self.seg_index,
+            start_pts: self.seg_start_pts,
+            end_pts,
+            duration_us: rescale_rnd(
+                end_pts.saturating_sub(self.seg_start_pts),
+                self.cfg.tb_num as i64 * 1_000_000,
+                self.cfg.tb_den as i64,
+            ),
+            size: self.seg_size,
+            first_packet: self.seg_first_packet,
+            n_packets: self.seg_packets,
+            reason,
+        };
+        self.seg_index += 1;
+        self.seg_start_pts = end_pts;
+        self.seg_size = 0;
+        self.seg_first_packet = self.n_packets;
+        self.seg_packets = 0;
+        b
+    }
+
+    fn push(&mut self, pkt: &FFmpegRsHlsPlannerPacket) -> Result<Option<FFmpegRsHlsSegmentBoundary>, c_int> {
+        if pkt.size < 0 {
+            return Err(EINVAL);
+        }
+        let mut closed = None;
+        if self.started == 0 {
+            self.started = 1;
+            self.first_pts = pkt.pts;
+            self.seg_start_pts = pkt.pts;
+        } else if self.seg_packets > 0 && pkt.pts > self.seg_start_pts {
+            let can_split = pkt.is_keyframe != 0 || self.cfg.split_by_time != 0;
+            if can_split {
+                let mut reason = 0;
+                if self.elapsed_reaches(pkt.pts, self.target_end_us(self.seg_index)) {
+                    reason |= FFMPEG_RS_HLS_CUT_TIME;
+                }
+                if self.cfg.max_seg_size > 0 && self.seg_size.saturating_add(pkt.size) > self.cfg.max_seg_size {
+                    reason |= FFMPEG_RS_HLS_CUT_SIZE;
+                }
+                if reason != 0 {
+                    closed = Some(self.close(pkt.pts, reason));
+                }
+            }
+        }
+        self.seg_size = self.seg_size.saturating_add(pkt.size);
+        self.seg_packets += 1;
+        self.n_packets += 1;
+        Ok(closed)
+    }
+
+    fn flush(&mut self, end_pts: i64) -> Result<Option<FFmpegRsHlsSegmentBoundary>, c_int> {
+        if self.started == 0 || self.seg_packets == 0 {
+            return Ok(None);
+        }
+        if end_pts < self.seg_start_pts {
+            return Err(EINVAL);
+        }
+        Ok(Some(self.close(end_pts, FFMPEG_RS_HLS_CUT_END)))
+    }
+}
+
+fn emit(
+    r: Result<Option<FFmpegRsHlsSegmentBoundary>, c_int>,
+    out: *mut FFmpegRsHlsSegmentBoundary,
+) -> c_int {
+    match r {
+        Err(e) => e,
+        Ok(None) => 0,
+        Ok(Some(b)) => {
+            if let Some(out) = out_ref(out) {
+                *out = b;
+            }
+            1
+        }
+    }
+}
+
+// Initializes caller-allocated planner state. Returns 0 or -EINVAL.
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_hls_planner_init(
+    planner: *mut FFmpegRsHlsSegmentPlanner,
+    cfg: *const FFmpegRsHlsPlannerConfig,
+) -> c_int {
+    let (planner, cfg) = match (out_ref(planner), in_ref(cfg)) {
+        (Some(p), Some(c)) => (p, c),
+        _ => return EINVAL,
+    };
+    match FFmpegRsHlsSegmentPlanner::new(cfg) {
+        Ok(p) => {
+            *planner = p;
+            0
+        }
+        Err(e) => e,
+    }
+}
+
+// Feeds one packet. Returns 1 if a segment was closed before this packet
+// (described in `*out`), 0 if the packet extends the current segment, or <0.
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_hls_planner_push(
+    planner: *mut FFmpegRsHlsSegmentPlanner,
+    pkt: *const FFmpegRsHlsPlannerPacket,
+    out: *mut FFmpegRsHlsSegmentBoundary,
+) -> c_int {
+    match (out_ref(planner), in_ref(pkt)) {
+        (Some(p), Some(pkt)) => emit(p.push(pkt), out),
+        _ => EINVAL,
+    }
+}
+
+// Closes the last segment at `end_pts`. Returns 1 if a segment was emitted,
+// 0 if there was nothing pending, or <0.
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_hls_planner_flush(
+    planner: *mut FFmpegRsHlsSegmentPlanner,
+    end_pts: i64,
+    out: *mut FFmpegRsHlsSegmentBoundary,
+) -> c_int {
+    match out_ref(planner) {
+        Some(p) => emit(p.flush(end_pts), out),
+        None => EINVAL,
+    }
+}
+
+// Plans a whole recorded packet trace. `segments` may be NULL for a size-only
+// pass; `n_segments_total` always reports the full count.
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_hls_plan_segments(
+    cfg: *const FFmpegRsHlsPlannerConfig,
+    packets: *const FFmpegRsHlsPlannerPacket,
+    n_packets: usize,
+    end_pts: i64,
+    out: *mut FFmpegRsHlsPlanResult,
+    segments: *mut FFmpegRsHlsSegmentBoundary,
+    segments_cap: usize,
+) -> c_int {
+    let (cfg, pkts, res) = match (in_ref(cfg), in_array(packets, n_packets), out_ref(out)) {
+        (Some(c), Some(p), Some(r)) => (c, p, r),
+        _ => return EINVAL,
+    };
+    let segs = out_array(segments, segments_cap);
+    res.n_segments_total = 0;
+    res.n_segments_written = 0;
+    res.truncated = 0;
+
+    let mut planner = match FFmpegRsHlsSegmentPlanner::new(cfg) {
+        Ok(p) => p,
+        Err(e) => return e,
+    };
+    let mut store = |b: FFmpegRsHlsSegmentBoundary| {
+        if res.n_segments_total < segs.len() {
+            segs[res.n_segments_total] = b;
+            res.n_segments_written += 1;
+        } else if !segs.is_empty() {
+            res.truncated = 1;
+        }
+        res.n_segments_total += 1;
+    };
+    for pkt in pkts {
+        match planner.push(pkt) {
+            Ok(Some(b)) => store(b),
+            Ok(None) => {}
+            Err(e) => return e,
+        }
+    }
+    match planner.flush(end_pts) {
+        Ok(Some(b)) => store(b),
+        Ok(None) => {}
+        Err(e) => return e,
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMPTY: FFmpegRsHlsSegmentBoundary = FFmpegRsHlsSegmentBoundary {
+        index: 0,
+        start_pts: 0,
+        end_pts: 0,
+        duration_us: 0,
+        size: 0,
+        first_packet: 0,
+        n_packets: 0,
+        reason: 0,
+    };
+
+    fn cfg(time_us: i64) -> FFmpegRsHlsPlannerConfig {
+        FFmpegRsHlsPlannerConfig {
+            tb_num: 1,
+            tb_den: 90000,
+            time_us,
+            init_time_us: 0,
+            init_segments: 0,
+            split_by_time: 0,
+            max_seg_size: 0,
+        }
+    }
+
+    // 25 fps at 90 kHz, keyframe every `gop` frames.
+    fn trace(n: usize, gop: usize) -> [FFmpegRsHlsPlannerPacket; 300] {
+        let mut pkts = [FFmpegRsHlsPlannerPacket { pts: 0, is_keyframe: 0, size: 0 }; 300];
+        for (i, p) in pkts.iter_mut().enumerate().take(n) {
+            *p = FFmpegRsHlsPlannerPacket {
+                pts: 1000 + i as i64 * 3600,
+                is_keyframe: (i % gop == 0) as c_int,
+                size: 1000,
+            };
+        }
+        pkts
+    }
+
+    fn plan(c: &FFmpegRsHlsPlannerConfig, pkts: &[FFmpegRsHlsPlannerPacket], end_pts: i64, segs: &mut [FFmpegRsHlsSegmentBoundary]) -> usize {
+        let mut res = FFmpegRsHlsPlanResult { n_segments_total: 0, n_segments_written: 0, truncated: 0 };
+        let r = ffmpeg_rs_hls_plan_segments(c, pkts.as_ptr(), pkts.len(), end_pts, &mut res, segs.as_mut_ptr(), segs.len());
+        assert_eq!(r, 0);
+        assert_eq!(res.truncated, 0);
+        res.n_segments_total
+    }
+
+    #[test]
+    fn cuts_on_keyframes_after_target() {
+        // 10 s of video, GOP of 3 s (75 frames), hls_time 4 s: cuts land on
+        // the first keyframe at or after each cumulative target.
+        let pkts = trace(250, 75);
+        let mut segs = [EMPTY; 8];
+        let n = plan(&cfg(4_000_000), &pkts[..250], 1000 + 250 * 3600, &mut segs);
+        assert_eq!(n, 3);
+        assert_eq!((segs[0].first_packet, segs[0].n_packets), (0, 150));
+        assert_eq!(segs[0].duration_us, 6_000_000);
+        assert_eq!(segs[0].reason, FFMPEG_RS_HLS_CUT_TIME);
+        assert_eq!((segs[1].first_packet, segs[1].n_packets), (150, 75));
+        assert_eq!(segs[1].duration_us, 3_000_000);
+        assert_eq!(segs[2].duration_us, 1_000_000);
+        assert_eq!(segs[2].reason, FFMPEG_RS_HLS_CUT_END);
+        assert_eq!(segs[2].size, 25_000);
+    }
+
+    #[test]
+    fn split_by_time_and_init_time() {
+        let pkts = trace(250, 250);
+        let mut c = cfg(4_000_000);
+        c.split_by_time = 1;
+        c.init_time_us = 1_000_000;
+        c.init_segments = 2;
+        let mut segs = [EMPTY; 8];
+        let n = plan(&c, &pkts[..250], 1000 + 250 * 3600, &mut segs);
+        assert_eq!(n, 4);
+        let d: [i64; 4] = core::array::from_fn(|i| segs[i].duration_us);
+        assert_eq!(d, [1_000_000, 1_000_000, 4_000_000, 4_000_000]);
+    }
+
+    #[test]
+    fn incremental_matches_max_size() {
+        let mut c = cfg(10_000_000);
+        c.max_seg_size = 2500;
+        let mut p = core::mem::MaybeUninit::<FFmpegRsHlsSegmentPlanner>::uninit();
+        assert_eq!(ffmpeg_rs_hls_planner_init(p.as_mut_ptr(), &c), 0);
+        let mut p = unsafe { p.assume_init() };
+        let pkts = trace(6, 1);
+        let mut b = EMPTY;
+        let mut cuts = 0;
+        for pkt in &pkts[..6] {
+            let r = ffmpeg_rs_hls_planner_push(&mut p, pkt, &mut b);
+            assert!(r >= 0);
+            if r == 1 {
+                assert_eq!(b.reason, FFMPEG_RS_HLS_CUT_SIZE);
+                assert_eq!(b.size, 2000);
+                cuts += 1;
+            }
+        }
+        assert_eq!(cuts, 2);
+        assert_eq!(ffmpeg_rs_hls_planner_flush(&mut p, 1000 + 6 * 3600, &mut b), 1);
+        assert_eq!(b.index, 2);
+        assert_eq!(ffmpeg_rs_hls_planner_flush(&mut p, 1000 + 6 * 3600, &mut b), 0);
+    }
+}
@@ -0,0 +1,317 @@
+use core::ffi::{c_char, c_int};
+
+use crate::daterange::civil_from_days;
+use crate::writer::{in_ref, in_str, out_buf, Writer, EINVAL};
+
+/// Expand strftime conversions (hlsenc `strftime`, segment `strftime`).
+pub const FFMPEG_RS_HLS_TEMPLATE_STRFTIME: u32 = 1;
+/// In strftime mode, expand `%%d`/`%%0Nd` to the index
+/// (hlsenc `second_level_segment_index`).
+pub const FFMPEG_RS_HLS_TEMPLATE_SECOND_LEVEL_INDEX: u32 = 2;
+/// Allow more than one index placeholder (AV_FRAME_FILENAME_FLAGS_MULTIPLE).
+pub const FFMPEG_RS_HLS_TEMPLATE_MULTIPLE: u32 = 4;
+/// Fail unless the template contains an index placeholder, like
+/// av_get_frame_filename2().
+pub const FFMPEG_RS_HLS_TEMPLATE_REQUIRE_INDEX: u32 = 8;
+
+/// Upper bound on `%Nd` widths; anything larger is treated as malformed.
+const MAX_WIDTH: usize = 64;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsHlsTemplateParams {
+    pub flags: u32,
+    /// Value for `%d` / `%0Nd`.
+    pub index: i64,
+    /// Value for `%v` when `variant_name` is NULL; negative means `%v` is not
+    /// allowed.
+    pub variant_index: i64,
+    /// Replacement for `%v` (hlsenc `var_stream_map` name:).
+    pub variant_name: *const c_char,
+    pub variant_name_len: usize,
+    /// Time used for strftime conversions.
+    pub epoch_us: i64,
+    pub tz_offset_min: c_int,
+}
+
+struct Clock {
+    year: i64,
+    month: u32,
+    day: u32,
+    yday: u32,
+    hour: u32,
+    min: u32,
+    sec: u32,
+    epoch_s: i64,
+    tz_offset_min: c_int,
+}
+
+fn days_from_civil(y: i64, m: u32, d: u32) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = if m > 2 { m - 3 } else { m + 9 } as i64;
+    let doy = (153 * mp + 2) / 5 + d as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn clock(epoch_us: i64, tz_offset_min: c_int) -> Result<Clock, c_int> {
+    if !(-24 * 60 < tz_offset_min && tz_offset_min < 24 * 60) {
+        return Err(EINVAL);
+    }
+    let epoch_s = epoch_us.div_euclid(1_000_000);
+    let local = epoch_s.checked_add(tz_offset_min as i64 * 60).ok_or(EINVAL)?;
+    let days = local.div_euclid(86_400);
+    let tod = local.rem_euclid(86_400) as u32;
+    let (year, month, day) = civil_from_days(days);
+    if !(0..=9999).contains(&year) {
+        return Err(EINVAL);
+    }
+    Ok(Clock {
+        year,
+        month,
+        day,
+        yday: (days - days_from_civil(year, 1, 1)) as u32 + 1,
+        hour: tod / 3600,
+        min: tod / 60 % 60,
+        sec: tod % 60,
+        epoch_s,
+        tz_offset_min,
+    })
+}
+
+fn write_strftime(w: &mut Writer, c: &Clock, conv: u8) -> Result<(), c_int> {
+    match conv {
+        b'Y' => w.uint_padded(c.year as u64, 4),
+        b'y' => w.uint_padded((c.year % 100) as u64, 2),
+        b'm' => w.uint_padded(c.month as u64, 2),
+        b'd' => w.uint_padded(c.day as u64, 2),
+        b'e' => {
+            if c.day < 10 {
+                w.byte(b' ');
+            }
+            w.uint(c.day as u64);
+        }
+        b'j' => w.uint_padded(c.yday as u64, 3),
+        b'H' => w.uint_padded(c.hour as u64, 2),
+        b'M' => w.uint_padded(c.min as u64, 2),
+        b'S' => w.uint_padded(c.sec as u64, 2),
+        b's' => {
+            if c.epoch_s < 0 {
+                w.byte(b'-');
+            }
+            w.uint(c.epoch_s.unsigned_abs());
+        }
+        b'F' => {
+            write_strftime(w, c, b'Y')?;
+            w.byte(b'-');
+            write_strftime(w, c, b'm')?;
+            w.byte(b'-');
+            write_strftime(w, c, b'd')?;
+        }
+        b'T' => {
+            write_strftime(w, c, b'H')?;
+            w.byte(b':');
+            write_strftime(w, c, b'M')?;
+            w.byte(b':');
+            write_strftime(w, c, b'S')?;
+        }
+        b'z' => {
+            w.byte(if c.tz_offset_min < 0 { b'-' } else { b'+' });
+            let off = c.tz_offset_min.unsigned_abs();
+            w.uint_padded((off / 60) as u64, 2);
+            w.uint_padded((off % 60) as u64, 2);
+        }
+        // Locale dependent or otherwise unsupported conversions.
+        _ => return Err(EINVAL),
+    }
+    Ok(())
+}
+
+// `%0*PRId64` with the av_get_frame_filename2() sign handling: the width
+// counts digits only.
+fn write_index(w: &mut Writer, v: i64, width: usize) {
+    if v < 0 {
+        w.byte(b'-');
+    }
+    w.uint_padded(v.unsigned_abs(), width);
+}
+
+// Parses an optional run of digits at `t[*i..]` as a field width.
+fn parse_width(t: &[u8], i: &mut usize) -> Result<usize, c_int> {
+    let mut width = 0usize;
+    while *i < t.len() && t[*i].is_ascii_digit() {
+        width = width * 10 + (t[*i] - b'0') as usize;
+        if width > MAX_WIDTH {
+            return Err(EINVAL);
+        }
+        *i += 1;
+    }
+    Ok(width)
+}
+
+fn expand(w: &mut Writer, t: &[u8], p: &FFmpegRsHlsTemplateParams) -> Result<(), c_int> {
+    let strftime = p.flags & FFMPEG_RS_HLS_TEMPLATE_STRFTIME != 0;
+    let second_level = p.flags & FFMPEG_RS_HLS_TEMPLATE_SECOND_LEVEL_INDEX != 0;
+    if second_level && !strftime {
+        return Err(EINVAL);
+    }
+    let clock = if strftime {
+        Some(clock(p.epoch_us, p.tz_offset_min)?)
+    } else {
+        None
+    };
+    let variant_name = in_str(p.variant_name, p.variant_name_len);
+    let mut n_index = 0usize;
+
+    let mut i = 0usize;
+    while i < t.len() {
+        let c = t[i];
+        i += 1;
+        if c != b'%' {
+            w.byte(c);
+            continue;
+        }
+        if i < t.len() && t[i] == b'%' {
+            i += 1;
+            // `%%0Nd` survives strftime as `%0Nd`, which hlsenc then fills
+            // with the segment index.
+            if second_level {
+                let mut j = i;
+                let width = parse_width(t, &mut j)?;
+                if j < t.len() && t[j] == b'd' {
+                    i = j + 1;
+                    n_index += 1;
+                    write_index(w, p.index, width);
+                    continue;
+                }
+            }
+            w.byte(b'%');
+            continue;
+        }
+        let width = parse_width(t, &mut i)?;
+        let conv = *t.get(i).ok_or(EINVAL)?;
+        i += 1;
+        match conv {
+            b'v' => match variant_name {
+                Some(name) => w.bytes(name),
+                None if p.variant_index >= 0 => w.uint_padded(p.variant_index as u64, width),
+                None => return Err(EINVAL),
+            },
+            b'd' if !strftime => {
+                n_index += 1;
+                write_index(w, p.index, width);
+            }
+            _ => match &clock {
+                Some(clock) if width == 0 => write_strftime(w, clock, conv)?,
+                _ => return Err(EINVAL),
+            },
+        }
+    }
+
+    if n_index > 1 && p.flags & FFMPEG_RS_HLS_TEMPLATE_MULTIPLE == 0 {
+        return Err(EINVAL);
+    }
+    if n_index == 0 && p.flags & FFMPEG_RS_HLS_TEMPLATE_REQUIRE_INDEX != 0 {
+        return Err(EINVAL);
+    }
+    Ok(())
+}
+
+// Expands a segment filename template: `%d`/`%0Nd` (index, non-strftime
+// mode), `%v`/`%0Nv` (variant), strftime conversions and `%%0Nd`
+// (strftime + second-level index), and `%%` (literal percent). Unknown or
+// malformed placeholders are rejected with -EINVAL rather than copied.
+// Same size query then fill convention as ffmpeg_rs_hls_write_daterange().
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_hls_expand_template(
+    tmpl: *const c_char,
+    tmpl_len: usize,
+    params: *const FFmpegRsHlsTemplateParams,
+    dst: *mut c_char,
+    dst_len: usize,
+    out_required: *mut usize,
+) -> c_int {
+    let (t, p) = match (in_str(tmpl, tmpl_len), in_ref(params)) {
+        (Some(t), Some(p)) => (t, p),
+        _ => return EINVAL,
+    };
+    let mut w = Writer::new(out_buf(dst, dst_len));
+    if let Err(e) = expand(&mut w, t, p) {
+        return e;
+    }
+    w.finish(out_required)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::writer::ENOSPC;
+
+    fn params(flags: u32, index: i64) -> FFmpegRsHlsTemplateParams {
+        FFmpegRsHlsTemplateParams {
+            flags,
+            index,
+            variant_index: -1,
+            variant_name: core::ptr::null(),
+            variant_name_len: 0,
+            // 2024-02-29T13:04:05Z
+            epoch_us: 1_709_211_845_000_000,
+            tz_offset_min: 0,
+        }
+    }
+
+    fn expand_to<'a>(t: &[u8], p: &FFmpegRsHlsTemplateParams, buf: &'a mut [i8]) -> Result<&'a [u8], c_int> {
+        let mut req = 0usize;
+        let r = ffmpeg_rs_hls_expand_template(t.as_ptr() as *const c_char, t.len(), p, buf.as_mut_ptr(), buf.len(), &mut req);
+        if r < 0 {
+            return Err(r);
+        }
+        Ok(unsafe { core::slice::from_raw_parts(buf.as_ptr() as *const u8, req - 1) })
+    }
+
+    #[test]
+    fn expands_index_and_variant() {
+        let mut buf = [0i8; 64];
+        let mut p = params(FFMPEG_RS_HLS_TEMPLATE_REQUIRE_INDEX, 42);
+        p.variant_index = 1;
+        assert_eq!(expand_to(b"stream_%v/seg_%05d.ts", &p, &mut buf), Ok(&b"stream_1/seg_00042.ts"[..]));
+        assert_eq!(expand_to(b"100%%_%3d", &p, &mut buf), Ok(&b"100%_042"[..]));
+
+        let name = b"hd";
+        p.variant_name = name.as_ptr() as *const c_char;
+        p.variant_name_len = name.len();
+        assert_eq!(expand_to(b"%v_%d.ts", &p, &mut buf), Ok(&b"hd_42.ts"[..]));
+
+        p.index = -7;
+        assert_eq!(expand_to(b"%03d", &p, &mut buf), Ok(&b"-007"[..]));
+    }
+
+    #[test]
+    fn expands_strftime_and_second_level_index() {
+        let mut buf = [0i8; 64];
+        let p = params(FFMPEG_RS_HLS_TEMPLATE_STRFTIME, 3);
+        assert_eq!(expand_to(b"%Y%m%d-%s.ts", &p, &mut buf), Ok(&b"20240229-1709211845.ts"[..]));
+        assert_eq!(expand_to(b"%F_%T_%j%z", &p, &mut buf), Ok(&b"2024-02-29_13:04:05_060+0000"[..]));
+
+        let p = params(FFMPEG_RS_HLS_TEMPLATE_STRFTIME | FFMPEG_RS_HLS_TEMPLATE_SECOND_LEVEL_INDEX, 3);
+        assert_eq!(expand_to(b"%H%M%S-%%04d.ts", &p, &mut buf), Ok(&b"130405-0003.ts"[..]));
+    }
+
+    #[test]
+    fn rejects_malformed_templates() {
+        let mut buf = [0i8; 64];
+        let p = params(0, 1);
+        assert_eq!(expand_to(b"%d-%d", &p, &mut buf), Err(EINVAL));
+        assert_eq!(expand_to(b"%x", &p, &mut buf), Err(EINVAL));
+        assert_eq!(expand_to(b"seg%", &p, &mut buf), Err(EINVAL));
+        assert_eq!(expand_to(b"%v", &p, &mut buf), Err(EINVAL));
+        assert_eq!(expand_to(b"%999999999999d", &p, &mut buf), Err(EINVAL));
+        assert_eq!(expand_to(b"plain.ts", &params(FFMPEG_RS_HLS_TEMPLATE_REQUIRE_INDEX, 1), &mut buf), Err(EINVAL));
+        assert_eq!(expand_to(b"%d%d", &params(FFMPEG_RS_HLS_TEMPLATE_MULTIPLE, 1), &mut buf), Ok(&b"11"[..]));
+
+        let mut small = [0i8; 4];
+        assert_eq!(expand_to(b"seg_%05d", &p, &mut small), Err(ENOSPC));
+    }
+}
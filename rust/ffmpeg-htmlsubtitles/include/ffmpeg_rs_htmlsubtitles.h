@@ -0,0 +1,28 @@
+#pragma once
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * Converts SubRip/SAMI style HTML markup into ASS override tags, with the
+ * same output as ff_htmlmarkup_to_ass(): <font color size face> (nested up
+ * to 15 deep), <b>, <i>, <u>, <s> and <br> are translated, the first {\anN}
+ * passes through, other {\...} and MicroDVD-style {Y:...} overrides and
+ * unknown tags are dropped without losing text, and entities are kept as
+ * text. Input ends at text_len or the first NUL; conversion stops at the
+ * first empty line. A "random" font color is not supported and is ignored.
+ *
+ * Output is NUL-terminated; *out_required includes the terminator. Pass
+ * dst == NULL to query the size. Returns 0, -EINVAL on invalid arguments,
+ * or -ENOSPC if dst is too small.
+ */
+int ffmpeg_rs_htmlmarkup_to_ass(const char *text, size_t text_len,
+                                char *dst, size_t dst_len, size_t *out_required);
+
+#ifdef __cplusplus
+}
+#endif
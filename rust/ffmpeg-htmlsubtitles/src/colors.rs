@@ -0,0 +1,316 @@
+// Named colors accepted by av_parse_color(), sorted like libavutil's table.
+pub(crate) const COLORS: &[(&[u8], [u8; 3])] = &[
+    (b"AliceBlue", [0xF0, 0xF8, 0xFF]),
+    (b"AntiqueWhite", [0xFA, 0xEB, 0xD7]),
+    (b"Aqua", [0x00, 0xFF, 0xFF]),
+    (b"Aquamarine", [0x7F, 0xFF, 0xD4]),
+    (b"Azure", [0xF0, 0xFF, 0xFF]),
+    (b"Beige", [0xF5, 0xF5, 0xDC]),
+    (b"Bisque", [0xFF, 0xE4, 0xC4]),
+    (b"Black", [0x00, 0x00, 0x00]),
+    (b"BlanchedAlmond", [0xFF, 0xEB, 0xCD]),
+    (b"Blue", [0x00, 0x00, 0xFF]),
+    (b"BlueViolet", [0x8A, 0x2B, 0xE2]),
+    (b"Brown", [0xA5, 0x2A, 0x2A]),
+    (b"BurlyWood", [0xDE, 0xB8, 0x87]),
+    (b"CadetBlue", [0x5F, 0x9E, 0xA0]),
+    (b"Chartreuse", [0x7F, 0xFF, 0x00]),
+    (b"Chocolate", [0xD2, 0x69, 0x1E]),
+    (b"Coral", [0xFF, 0x7F, 0x50]),
+    (b"CornflowerBlue", [0x64, 0x95, 0xED]),
+    (b"Cornsilk", [0xFF, 0xF8, 0xDC]),
+    (b"Crimson", [0xDC, 0x14, 0x3C]),
+    (b"Cyan", [0x00, 0xFF, 0xFF]),
+    (b"DarkBlue", [0x00, 0x00, 0x8B]),
+    (b"DarkCyan", [0x00, 0x8B, 0x8B]),
+    (b"DarkGoldenRod", [0xB8, 0x86, 0x0B]),
+    (b"DarkGray", [0xA9, 0xA9, 0xA9]),
+    (b"DarkGreen", [0x00, 0x64, 0x00]),
+    (b"DarkKhaki", [0xBD, 0xB7, 0x6B]),
+    (b"DarkMagenta", [0x8B, 0x00, 0x8B]),
+    (b"DarkOliveGreen", [0x55, 0x6B, 0x2F]),
+    (b"Darkorange", [0xFF, 0x8C, 0x00]),
+    (b"DarkOrchid", [0x99, 0x32, 0xCC]),
+    (b"DarkRed", [0x8B, 0x00, 0x00]),
+    (b"DarkSalmon", [0xE9, 0x96, 0x7A]),
+    (b"DarkSeaGreen", [0x8F, 0xBC, 0x8F]),
+    (b"DarkSlateBlue", [0x48, 0x3D, 0x8B]),
+    (b"DarkSlateGray", [0x2F, 0x4F, 0x4F]),
+    (b"DarkTurquoise", [0x00, 0xCE, 0xD1]),
+    (b"DarkViolet", [0x94, 0x00, 0xD3]),
+    (b"DeepPink", [0xFF, 0x14, 0x93]),
+    (b"DeepSkyBlue", [0x00, 0xBF, 0xFF]),
+    (b"DimGray", [0x69, 0x69, 0x69]),
+    (b"DodgerBlue", [0x1E, 0x90, 0xFF]),
+    (b"FireBrick", [0xB2, 0x22, 0x22]),
+    (b"FloralWhite", [0xFF, 0xFA, 0xF0]),
+    (b"ForestGreen", [0x22, 0x8B, 0x22]),
+    (b"Fuchsia", [0xFF, 0x00, 0xFF]),
+    (b"Gainsboro", [0xDC, 0xDC, 0xDC]),
+    (b"GhostWhite", [0xF8, 0xF8, 0xFF]),
+    (b"Gold", [0xFF, 0xD7, 0x00]),
+    (b"GoldenRod", [0xDA, 0xA5, 0x20]),
+    (b"Gray", [0x80, 0x80, 0x80]),
+    (b"Green", [0x00, 0x80, 0x00]),
+    (b"GreenYellow", [0xAD, 0xFF, 0x2F]),
+    (b"HoneyDew", [0xF0, 0xFF, 0xF0]),
+    (b"HotPink", [0xFF, 0x69, 0xB4]),
+    (b"IndianRed", [0xCD, 0x5C, 0x5C]),
+    (b"Indigo", [0x4B, 0x00, 0x82]),
+    (b"Ivory", [0xFF, 0xFF, 0xF0]),
+    (b"Khaki", [0xF0, 0xE6, 0x8C]),
+    (b"Lavender", [0xE6, 0xE6, 0xFA]),
+    (b"LavenderBlush", [0xFF, 0xF0, 0xF5]),
+    (b"LawnGreen", [0x7C, 0xFC, 0x00]),
+    (b"LemonChiffon", [0xFF, 0xFA, 0xCD]),
+    (b"LightBlue", [0xAD, 0xD8, 0xE6]),
+    (b"LightCoral", [0xF0, 0x80, 0x80]),
+    (b"LightCyan", [0xE0, 0xFF, 0xFF]),
+    (b"LightGoldenRodYellow", [0xFA, 0xFA, 0xD2]),
+    (b"LightGreen", [0x90, 0xEE, 0x90]),
+    (b"LightGrey", [0xD3, 0xD3, 0xD3]),
+    (b"LightPink", [0xFF, 0xB6, 0xC1]),
+    (b"LightSalmon", [0xFF, 0xA0, 0x7A]),
+    (b"LightSeaGreen", [0x20, 0xB2, 0xAA]),
+    (b"LightSkyBlue", [0x87, 0xCE, 0xFA]),
+    (b"LightSlateGray", [0x77, 0x88, 0x99]),
+    (b"LightSteelBlue", [0xB0, 0xC4, 0xDE]),
+    (b"LightYellow", [0xFF, 0xFF, 0xE0]),
+    (b"Lime", [0x00, 0xFF, 0x00]),
+    (b"LimeGreen", [0x32, 0xCD, 0x32]),
+    (b"Linen", [0xFA, 0xF0, 0xE6]),
+    (b"Magenta", [0xFF, 0x00, 0xFF]),
+    (b"Maroon", [0x80, 0x00, 0x00]),
+    (b"MediumAquaMarine", [0x66, 0xCD, 0xAA]),
+    (b"MediumBlue", [0x00, 0x00, 0xCD]),
+    (b"MediumOrchid", [0xBA, 0x55, 0xD3]),
+    (b"MediumPurple", [0x93, 0x70, 0xD8]),
+    (b"MediumSeaGreen", [0x3C, 0xB3, 0x71]),
+    (b"MediumSlateBlue", [0x7B, 0x68, 0xEE]),
+    (b"MediumSpringGreen", [0x00, 0xFA, 0x9A]),
+    (b"MediumTurquoise", [0x48, 0xD1, 0xCC]),
+    (b"MediumVioletRed", [0xC7, 0x15, 0x85]),
+    (b"MidnightBlue", [0x19, 0x19, 0x70]),
+    (b"MintCream", [0xF5, 0xFF, 0xFA]),
+    (b"MistyRose", [0xFF, 0xE4, 0xE1]),
+    (b"Moccasin", [0xFF, 0xE4, 0xB5]),
+    (b"NavajoWhite", [0xFF, 0xDE, 0xAD]),
+    (b"Navy", [0x00, 0x00, 0x80]),
+    (b"OldLace", [0xFD, 0xF5, 0xE6]),
+    (b"Olive", [0x80, 0x80, 0x00]),
+    (b"OliveDrab", [0x6B, 0x8E, 0x23]),
+    (b"Orange", [0xFF, 0xA5, 0x00]),
+    (b"OrangeRed", [0xFF, 0x45, 0x00]),
+    (b"Orchid", [0xDA, 0x70, 0xD6]),
+    (b"PaleGoldenRod", [0xEE, 0xE8, 0xAA]),
+    (b"PaleGreen", [0x98, 0xFB, 0x98]),
+    (b"PaleTurquoise", [0xAF, 0xEE, 0xEE]),
+    (b"PaleVioletRed", [0xD8, 0x70, 0x93]),
+    (b"PapayaWhip", [0xFF, 0xEF, 0xD5]),
+    (b"PeachPuff", [0xFF, 0xDA, 0xB9]),
+    (b"Peru", [0xCD, 0x85, 0x3F]),
+    (b"Pink", [0xFF, 0xC0, 0xCB]),
+    (b"Plum", [0xDD, 0xA0, 0xDD]),
+    (b"PowderBlue", [0xB0, 0xE0, 0xE6]),
+    (b"Purple", [0x80, 0x00, 0x80]),
+    (b"Red", [0xFF, 0x00, 0x00]),
+    (b"RosyBrown", [0xBC, 0x8F, 0x8F]),
+    (b"RoyalBlue", [0x41, 0x69, 0xE1]),
+    (b"SaddleBrown", [0x8B, 0x45, 0x13]),
+    (b"Salmon", [0xFA, 0x80, 0x72]),
+    (b"SandyBrown", [0xF4, 0xA4, 0x60]),
+    (b"SeaGreen", [0x2E, 0x8B, 0x57]),
+    (b"SeaShell", [0xFF, 0xF5, 0xEE]),
+    (b"Sienna", [0xA0, 0x52, 0x2D]),
+    (b"Silver", [0xC0, 0xC0, 0xC0]),
+    (b"SkyBlue", [0x87, 0xCE, 0xEB]),
+    (b"SlateBlue", [0x6A, 0x5A, 0xCD]),
+    (b"SlateGray", [0x70, 0x80, 0x90]),
+    (b"Snow", [0xFF, 0xFA, 0xFA]),
+    (b"SpringGreen", [0x00, 0xFF, 0x7F]),
+    (b"SteelBlue", [0x46, 0x82, 0xB4]),
+    (b"Tan", [0xD2, 0xB4, 0x8C]),
+    (b"Teal", [0x00, 0x80, 0x80]),
+    (b"Thistle", [0xD8, 0xBF, 0xD8]),
+    (b"Tomato", [0xFF, 0x63, 0x47]),
+    (b"Turquoise", [0x40, 0xE0, 0xD0]),
+    (b"Violet", [0xEE, 0x82, 0xEE]),
+    (b"Wheat", [0xF5, 0xDE, 0xB3]),
+    (b"White", [0xFF, 0xFF, 0xFF]),
+    (b"WhiteSmoke", [0xF5, 0xF5, 0xF5]),
+    (b"Yellow", [0xFF, 0xFF, 0x00]),
+    (b"YellowGreen", [0x9A, 0xCD, 0x32]),
+];
+
+fn is_space(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | 0x0b | 0x0c | b'\r')
+}
+
+fn hex_digit(b: u8) -> Option<u64> {
+    (b as char).to_digit(16).map(u64::from)
+}
+
+// strtoul(s, &end, 16): returns the value and whether the whole of `s` was
+// consumed. Overflow saturates like ERANGE.
+fn strtoul_hex(s: &[u8]) -> (u64, bool) {
+    let mut i = s.iter().take_while(|&&b| is_space(b)).count();
+    let neg = match s.get(i) {
+        Some(b'-') => {
+            i += 1;
+            true
+        }
+        Some(b'+') => {
+            i += 1;
+            false
+        }
+        _ => false,
+    };
+    if s.get(i) == Some(&b'0') && matches!(s.get(i + 1), Some(b'x' | b'X')) && s.get(i + 2).and_then(|&b| hex_digit(b)).is_some() {
+        i += 2;
+    }
+    let n = s[i..].iter().take_while(|&&b| hex_digit(b).is_some()).count();
+    if n == 0 {
+        return (0, s.is_empty());
+    }
+    let mut v = 0u64;
+    let mut overflow = false;
+    for &b in &s[i..i + n] {
+        match v.checked_mul(16).and_then(|v| v.checked_add(hex_digit(b).unwrap_or(0))) {
+            Some(x) => v = x,
+            None => overflow = true,
+        }
+    }
+    let v = if overflow {
+        u64::MAX
+    } else if neg {
+        v.wrapping_neg()
+    } else {
+        v
+    };
+    (v, i + n == s.len())
+}
+
+// strtod() range check for the `@alpha` suffix: true if the whole
+// string is a decimal number in [0, 1] (or NaN, which passes every check in
+// av_parse_color()).
+fn unit_decimal(s: &[u8]) -> bool {
+    let mut i = s.iter().take_while(|&&b| is_space(b)).count();
+    let neg = match s.get(i) {
+        Some(b'-') => {
+            i += 1;
+            true
+        }
+        Some(b'+') => {
+            i += 1;
+            false
+        }
+        _ => false,
+    };
+    if s.len() - i == 3 && s[i..].eq_ignore_ascii_case(b"nan") {
+        return true;
+    }
+    let mut v = 0f64;
+    let mut digits = 0usize;
+    let mut scale = 0i64;
+    while let Some(d) = s.get(i).filter(|b| b.is_ascii_digit()) {
+        v = v * 10.0 + f64::from(d - b'0');
+        digits += 1;
+        i += 1;
+    }
+    if s.get(i) == Some(&b'.') {
+        i += 1;
+        while let Some(d) = s.get(i).filter(|b| b.is_ascii_digit()) {
+            v = v * 10.0 + f64::from(d - b'0');
+            digits += 1;
+            scale -= 1;
+            i += 1;
+        }
+    }
+    if digits == 0 {
+        return false;
+    }
+    if matches!(s.get(i), Some(b'e' | b'E')) {
+        let mut j = i + 1;
+        let eneg = match s.get(j) {
+            Some(b'-') => {
+                j += 1;
+                true
+            }
+            Some(b'+') => {
+                j += 1;
+                false
+            }
+            _ => false,
+        };
+        let n = s[j.min(s.len())..].iter().take_while(|b| b.is_ascii_digit()).count();
+        if n > 0 {
+            let e = s[j..j + n].iter().fold(0i64, |e, &d| e.saturating_mul(10).saturating_add(i64::from(d - b'0')));
+            scale = scale.saturating_add(if eneg { -e } else { e });
+            i = j + n;
+        }
+    }
+    if i != s.len() {
+        return false;
+    }
+    if v == 0.0 {
+        return true;
+    }
+    if neg {
+        return false;
+    }
+    for _ in 0..scale.clamp(-400, 400).unsigned_abs() {
+        if scale > 0 {
+            v *= 10.0;
+        } else {
+            v /= 10.0;
+        }
+    }
+    v <= 1.0
+}
+
+// av_parse_color() validity of an `@alpha` suffix.
+fn valid_alpha(s: &[u8]) -> bool {
+    if s.starts_with(b"0x") {
+        let (v, whole) = strtoul_hex(s);
+        return whole && v <= 255;
+    }
+    unit_decimal(s)
+}
+
+/// av_parse_color() subset used by the HTML converter: `#RRGGBB[AA]`,
+/// `0xRRGGBB[AA]`, bare hex or a color name, with an optional `@alpha`.
+/// Returns the RGB triplet.
+pub(crate) fn parse_color(s: &[u8]) -> Option<[u8; 3]> {
+    let hex_offset = if s.starts_with(b"#") {
+        1
+    } else if s.starts_with(b"0x") {
+        2
+    } else {
+        0
+    };
+    // av_parse_color() copies at most 127 bytes.
+    let s = &s[hex_offset..];
+    let s = &s[..s.len().min(127)];
+    let (color, alpha) = match s.iter().position(|&b| b == b'@') {
+        Some(at) => (&s[..at], Some(&s[at + 1..])),
+        None => (s, None),
+    };
+    let rgb = if hex_offset > 0 || color.iter().all(|&b| hex_digit(b).is_some()) {
+        let (v, whole) = strtoul_hex(color);
+        if !whole || (color.len() != 6 && color.len() != 8) {
+            return None;
+        }
+        let mut rgba = v as u32;
+        if color.len() == 8 {
+            rgba >>= 8;
+        }
+        [(rgba >> 16) as u8, (rgba >> 8) as u8, rgba as u8]
+    } else {
+        // "random" has no deterministic value here and is rejected.
+        COLORS.iter().find(|(name, _)| name.eq_ignore_ascii_case(color))?.1
+    };
+    match alpha {
+        Some(a) if !valid_alpha(a) => None,
+        _ => Some(rgb),
+    }
+}
@@ -0,0 +1,460 @@
+use core::ffi::{c_char, c_int};
+
+use crate::colors::parse_color;
+use crate::writer::{in_bytes, out_buf, Writer};
+
+const EINVAL: c_int = -22;
+
+/// Output with the trailing part that ff_htmlmarkup_to_ass() may strip held
+/// back: `spaces` blanks, `breaks` "\N" pairs, `spaces_after` blanks, then a
+/// lone backslash that a following 'N' would turn into one more pair.
+struct Out<'a> {
+    w: Writer<'a>,
+    spaces: usize,
+    breaks: usize,
+    spaces_after: usize,
+    backslash: bool,
+}
+
+impl Out<'_> {
+    fn spaces(&mut self, n: usize) {
+        for _ in 0..n {
+            self.w.byte(b' ');
+        }
+    }
+
+    // Writes `spaces` and `breaks`, which can no longer be stripped.
+    fn commit_breaks(&mut self) {
+        self.spaces(self.spaces);
+        for _ in 0..self.breaks {
+            self.w.bytes(b"\\N");
+        }
+        self.spaces = 0;
+        self.breaks = 0;
+    }
+
+    fn commit(&mut self) {
+        self.commit_breaks();
+        self.spaces(self.spaces_after);
+        if self.backslash {
+            self.w.byte(b'\\');
+        }
+        self.spaces_after = 0;
+        self.backslash = false;
+    }
+
+    fn byte(&mut self, b: u8) {
+        match b {
+            b' ' if self.backslash => {
+                self.commit();
+                self.spaces = 1;
+            }
+            b' ' if self.breaks > 0 => self.spaces_after += 1,
+            b' ' => self.spaces += 1,
+            b'\\' => {
+                if self.backslash {
+                    self.commit();
+                }
+                self.backslash = true;
+            }
+            b'N' if self.backslash => {
+                self.backslash = false;
+                if self.spaces_after > 0 {
+                    self.commit_breaks();
+                    self.spaces = self.spaces_after;
+                    self.spaces_after = 0;
+                }
+                self.breaks += 1;
+            }
+            _ => {
+                self.commit();
+                self.w.byte(b);
+            }
+        }
+    }
+
+    fn str(&mut self, s: &[u8]) {
+        for &b in s {
+            self.byte(b);
+        }
+    }
+
+    fn num(&mut self, mut v: u64, radix: u64) {
+        let mut tmp = [0u8; 20];
+        let mut i = 0usize;
+        loop {
+            tmp[i] = b"0123456789ABCDEF"[(v % radix) as usize];
+            i += 1;
+            v /= radix;
+            if v == 0 {
+                break;
+            }
+        }
+        while i > 0 {
+            i -= 1;
+            self.byte(tmp[i]);
+        }
+    }
+
+    fn int(&mut self, v: i32) {
+        if v < 0 {
+            self.byte(b'-');
+        }
+        self.num(u64::from(v.unsigned_abs()), 10);
+    }
+
+    /// Drops trailing blanks, like rstrip_spaces_buf().
+    fn rstrip(&mut self) {
+        if self.backslash {
+            return;
+        }
+        if self.breaks > 0 {
+            self.spaces_after = 0;
+        } else {
+            self.spaces = 0;
+        }
+    }
+
+    /// Drops trailing "\N" pairs and then trailing blanks.
+    fn finish(mut self, out_required: *mut usize) -> c_int {
+        if self.backslash {
+            self.commit();
+        } else if self.spaces_after > 0 {
+            self.commit_breaks();
+        }
+        self.w.finish(out_required)
+    }
+}
+
+#[derive(Copy, Clone)]
+struct Font<'a> {
+    face: &'a [u8],
+    size: u32,
+    /// 0xff000000 | BGR when set.
+    color: u32,
+}
+
+// Tags nest at most this deep; deeper <font> tags are ignored.
+const MAX_FONTS: usize = 16;
+
+fn is_space(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | 0x0b | 0x0c | b'\r')
+}
+
+fn starts_with_ci(s: &[u8], prefix: &[u8]) -> bool {
+    s.len() >= prefix.len() && s[..prefix.len()].eq_ignore_ascii_case(prefix)
+}
+
+// sscanf("%u"): strtoul() semantics truncated to unsigned int.
+fn scan_uint(s: &[u8]) -> Option<u32> {
+    let mut i = s.iter().take_while(|&&b| is_space(b)).count();
+    let neg = match s.get(i) {
+        Some(b'-') => {
+            i += 1;
+            true
+        }
+        Some(b'+') => {
+            i += 1;
+            false
+        }
+        _ => false,
+    };
+    let digits = &s[i..];
+    let n = digits.iter().take_while(|b| b.is_ascii_digit()).count();
+    if n == 0 {
+        return None;
+    }
+    let v = digits[..n]
+        .iter()
+        .try_fold(0u64, |v, &d| v.checked_mul(10)?.checked_add(u64::from(d - b'0')));
+    let v = match v {
+        Some(v) if neg => v.wrapping_neg(),
+        Some(v) => v,
+        None => u64::MAX,
+    };
+    Some(v as u32)
+}
+
+// html_color_parse(): BGR, or None if av_parse_color() would fail.
+fn html_color(s: &[u8]) -> Option<u32> {
+    let sharps = s.iter().take_while(|&&b| b == b'#').count();
+    let s = &s[sharps.saturating_sub(1)..];
+    let len = s.iter().position(|&b| matches!(b, b'"' | b' ' | b'>')).unwrap_or(s.len());
+    let [r, g, b] = parse_color(&s[..len])?;
+    Some(u32::from(r) | u32::from(g) << 8 | u32::from(b) << 16)
+}
+
+// Parses the attributes of an opening <font> tag into `font`, emitting the
+// matching override tags.
+fn open_font<'a>(out: &mut Out, font: &mut Font<'a>, mut param: Option<&'a [u8]>) {
+    let quoted = |p: &[u8], at: usize| p.get(at) == Some(&b'"');
+    while let Some(mut p) = param {
+        if starts_with_ci(p, b"size=") {
+            p = &p[5 + quoted(p, 5) as usize..];
+            if let Some(size) = scan_uint(p) {
+                font.size = size;
+                out.str(b"{\\fs");
+                out.num(u64::from(size), 10);
+                out.byte(b'}');
+            }
+        } else if starts_with_ci(p, b"color=") {
+            p = &p[6 + quoted(p, 6) as usize..];
+            if let Some(color) = html_color(p) {
+                font.color = 0xff000000 | color;
+                out.str(b"{\\c&H");
+                out.num(u64::from(color), 16);
+                out.str(b"&}");
+            }
+        } else if starts_with_ci(p, b"face=") {
+            let q = quoted(p, 5);
+            p = &p[5 + q as usize..];
+            let stop = if q { b'"' } else { b' ' };
+            let len = p.iter().position(|&b| b == stop).unwrap_or(p.len());
+            font.face = &p[..len];
+            p = &p[len..];
+            out.str(b"{\\fn");
+            out.str(font.face);
+            out.byte(b'}');
+        }
+        param = p.iter().position(|&b| b == b' ').map(|k| &p[k + 1..]);
+    }
+}
+
+// Emits the overrides restoring `last` after the font `cur` is closed.
+fn close_font(out: &mut Out, cur: &Font, last: &Font) {
+    if cur.size != 0 {
+        if last.size == 0 {
+            out.str(b"{\\fs}");
+        } else if last.size != cur.size {
+            out.str(b"{\\fs");
+            out.int(last.size as i32);
+            out.byte(b'}');
+        }
+    }
+    if cur.color & 0xff000000 != 0 {
+        if last.color & 0xff000000 == 0 {
+            out.str(b"{\\c}");
+        } else if last.color != cur.color {
+            out.str(b"{\\c&H");
+            out.num(u64::from(last.color & 0xffffff), 16);
+            out.str(b"&}");
+        }
+    }
+    if !cur.face.is_empty() {
+        if last.face.is_empty() {
+            out.str(b"{\\fn}");
+        } else if last.face != cur.face {
+            out.str(b"{\\fn");
+            out.str(last.face);
+            out.byte(b'}');
+        }
+    }
+}
+
+fn is_tag_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b == b'/'
+}
+
+// Port of ff_htmlmarkup_to_ass(). Input ends at the first NUL; conversion
+// stops at the first empty line. Unsupported tags are dropped without
+// dropping text; <font> attributes are the only state kept on a stack, the
+// other tags toggle their style whatever the nesting.
+fn convert(data: &[u8], out: &mut Out) {
+    let data = &data[..data.iter().position(|&b| b == 0).unwrap_or(data.len())];
+    let at = |i: usize| data.get(i).copied().unwrap_or(0);
+    let mut stack = [Font { face: b"", size: 0, color: 0 }; MAX_FONTS];
+    let mut sptr = 0usize;
+    let mut line_start = true;
+    let mut an = 0u32;
+    let mut brace_missing = false;
+
+    let mut i = 0usize;
+    while i < data.len() {
+        match data[i] {
+            b'\r' => {}
+            b'\n' => {
+                if line_start {
+                    break;
+                }
+                out.rstrip();
+                out.str(b"\\N");
+                line_start = true;
+            }
+            b' ' => {
+                if !line_start {
+                    out.byte(b' ');
+                }
+            }
+            b'{' => {
+                // Drop {\xxx} overrides except the first {\anN}, and
+                // MicroDVD-like {Y:xxx} styles.
+                let is_an = data[i..].starts_with(b"{\\an") && at(i + 4).is_ascii_digit() && at(i + 5) == b'}';
+                an = an.saturating_add(is_an as u32);
+                let c1 = at(i + 1);
+                let mut skipped = false;
+                if !brace_missing && ((an != 1 && c1 == b'\\') || (c1 != 0 && b"CcFfoPSsYy".contains(&c1) && at(i + 2) == b':')) {
+                    match data[i + 2..].iter().position(|&b| b == b'}') {
+                        Some(p) => {
+                            i += 2 + p;
+                            skipped = true;
+                        }
+                        None => brace_missing = true,
+                    }
+                }
+                if !skipped {
+                    out.byte(b'{');
+                }
+            }
+            b'<' => {
+                // "<<" are likely guillemets or a style effect, not a tag.
+                let mut likely_a_tag = true;
+                while at(i + 1) == b'<' {
+                    out.byte(b'<');
+                    likely_a_tag = false;
+                    i += 1;
+                }
+                let tag_close = at(i + 1) == b'/';
+                if tag_close {
+                    likely_a_tag = true;
+                }
+                let start = i + 1 + tag_close as usize;
+                let rest = &data[start..];
+                let end = rest.iter().take(128).position(|&b| b == b'<' || b == b'>');
+                let content = match end {
+                    Some(p) if rest[p] == b'>' => &rest[..p],
+                    _ => {
+                        out.byte(b'<');
+                        line_start = false;
+                        i += 1;
+                        continue;
+                    }
+                };
+                let skip = content.len() + 1 + tag_close as usize;
+                let blanks = content.iter().take_while(|&&b| b == b' ').count();
+                if blanks > 0 {
+                    likely_a_tag = false;
+                }
+                let tag = &content[blanks..];
+                let (name, param) = match tag.iter().position(|&b| b == b' ') {
+                    Some(p) => (&tag[..p], Some(&tag[p + 1..])),
+                    None => (tag, None),
+                };
+                if !name.iter().all(|&b| is_tag_char(b)) {
+                    likely_a_tag = false;
+                }
+
+                if name.eq_ignore_ascii_case(b"font") {
+                    if tag_close && sptr > 0 {
+                        let cur = stack[sptr];
+                        sptr -= 1;
+                        close_font(out, &cur, &stack[sptr]);
+                    } else if !tag_close && sptr < MAX_FONTS - 1 {
+                        stack[sptr + 1] = stack[sptr];
+                        sptr += 1;
+                        open_font(out, &mut stack[sptr], param);
+                    }
+                    i += skip;
+                } else if name.len() == 1 && b"bisu".contains(&name[0].to_ascii_lowercase()) {
+                    out.str(b"{\\");
+                    out.byte(name[0].to_ascii_lowercase());
+                    out.byte(if tag_close { b'0' } else { b'1' });
+                    out.byte(b'}');
+                    i += skip;
+                } else if starts_with_ci(name, b"br") && (name.len() == 2 || (name.len() == 3 && name[2] == b'/')) {
+                    out.str(b"\\N");
+                    i += skip;
+                } else if likely_a_tag {
+                    i += skip;
+                } else {
+                    out.byte(b'<');
+                }
+            }
+            b => out.byte(b),
+        }
+        if !matches!(data[i], b' ' | b'\r' | b'\n') {
+            line_start = false;
+        }
+        i += 1;
+    }
+}
+
+// Converts SubRip/SAMI style HTML markup (<font color size face>, <b>, <i>,
+// <u>, <s>, <br>) into ASS override tags, like ff_htmlmarkup_to_ass().
+// Entities are kept as text. The output is NUL-terminated; `*out_required`
+// includes the terminator. Returns 0, -EINVAL on invalid arguments or
+// -ENOSPC if `dst` is too small (NULL queries the size).
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_htmlmarkup_to_ass(
+    text: *const c_char,
+    text_len: usize,
+    dst: *mut c_char,
+    dst_len: usize,
+    out_required: *mut usize,
+) -> c_int {
+    let data = match in_bytes(text as *const u8, text_len) {
+        Some(d) => d,
+        None => return EINVAL,
+    };
+    let mut out = Out { w: Writer::new(out_buf(dst, dst_len)), spaces: 0, breaks: 0, spaces_after: 0, backslash: false };
+    convert(data, &mut out);
+    out.finish(out_required)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::writer::ENOSPC;
+
+    fn run(input: &[u8], buf: &mut [u8]) -> usize {
+        let mut req = 0usize;
+        let r = ffmpeg_rs_htmlmarkup_to_ass(input.as_ptr() as *const c_char, input.len(), buf.as_mut_ptr() as *mut c_char, buf.len(), &mut req);
+        assert_eq!(r, 0);
+        req - 1
+    }
+
+    #[test]
+    fn converts_like_htmlsubtitles() {
+        let cases: &[(&[u8], &[u8])] = &[
+            (b"<i>Hello</i> <b>world</b>", b"{\\i1}Hello{\\i0} {\\b1}world{\\b0}"),
+            (b"  line one  \r\nline<br>two<BR/>three \n\nignored", b"line one\\Nline\\Ntwo\\Nthree"),
+            (
+                b"<font color=\"red\" size=10>red <font size=\"50\" face=\"Comic Sans\">BIG</font> again</font> plain",
+                b"{\\c&HFF&}{\\fs10}red {\\fs50}{\\fnComic Sans}BIG{\\fs10}{\\fn} again{\\fs}{\\c} plain",
+            ),
+            (b"<font color=#00ff00>g</font><font color=\"bogus\">x</font>", b"{\\c&HFF00&}g{\\c}x"),
+            (b"{\\an8}top{\\b1}{\\an2}{Y:i}&amp;", b"{\\an8}top{\\b1}&amp;"),
+            (b"{\\i1}a{\\i0}", b"a"),
+            (b"<b> foo <i> bar </b> bla </i>", b"{\\b1} foo {\\i1} bar {\\b0} bla {\\i0}"),
+            (b"<<guillemets>> a < b <unknown attr>x</unknown> <3 <blink", b"<<guillemets>> a < b x <3 <blink"),
+            (b"</font></font><font size=12>unclosed", b"{\\fs12}unclosed"),
+            (b"end<br><br>", b"end"),
+            // The trailing blank keeps the breaks, as in the C version.
+            (b"end<br><br> ", b"end\\N\\N"),
+            (b"a\\N\\", b"a\\N\\"),
+        ];
+        for &(input, expected) in cases {
+            let mut buf = [0u8; 256];
+            let n = run(input, &mut buf);
+            assert_eq!(&buf[..n], expected, "{:?}", core::str::from_utf8(input));
+            assert_eq!(buf[n], 0);
+        }
+
+        let mut req = 0usize;
+        let input = b"<i>x</i>";
+        let r = ffmpeg_rs_htmlmarkup_to_ass(input.as_ptr() as *const c_char, input.len(), core::ptr::null_mut(), 0, &mut req);
+        assert_eq!((r, req), (ENOSPC, 12));
+    }
+
+    #[test]
+    fn bounds_font_stack() {
+        // 20 nested fonts: only 15 fit, the rest are ignored.
+        let mut input = [0u8; 20 * 13 + 1];
+        for chunk in input.chunks_mut(13).take(20) {
+            chunk.copy_from_slice(b"<font size=1>");
+        }
+        input[20 * 13] = b'x';
+        let mut buf = [0u8; 256];
+        let n = run(&input, &mut buf);
+        assert_eq!(n, 15 * 6 + 1);
+    }
+}
@@ -0,0 +1,13 @@
+#![no_std]
+
+mod colors;
+mod convert;
+mod writer;
+
+pub use convert::*;
+
+#[cfg(not(test))]
+#[panic_handler]
+fn panic_handler(_: &core::panic::PanicInfo) -> ! {
+    loop {}
+}
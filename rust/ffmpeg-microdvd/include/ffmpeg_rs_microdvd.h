@@ -23,6 +23,61 @@ typedef struct FFmpegRsMicrodvdEvent {
 int ffmpeg_rs_microdvd_parse_line(const uint8_t *line, size_t line_len,
                                   FFmpegRsMicrodvdEvent *out);
 
+/*
+ * MicroDVD control codes. Codes are recognised at the start of each
+ * '|'-separated line, as in libavcodec/microdvddec.c; uppercase codes
+ * (global = 1) apply to the rest of the subtitle, lowercase ones to the
+ * current line only.
+ */
+typedef enum FFmpegRsMicrodvdTokenKind {
+    FFMPEG_RS_MICRODVD_TOKEN_TEXT = 0,
+    FFMPEG_RS_MICRODVD_TOKEN_LINE_BREAK = 1,  /* '|' */
+    FFMPEG_RS_MICRODVD_TOKEN_STYLE = 2,       /* {y:}; value1: 1 i, 2 b, 4 u, 8 s; also a bare '/' (italic) */
+    FFMPEG_RS_MICRODVD_TOKEN_COLOR = 3,       /* {c:$BBGGRR}; value1 = 0xBBGGRR */
+    FFMPEG_RS_MICRODVD_TOKEN_FONT = 4,        /* {f:name}; name in arg */
+    FFMPEG_RS_MICRODVD_TOKEN_SIZE = 5,        /* {s:n}; value1 */
+    FFMPEG_RS_MICRODVD_TOKEN_POSITION = 6,    /* {P:0|1}; value1 = 1 for bottom */
+    FFMPEG_RS_MICRODVD_TOKEN_COORDINATES = 7, /* {o:x,y}; value1, value2 */
+    FFMPEG_RS_MICRODVD_TOKEN_CHARSET = 8,     /* {H:name}; name in arg */
+} FFmpegRsMicrodvdTokenKind;
+
+typedef struct FFmpegRsMicrodvdToken {
+    uint32_t kind; /* FFmpegRsMicrodvdTokenKind */
+    int global;
+    /* The whole token, braces included. */
+    size_t offset;
+    size_t len;
+    size_t arg_offset;
+    size_t arg_len;
+    int32_t value1;
+    int32_t value2;
+} FFmpegRsMicrodvdToken;
+
+typedef struct FFmpegRsMicrodvdTokenizeResult {
+    size_t n_tokens; /* including any that did not fit */
+} FFmpegRsMicrodvdTokenizeResult;
+
+/*
+ * Splits a payload (the text after the frame numbers) into tokens.
+ * Input ends at the first NUL. Returns 0, or -EINVAL on invalid arguments.
+ */
+int ffmpeg_rs_microdvd_tokenize(const uint8_t *text, size_t text_len,
+                                FFmpegRsMicrodvdTokenizeResult *out,
+                                FFmpegRsMicrodvdToken *tokens, size_t tokens_cap);
+
+typedef struct FFmpegRsMicrodvdFrameRate {
+    int num;
+    int den;
+} FFmpegRsMicrodvdFrameRate;
+
+/*
+ * Detects the frame rate header line, e.g. "{1}{1}23.976" (frame <= 1,
+ * 3 < fps < 100). Returns 1 and fills *out for a header, 0 for any other
+ * line, or -EINVAL on invalid arguments.
+ */
+int ffmpeg_rs_microdvd_parse_framerate(const uint8_t *line, size_t line_len,
+                                       FFmpegRsMicrodvdFrameRate *out);
+
 #ifdef __cplusplus
 }
 #endif
@@ -0,0 +1,121 @@
+use core::ffi::c_int;
+
+use crate::writer::{in_bytes, out_ref, EINVAL};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FFmpegRsMicrodvdFrameRate {
+    pub num: c_int,
+    pub den: c_int,
+}
+
+fn is_space(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | b'\x0b' | b'\x0c' | b'\r')
+}
+
+fn skip_space(s: &[u8]) -> &[u8] {
+    let n = s.iter().take_while(|&&b| is_space(b)).count();
+    &s[n..]
+}
+
+// sscanf("%d"): whitespace, an optional sign and at least one digit.
+fn scan_int(s: &[u8]) -> Option<(i64, &[u8])> {
+    let s = skip_space(s);
+    let digits = matches!(s.first(), Some(b'-' | b'+')) as usize;
+    let n = s[digits..].iter().take_while(|b| b.is_ascii_digit()).count();
+    if n == 0 {
+        return None;
+    }
+    Some((crate::parse_i64_ascii(s.strip_prefix(b"+").unwrap_or(s))?, &s[digits + n..]))
+}
+
+// sscanf("%6lf") restricted to decimal notation, returned as an exact
+// fraction: at most six characters of sign, digits and one '.'.
+fn scan_fps(s: &[u8]) -> Option<(u64, u64)> {
+    let s = skip_space(s);
+    let s = &s[..s.len().min(6)];
+    let s = s.strip_prefix(b"+").unwrap_or(s);
+    let (mut num, mut den, mut any, mut dot) = (0u64, 1u64, false, false);
+    for &b in s {
+        match b {
+            b'0'..=b'9' => {
+                num = num * 10 + u64::from(b - b'0');
+                den *= if dot { 10 } else { 1 };
+                any = true;
+            }
+            b'.' if !dot => dot = true,
+            _ => break,
+        }
+    }
+    any.then_some((num, den))
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+// The header line some files start with: frame 0 or 1 showing the frame
+// rate as its text, e.g. "{1}{1}23.976" or "{1}{}25".
+fn parse_framerate(line: &[u8]) -> Option<FFmpegRsMicrodvdFrameRate> {
+    let (frame, rest) = scan_int(line.strip_prefix(b"{")?)?;
+    let rest = rest.strip_prefix(b"}{")?;
+    let rest = match rest.strip_prefix(b"}") {
+        Some(r) => r,
+        None => scan_int(rest)?.1.strip_prefix(b"}")?,
+    };
+    let (num, den) = scan_fps(rest)?;
+    if frame > 1 || num <= 3 * den || num >= 100 * den {
+        return None;
+    }
+    // Six characters bound the fraction well below av_d2q()'s 100000, so
+    // the reduced fraction is what the C demuxer gets.
+    let g = gcd(num, den);
+    Some(FFmpegRsMicrodvdFrameRate { num: (num / g) as c_int, den: (den / g) as c_int })
+}
+
+// Detects the `{1}{1}23.976` frame rate header (frame <= 1, 3 < fps < 100).
+// Returns 1 and fills `out` for a header line, 0 for any other line, or
+// -EINVAL on invalid arguments.
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_microdvd_parse_framerate(
+    line: *const u8,
+    line_len: usize,
+    out: *mut FFmpegRsMicrodvdFrameRate,
+) -> c_int {
+    let (data, out) = match (in_bytes(line, line_len), out_ref(out)) {
+        (Some(d), Some(o)) => (d, o),
+        _ => return EINVAL,
+    };
+    match parse_framerate(data) {
+        Some(r) => {
+            *out = r;
+            1
+        }
+        None => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_framerate_header() {
+        let rate = |num, den| Some(FFmpegRsMicrodvdFrameRate { num, den });
+        assert_eq!(parse_framerate(b"{1}{1}23.976"), rate(2997, 125));
+        assert_eq!(parse_framerate(b"{0}{}25"), rate(25, 1));
+        assert_eq!(parse_framerate(b"{1}{1} 29.970001"), rate(2997, 100));
+        assert_eq!(parse_framerate(b"{ -3}{ +7}+59.94fps"), rate(2997, 50));
+        for line in [&b"{2}{2}25"[..], b"{1}{1}3", b"{1}{1}100", b"{1}{1}Hello", b"{1}{x}25", b"{1}25"] {
+            assert_eq!(parse_framerate(line), None);
+        }
+        let mut out = FFmpegRsMicrodvdFrameRate::default();
+        assert_eq!(ffmpeg_rs_microdvd_parse_framerate(b"{1}{1}24".as_ptr(), 8, &mut out), 1);
+        assert_eq!(out, FFmpegRsMicrodvdFrameRate { num: 24, den: 1 });
+        assert_eq!(ffmpeg_rs_microdvd_parse_framerate(b"{10}{20}Hi".as_ptr(), 10, &mut out), 0);
+        assert_eq!(ffmpeg_rs_microdvd_parse_framerate(core::ptr::null(), 0, &mut out), EINVAL);
+    }
+}
@@ -2,6 +2,15 @@
 
 use core::ffi::c_int;
 
+mod framerate;
+mod tokens;
+mod writer;
+
+pub use framerate::*;
+pub use tokens::*;
+
+use writer::{in_bytes, out_ref};
+
 #[cfg(not(test))]
 #[panic_handler]
 fn panic_handler(_: &core::panic::PanicInfo) -> ! {
@@ -17,7 +26,7 @@ pub struct FFmpegRsMicrodvdEvent {
     pub payload_len: usize,
 }
 
-fn parse_i64_ascii(s: &[u8]) -> Option<i64> {
+pub(crate) fn parse_i64_ascii(s: &[u8]) -> Option<i64> {
     if s.is_empty() {
         return None;
     }
@@ -31,7 +40,7 @@ fn parse_i64_ascii(s: &[u8]) -> Option<i64> {
     let mut any = false;
     while i < s.len() {
         let b = s[i];
-        if b < b'0' || b > b'9' {
+        if !b.is_ascii_digit() {
             break;
         }
         any = true;
@@ -61,10 +70,10 @@ pub extern "C" fn ffmpeg_rs_microdvd_parse_line(
     line_len: usize,
     out: *mut FFmpegRsMicrodvdEvent,
 ) -> c_int {
-    if line.is_null() || out.is_null() {
-        return -1;
-    }
-    let data = unsafe { core::slice::from_raw_parts(line, line_len) };
+    let (data, out) = match (in_bytes(line, line_len), out_ref(out)) {
+        (Some(d), Some(o)) => (d, o),
+        _ => return -1,
+    };
     if data.is_empty() || data[0] != b'{' {
         return -2;
     }
@@ -105,14 +114,12 @@ pub extern "C" fn ffmpeg_rs_microdvd_parse_line(
     let payload_off = close1 + 1;
     let payload_len = data.len().saturating_sub(payload_off);
 
-    unsafe {
-        *out = FFmpegRsMicrodvdEvent {
-            start_frame,
-            duration_frames,
-            payload_offset: payload_off,
-            payload_len,
-        };
-    }
+    *out = FFmpegRsMicrodvdEvent {
+        start_frame,
+        duration_frames,
+        payload_offset: payload_off,
+        payload_len,
+    };
     0
 }
 
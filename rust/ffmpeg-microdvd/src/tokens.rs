@@ -0,0 +1,286 @@
+use core::ffi::c_int;
+
+use crate::writer::{in_bytes, out_array, out_ref, EINVAL};
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FFmpegRsMicrodvdTokenKind {
+    /// Literal text, up to the next `|`.
+    Text = 0,
+    /// `|`.
+    LineBreak = 1,
+    /// `{y:...}`. `value1` is a bitmask: 1 italic, 2 bold, 4 underline,
+    /// 8 strike-through. A `/` before or after the codes of a line is
+    /// reported as a one-byte italic Style token.
+    Style = 2,
+    /// `{c:$BBGGRR}`; `value1` is 0xBBGGRR.
+    Color = 3,
+    /// `{f:name}`; the name is the arg span.
+    Font = 4,
+    /// `{s:n}`; `value1` is the size.
+    Size = 5,
+    /// `{P:0}` (top) or `{P:1}` (bottom); `value1` is 1 for bottom.
+    Position = 6,
+    /// `{o:x,y}`; `value1`/`value2` are the coordinates.
+    Coordinates = 7,
+    /// `{H:name}`; the name is the arg span.
+    Charset = 8,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FFmpegRsMicrodvdToken {
+    /// FFmpegRsMicrodvdTokenKind.
+    pub kind: u32,
+    /// Uppercase code, applying to the rest of the subtitle rather than the
+    /// current line. `{o:}` is global too, as in microdvddec.
+    pub global: c_int,
+    /// The whole token, braces included.
+    pub offset: usize,
+    pub len: usize,
+    pub arg_offset: usize,
+    pub arg_len: usize,
+    pub value1: i32,
+    pub value2: i32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FFmpegRsMicrodvdTokenizeResult {
+    /// Total tokens, including any that did not fit the caller array.
+    pub n_tokens: usize,
+}
+
+const STYLES: &[u8] = b"ibus";
+
+impl FFmpegRsMicrodvdToken {
+    fn new(kind: FFmpegRsMicrodvdTokenKind, offset: usize, len: usize) -> Self {
+        FFmpegRsMicrodvdToken { kind: kind as u32, global: 0, offset, len, arg_offset: 0, arg_len: 0, value1: 0, value2: 0 }
+    }
+}
+
+// strtol(): leading whitespace, an optional sign, then digits in `radix`
+// (a 0x prefix is accepted in base 16). Returns the value, clamped like
+// strtol, and the bytes consumed, which is 0 without any digits.
+fn strtol(s: &[u8], radix: u32) -> (i64, usize) {
+    let mut i = 0usize;
+    while i < s.len() && matches!(s[i], b' ' | b'\t' | b'\n' | b'\x0b' | b'\x0c' | b'\r') {
+        i += 1;
+    }
+    let neg = s.get(i) == Some(&b'-');
+    if matches!(s.get(i), Some(b'-' | b'+')) {
+        i += 1;
+    }
+    if radix == 16
+        && s.get(i) == Some(&b'0')
+        && matches!(s.get(i + 1), Some(b'x' | b'X'))
+        && s.get(i + 2).is_some_and(|b| b.is_ascii_hexdigit())
+    {
+        i += 2;
+    }
+    let digits = i;
+    let mut v: i64 = 0;
+    while let Some(d) = s.get(i).and_then(|&b| (b as char).to_digit(radix)) {
+        let d = i64::from(d);
+        v = v.saturating_mul(i64::from(radix));
+        v = if neg { v.saturating_sub(d) } else { v.saturating_add(d) };
+        i += 1;
+    }
+    if i == digits {
+        return (0, 0);
+    }
+    (v, i)
+}
+
+// One `{x:...}` code starting at `start`, mirroring microdvd_load_tags().
+// Returns the token and the offset past its closing brace, or None if the
+// bytes are not a valid code and should be treated as text.
+fn parse_code(data: &[u8], start: usize) -> Option<(FFmpegRsMicrodvdToken, usize)> {
+    use FFmpegRsMicrodvdTokenKind as K;
+
+    let c = *data.get(start + 1)?;
+    if data.get(start + 2) != Some(&b':') {
+        return None;
+    }
+    let mut s = start + 3;
+    let mut tok = FFmpegRsMicrodvdToken::new(K::Text, start, 0);
+    tok.global = c.is_ascii_uppercase() as c_int;
+    let name = |s: usize| data[s..].iter().position(|&b| b == b'}').map(|len| (s, len));
+    match c {
+        b'y' | b'Y' => {
+            tok.kind = K::Style as u32;
+            // Style letters are capped like the C loop, 256 bytes from '{'.
+            while s < data.len() && data[s] != b'}' && s - start < 256 {
+                if let Some(bit) = STYLES.iter().position(|&b| b == data[s]) {
+                    tok.value1 |= 1 << bit;
+                }
+                s += 1;
+            }
+        }
+        b'c' | b'C' => {
+            tok.kind = K::Color as u32;
+            while matches!(data.get(s), Some(b'$' | b'#')) {
+                s += 1;
+            }
+            let (v, n) = strtol(&data[s..], 16);
+            tok.value1 = (v & 0xff_ffff) as i32;
+            s += n;
+        }
+        b'f' | b'F' | b'H' => {
+            tok.kind = if c == b'H' { K::Charset } else { K::Font } as u32;
+            (tok.arg_offset, tok.arg_len) = name(s)?;
+            s += tok.arg_len;
+        }
+        b's' | b'S' => {
+            tok.kind = K::Size as u32;
+            let (v, n) = strtol(&data[s..], 10);
+            tok.value1 = v as i32;
+            s += n;
+        }
+        b'P' => {
+            tok.kind = K::Position as u32;
+            tok.value1 = (*data.get(s)? == b'1') as i32;
+            s += 1;
+        }
+        b'o' => {
+            tok.kind = K::Coordinates as u32;
+            tok.global = 1;
+            let (x, n) = strtol(&data[s..], 10);
+            s += n;
+            if data.get(s) != Some(&b',') {
+                return None;
+            }
+            let (y, n) = strtol(&data[s + 1..], 10);
+            s += 1 + n;
+            (tok.value1, tok.value2) = (x as i32, y as i32);
+        }
+        _ => return None,
+    }
+    if data.get(s) != Some(&b'}') {
+        return None;
+    }
+    tok.len = s + 1 - start;
+    Some((tok, s + 1))
+}
+
+fn italic_slash(data: &[u8], i: usize, emit: &mut impl FnMut(FFmpegRsMicrodvdToken)) -> usize {
+    if data.get(i) != Some(&b'/') {
+        return i;
+    }
+    let mut tok = FFmpegRsMicrodvdToken::new(FFmpegRsMicrodvdTokenKind::Style, i, 1);
+    tok.value1 = 1;
+    emit(tok);
+    i + 1
+}
+
+// Codes are only recognised at the start of each `|`-separated line; once
+// one fails to parse, the rest of the line is text.
+fn tokenize(data: &[u8], mut emit: impl FnMut(FFmpegRsMicrodvdToken)) {
+    // The decoder stops at a NUL like the C string it receives.
+    let data = &data[..data.iter().position(|&b| b == 0).unwrap_or(data.len())];
+    let mut i = 0usize;
+    while i < data.len() {
+        i = italic_slash(data, i, &mut emit);
+        let mut ok = true;
+        while ok && data.get(i) == Some(&b'{') {
+            match parse_code(data, i) {
+                Some((tok, end)) => {
+                    emit(tok);
+                    i = end;
+                }
+                None => ok = false,
+            }
+        }
+        if ok {
+            i = italic_slash(data, i, &mut emit);
+        }
+        let text = i;
+        while i < data.len() && data[i] != b'|' {
+            i += 1;
+        }
+        if i > text {
+            emit(FFmpegRsMicrodvdToken::new(FFmpegRsMicrodvdTokenKind::Text, text, i - text));
+        }
+        if i < data.len() {
+            emit(FFmpegRsMicrodvdToken::new(FFmpegRsMicrodvdTokenKind::LineBreak, i, 1));
+            i += 1;
+        }
+    }
+}
+
+// Splits a MicroDVD payload (the text after the frame numbers) into control
+// codes, text runs and line breaks. Returns 0, or -EINVAL on invalid
+// arguments.
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_microdvd_tokenize(
+    text: *const u8,
+    text_len: usize,
+    out: *mut FFmpegRsMicrodvdTokenizeResult,
+    tokens: *mut FFmpegRsMicrodvdToken,
+    tokens_cap: usize,
+) -> c_int {
+    let (data, out) = match (in_bytes(text, text_len), out_ref(out)) {
+        (Some(d), Some(o)) => (d, o),
+        _ => return EINVAL,
+    };
+    let tokens = out_array(tokens, tokens_cap);
+    let mut n = 0usize;
+    tokenize(data, |tok| {
+        if let Some(slot) = tokens.get_mut(n) {
+            *slot = tok;
+        }
+        n += 1;
+    });
+    out.n_tokens = n;
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use FFmpegRsMicrodvdTokenKind as K;
+
+    fn run(text: &[u8]) -> ([FFmpegRsMicrodvdToken; 16], usize) {
+        let mut toks = [FFmpegRsMicrodvdToken::new(K::Text, 0, 0); 16];
+        let mut out = FFmpegRsMicrodvdTokenizeResult::default();
+        assert_eq!(ffmpeg_rs_microdvd_tokenize(text.as_ptr(), text.len(), &mut out, toks.as_mut_ptr(), toks.len()), 0);
+        (toks, out.n_tokens)
+    }
+
+    #[test]
+    fn tokenizes_codes_per_line() {
+        let text = b"{Y:ub}{c:$0000ff}{f:Arial}Hi {s:9}|/{P:0}{o:-10,20}{H:cp1250}{s:x}there|";
+        let (t, n) = run(text);
+        let got: [(u32, c_int, i32, i32, &[u8]); 11] = core::array::from_fn(|i| {
+            (t[i].kind, t[i].global, t[i].value1, t[i].value2, &text[t[i].offset..t[i].offset + t[i].len])
+        });
+        assert_eq!(n, 11);
+        assert_eq!(
+            got,
+            [
+                (K::Style as u32, 1, 6, 0, &b"{Y:ub}"[..]),
+                (K::Color as u32, 0, 0xff, 0, b"{c:$0000ff}"),
+                (K::Font as u32, 0, 0, 0, b"{f:Arial}"),
+                (K::Text as u32, 0, 0, 0, b"Hi {s:9}"),
+                (K::LineBreak as u32, 0, 0, 0, b"|"),
+                (K::Style as u32, 0, 1, 0, b"/"),
+                (K::Position as u32, 1, 0, 0, b"{P:0}"),
+                (K::Coordinates as u32, 1, -10, 20, b"{o:-10,20}"),
+                (K::Charset as u32, 1, 0, 0, b"{H:cp1250}"),
+                (K::Text as u32, 0, 0, 0, b"{s:x}there"),
+                (K::LineBreak as u32, 0, 0, 0, b"|"),
+            ]
+        );
+        assert_eq!(&text[t[2].arg_offset..t[2].arg_offset + t[2].arg_len], b"Arial");
+        assert_eq!(&text[t[8].arg_offset..t[8].arg_offset + t[8].arg_len], b"cp1250");
+
+        // {p:} (only {P:} exists) and unknown codes are text; a NUL ends the input.
+        let (t, n) = run(b"{p:1}{x:1}a\0|b");
+        assert_eq!((n, t[0].kind, t[0].len), (1, K::Text as u32, 11));
+
+        // Capacity is respected while the total is still counted.
+        let mut out = FFmpegRsMicrodvdTokenizeResult::default();
+        assert_eq!(ffmpeg_rs_microdvd_tokenize(b"a|b".as_ptr(), 3, &mut out, core::ptr::null_mut(), 0), 0);
+        assert_eq!(out.n_tokens, 3);
+    }
+}
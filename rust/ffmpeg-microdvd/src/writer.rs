@@ -0,0 +1,27 @@
+use core::ffi::c_int;
+
+pub(crate) const EINVAL: c_int = -22;
+
+/// Borrows a caller-owned input buffer; NULL yields None.
+pub(crate) fn in_bytes<'a>(p: *const u8, len: usize) -> Option<&'a [u8]> {
+    if p.is_null() {
+        return None;
+    }
+    Some(unsafe { core::slice::from_raw_parts(p, len) })
+}
+
+/// Borrows a caller-owned result struct.
+pub(crate) fn out_ref<'a, T>(p: *mut T) -> Option<&'a mut T> {
+    if p.is_null() {
+        return None;
+    }
+    Some(unsafe { &mut *p })
+}
+
+/// Borrows a caller-owned output array; NULL yields an empty array.
+pub(crate) fn out_array<'a, T>(p: *mut T, n: usize) -> &'a mut [T] {
+    if p.is_null() || n == 0 {
+        return &mut [];
+    }
+    unsafe { core::slice::from_raw_parts_mut(p, n) }
+}
@@ -23,6 +23,54 @@ typedef struct FFmpegRsMpl2Event {
 int ffmpeg_rs_mpl2_parse_line(const uint8_t *line, size_t line_len,
                               FFmpegRsMpl2Event *out);
 
+/* Line style prefixes. */
+#define FFMPEG_RS_MPL2_ITALIC    1 /* '/' */
+#define FFMPEG_RS_MPL2_BOLD      2 /* '\\' */
+#define FFMPEG_RS_MPL2_UNDERLINE 4 /* '_' */
+
+typedef struct FFmpegRsMpl2Line {
+    /* Line text without its style prefixes or the '|' separator. */
+    size_t offset;
+    size_t len;
+    uint32_t flags;    /* FFMPEG_RS_MPL2_* */
+    size_t prefix_len; /* the prefixes, in file order, end at offset */
+} FFmpegRsMpl2Line;
+
+typedef struct FFmpegRsMpl2LinesResult {
+    size_t n_lines; /* including any that did not fit */
+} FFmpegRsMpl2LinesResult;
+
+/*
+ * Splits a payload (the text after the timestamps) on '|' as the mpl2
+ * decoder reads it: one leading space is skipped, and each line may start
+ * with a run of style prefixes. A non-empty payload yields one line more
+ * than it has separators. Returns 0, or -EINVAL on invalid arguments.
+ */
+int ffmpeg_rs_mpl2_split_payload(const uint8_t *text, size_t text_len,
+                                 FFmpegRsMpl2LinesResult *out,
+                                 FFmpegRsMpl2Line *lines, size_t lines_cap);
+
+typedef struct FFmpegRsMpl2Cue {
+    int64_t start;    /* in the requested time base */
+    int64_t duration; /* -1 if unknown */
+    size_t payload_offset;
+    size_t payload_len;
+    size_t n_lines;   /* including any that did not fit */
+} FFmpegRsMpl2Cue;
+
+/*
+ * ffmpeg_rs_mpl2_parse_line() with the times rescaled to tb_num/tb_den
+ * (rounded to nearest, ties away from zero) and the payload split as by
+ * ffmpeg_rs_mpl2_split_payload(), with offsets relative to line. The
+ * duration is taken between the rounded start and end.
+ * Returns 0, -EINVAL on invalid arguments or a non-positive time base, the
+ * ffmpeg_rs_mpl2_parse_line() error (-2 to -7) for a malformed line, or
+ * -ERANGE if a time overflows int64_t.
+ */
+int ffmpeg_rs_mpl2_parse_line_ex(const uint8_t *line, size_t line_len,
+                                 int tb_num, int tb_den, FFmpegRsMpl2Cue *out,
+                                 FFmpegRsMpl2Line *lines, size_t lines_cap);
+
 #ifdef __cplusplus
 }
 #endif
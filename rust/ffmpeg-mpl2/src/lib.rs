@@ -2,6 +2,13 @@
 
 use core::ffi::c_int;
 
+mod lines;
+mod writer;
+
+pub use lines::*;
+
+use writer::{in_bytes, out_ref};
+
 #[cfg(not(test))]
 #[panic_handler]
 fn panic_handler(_: &core::panic::PanicInfo) -> ! {
@@ -31,7 +38,7 @@ fn parse_i64_ascii(s: &[u8]) -> Option<i64> {
     let mut any = false;
     while i < s.len() {
         let b = s[i];
-        if b < b'0' || b > b'9' {
+        if !b.is_ascii_digit() {
             break;
         }
         any = true;
@@ -55,60 +62,65 @@ fn find_byte(hay: &[u8], b: u8, start: usize) -> Option<usize> {
     None
 }
 
-#[no_mangle]
-pub extern "C" fn ffmpeg_rs_mpl2_parse_line(
-    line: *const u8,
-    line_len: usize,
-    out: *mut FFmpegRsMpl2Event,
-) -> c_int {
-    if line.is_null() || out.is_null() {
-        return -1;
-    }
-    let data = unsafe { core::slice::from_raw_parts(line, line_len) };
+pub(crate) struct Timing {
+    pub(crate) start: i64,
+    /// None for `[]` or an end before the start.
+    pub(crate) end: Option<i64>,
+    pub(crate) payload_offset: usize,
+}
+
+pub(crate) fn parse_timing(data: &[u8]) -> Result<Timing, c_int> {
     if data.is_empty() || data[0] != b'[' {
-        return -2;
+        return Err(-2);
     }
     let close0 = match find_byte(data, b']', 1) {
         Some(i) => i,
-        None => return -3,
+        None => return Err(-3),
     };
     let start = match parse_i64_ascii(&data[1..close0]) {
         Some(v) => v,
-        None => return -4,
+        None => return Err(-4),
     };
     if close0 + 1 >= data.len() || data[close0 + 1] != b'[' {
-        return -5;
+        return Err(-5);
     }
     let close1 = match find_byte(data, b']', close0 + 2) {
         Some(i) => i,
-        None => return -6,
+        None => return Err(-6),
     };
 
-    let duration = if close1 == close0 + 2 {
-        -1
+    let end = if close1 == close0 + 2 {
+        None
     } else {
-        let end = match parse_i64_ascii(&data[close0 + 2..close1]) {
-            Some(v) => v,
-            None => return -7,
-        };
-        if end < start {
-            -1
-        } else {
-            end.saturating_sub(start)
+        match parse_i64_ascii(&data[close0 + 2..close1]) {
+            Some(v) if v < start => None,
+            Some(v) => Some(v),
+            None => return Err(-7),
         }
     };
+    Ok(Timing { start, end, payload_offset: close1 + 1 })
+}
 
-    let payload_off = close1 + 1;
-    let payload_len = data.len().saturating_sub(payload_off);
-
-    unsafe {
-        *out = FFmpegRsMpl2Event {
-            start_ticks: start,
-            duration_ticks: duration,
-            payload_offset: payload_off,
-            payload_len,
-        };
-    }
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_mpl2_parse_line(
+    line: *const u8,
+    line_len: usize,
+    out: *mut FFmpegRsMpl2Event,
+) -> c_int {
+    let (data, out) = match (in_bytes(line, line_len), out_ref(out)) {
+        (Some(d), Some(o)) => (d, o),
+        _ => return -1,
+    };
+    let t = match parse_timing(data) {
+        Ok(t) => t,
+        Err(e) => return e,
+    };
+    *out = FFmpegRsMpl2Event {
+        start_ticks: t.start,
+        duration_ticks: t.end.map_or(-1, |end| end.saturating_sub(t.start)),
+        payload_offset: t.payload_offset,
+        payload_len: data.len().saturating_sub(t.payload_offset),
+    };
     0
 }
 
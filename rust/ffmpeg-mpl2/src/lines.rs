@@ -0,0 +1,218 @@
+use core::ffi::c_int;
+
+use crate::parse_timing;
+use crate::writer::{in_bytes, out_array, out_ref, EINVAL, ERANGE};
+
+/// `/` line prefix.
+pub const FFMPEG_RS_MPL2_ITALIC: u32 = 1;
+/// `\` line prefix.
+pub const FFMPEG_RS_MPL2_BOLD: u32 = 2;
+/// `_` line prefix.
+pub const FFMPEG_RS_MPL2_UNDERLINE: u32 = 4;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FFmpegRsMpl2Line {
+    /// Line text without its style prefixes or the `|` separator.
+    pub offset: usize,
+    pub len: usize,
+    /// FFMPEG_RS_MPL2_* bits for the prefixes.
+    pub flags: u32,
+    /// The prefixes themselves, ending at `offset`, in file order.
+    pub prefix_len: usize,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FFmpegRsMpl2LinesResult {
+    /// Total lines, including any that did not fit the caller array.
+    pub n_lines: usize,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FFmpegRsMpl2Cue {
+    /// In the caller's time base.
+    pub start: i64,
+    /// -1 if unknown.
+    pub duration: i64,
+    pub payload_offset: usize,
+    pub payload_len: usize,
+    /// Total lines, including any that did not fit the caller array.
+    pub n_lines: usize,
+}
+
+// Rescales deciseconds to num/den units, rounding to nearest with ties away
+// from zero like av_rescale().
+fn rescale_ds(v: i64, num: c_int, den: c_int) -> Option<i64> {
+    let n = v as i128 * den as i128;
+    let d = num as i128 * 10;
+    let q = (n.abs() + d / 2) / d;
+    i64::try_from(if n < 0 { -q } else { q }).ok()
+}
+
+// Splits a payload on `|` the way mpl2dec reads it: one leading space is
+// skipped, and each line may start with any run of `/`, `\` and `_`.
+// A non-empty payload always has one more line than it has separators.
+fn split_lines(data: &[u8], base: usize, mut emit: impl FnMut(FFmpegRsMpl2Line)) {
+    let data = &data[..data.iter().position(|&b| b == 0).unwrap_or(data.len())];
+    let mut i = usize::from(data.first() == Some(&b' '));
+    if i >= data.len() {
+        return;
+    }
+    loop {
+        let prefix = i;
+        let mut flags = 0;
+        while let Some(f) = data.get(i).and_then(|&b| match b {
+            b'/' => Some(FFMPEG_RS_MPL2_ITALIC),
+            b'\\' => Some(FFMPEG_RS_MPL2_BOLD),
+            b'_' => Some(FFMPEG_RS_MPL2_UNDERLINE),
+            _ => None,
+        }) {
+            flags |= f;
+            i += 1;
+        }
+        let text = i;
+        while i < data.len() && data[i] != b'|' {
+            i += 1;
+        }
+        emit(FFmpegRsMpl2Line { offset: base + text, len: i - text, flags, prefix_len: text - prefix });
+        if i >= data.len() {
+            break;
+        }
+        i += 1;
+    }
+}
+
+fn collect_lines(data: &[u8], base: usize, lines: &mut [FFmpegRsMpl2Line]) -> usize {
+    let mut n = 0usize;
+    split_lines(data, base, |l| {
+        if let Some(slot) = lines.get_mut(n) {
+            *slot = l;
+        }
+        n += 1;
+    });
+    n
+}
+
+// Splits an MPL2 payload (the text after the timestamps) into lines with
+// their style prefixes. Returns 0, or -EINVAL on invalid arguments.
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_mpl2_split_payload(
+    text: *const u8,
+    text_len: usize,
+    out: *mut FFmpegRsMpl2LinesResult,
+    lines: *mut FFmpegRsMpl2Line,
+    lines_cap: usize,
+) -> c_int {
+    let (data, out) = match (in_bytes(text, text_len), out_ref(out)) {
+        (Some(d), Some(o)) => (d, o),
+        _ => return EINVAL,
+    };
+    out.n_lines = collect_lines(data, 0, out_array(lines, lines_cap));
+    0
+}
+
+// ffmpeg_rs_mpl2_parse_line() with the times rescaled to tb_num/tb_den and
+// the payload split into lines (offsets relative to `line`). The duration is
+// the difference of the rescaled end and start, so start + duration is the
+// rounded end. Returns 0, -EINVAL on invalid arguments or a non-positive
+// time base, or the ffmpeg_rs_mpl2_parse_line() error for a malformed line;
+// -ERANGE if a time does not fit in int64_t after rescaling.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "C" fn ffmpeg_rs_mpl2_parse_line_ex(
+    line: *const u8,
+    line_len: usize,
+    tb_num: c_int,
+    tb_den: c_int,
+    out: *mut FFmpegRsMpl2Cue,
+    lines: *mut FFmpegRsMpl2Line,
+    lines_cap: usize,
+) -> c_int {
+    let (data, out) = match (in_bytes(line, line_len), out_ref(out)) {
+        (Some(d), Some(o)) => (d, o),
+        _ => return EINVAL,
+    };
+    if tb_num <= 0 || tb_den <= 0 {
+        return EINVAL;
+    }
+    let t = match parse_timing(data) {
+        Ok(t) => t,
+        Err(e) => return e,
+    };
+    let start = match rescale_ds(t.start, tb_num, tb_den) {
+        Some(v) => v,
+        None => return ERANGE,
+    };
+    let duration = match t.end.map(|end| rescale_ds(end, tb_num, tb_den)) {
+        None => -1,
+        Some(Some(end)) => end.saturating_sub(start),
+        Some(None) => return ERANGE,
+    };
+    let payload = &data[t.payload_offset.min(data.len())..];
+    *out = FFmpegRsMpl2Cue {
+        start,
+        duration,
+        payload_offset: t.payload_offset,
+        payload_len: payload.len(),
+        n_lines: collect_lines(payload, t.payload_offset, out_array(lines, lines_cap)),
+    };
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(text: &[u8]) -> ([FFmpegRsMpl2Line; 8], usize) {
+        let mut lines = [FFmpegRsMpl2Line::default(); 8];
+        let mut out = FFmpegRsMpl2LinesResult::default();
+        assert_eq!(ffmpeg_rs_mpl2_split_payload(text.as_ptr(), text.len(), &mut out, lines.as_mut_ptr(), 8), 0);
+        (lines, out.n_lines)
+    }
+
+    #[test]
+    fn splits_styled_lines() {
+        let text = b" /italic|\\/bold italic|plain|";
+        let (l, n) = split(text);
+        let spans: [(&[u8], u32, usize); 4] =
+            core::array::from_fn(|i| (&text[l[i].offset..l[i].offset + l[i].len], l[i].flags, l[i].prefix_len));
+        assert_eq!(n, 4);
+        assert_eq!(
+            spans,
+            [
+                (&b"italic"[..], FFMPEG_RS_MPL2_ITALIC, 1),
+                (b"bold italic", FFMPEG_RS_MPL2_ITALIC | FFMPEG_RS_MPL2_BOLD, 2),
+                (b"plain", 0, 0),
+                (b"", 0, 0),
+            ]
+        );
+        assert_eq!(split(b" ").1, 0);
+        assert_eq!(split(b"_").0[0], FFmpegRsMpl2Line { offset: 1, len: 0, flags: FFMPEG_RS_MPL2_UNDERLINE, prefix_len: 1 });
+        let mut out = FFmpegRsMpl2LinesResult::default();
+        assert_eq!(ffmpeg_rs_mpl2_split_payload(core::ptr::null(), 0, &mut out, core::ptr::null_mut(), 0), EINVAL);
+    }
+
+    #[test]
+    fn rescales_to_time_base() {
+        let line = b"[41][53]/a|b";
+        let mut cue = FFmpegRsMpl2Cue::default();
+        let mut lines = [FFmpegRsMpl2Line::default(); 1];
+        let r = ffmpeg_rs_mpl2_parse_line_ex(line.as_ptr(), line.len(), 1, 1000, &mut cue, lines.as_mut_ptr(), 1);
+        assert_eq!((r, cue.start, cue.duration, cue.payload_offset, cue.n_lines), (0, 4100, 1200, 8, 2));
+        assert_eq!((lines[0].offset, lines[0].len, lines[0].flags), (9, 1, FFMPEG_RS_MPL2_ITALIC));
+
+        // The duration is taken between the rounded times.
+        let r = ffmpeg_rs_mpl2_parse_line_ex(b"[-1][2]".as_ptr(), 7, 7, 9000, &mut cue, core::ptr::null_mut(), 0);
+        assert_eq!((r, cue.start, cue.duration), (0, -129, 386));
+        // 1.5 ticks: ties round away from zero.
+        let r = ffmpeg_rs_mpl2_parse_line_ex(b"[5][]x".as_ptr(), 6, 1, 3, &mut cue, core::ptr::null_mut(), 0);
+        assert_eq!((r, cue.start, cue.duration, cue.n_lines), (0, 2, -1, 1));
+
+        let big = b"[9223372036854775807][]";
+        assert_eq!(ffmpeg_rs_mpl2_parse_line_ex(big.as_ptr(), big.len(), 1, 1000, &mut cue, core::ptr::null_mut(), 0), ERANGE);
+        assert_eq!(ffmpeg_rs_mpl2_parse_line_ex(line.as_ptr(), line.len(), 0, 1000, &mut cue, core::ptr::null_mut(), 0), EINVAL);
+        assert_eq!(ffmpeg_rs_mpl2_parse_line_ex(b"x".as_ptr(), 1, 1, 10, &mut cue, core::ptr::null_mut(), 0), -2);
+    }
+}
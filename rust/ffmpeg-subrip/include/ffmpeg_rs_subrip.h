@@ -12,6 +12,11 @@ typedef struct FFmpegRsSubripEvent {
     int64_t duration_ms;
     size_t payload_offset;
     size_t payload_len;
+    int64_t index; /* numeric cue index, or -1 */
+    /* Trailing "X1:.. X2:.. Y1:.. Y2:.." rectangle; only valid when has_rect
+     * is non-zero. */
+    int has_rect;
+    int32_t x1, x2, y1, y2;
 } FFmpegRsSubripEvent;
 
 typedef struct FFmpegRsSubripParseResult {
@@ -19,14 +24,79 @@ typedef struct FFmpegRsSubripParseResult {
 } FFmpegRsSubripParseResult;
 
 /*
- * Parses SubRip (SRT) into events. Returns 0 on success.
+ * Unless noted otherwise, functions return 0 on success, -EINVAL on invalid
+ * arguments and, for the writer, -ENOSPC when the output buffer is too small.
+ */
+
+/*
+ * Parses SubRip (SRT) into events. Returns 0 on success, or -1 on invalid
+ * arguments.
  * Payload is returned as a slice into the input buffer.
  */
 int ffmpeg_rs_subrip_parse(const uint8_t *text, size_t text_len,
                            FFmpegRsSubripParseResult *out,
                            FFmpegRsSubripEvent *events, size_t events_cap);
 
+/*
+ * Parsing with a selectable mode. FFMPEG_RS_SUBRIP_MODE_STRICT behaves like
+ * ffmpeg_rs_subrip_parse(); FFMPEG_RS_SUBRIP_MODE_LENIENT recovers cue
+ * boundaries in damaged files and reports each repair.
+ */
+typedef enum FFmpegRsSubripMode {
+    FFMPEG_RS_SUBRIP_MODE_STRICT = 0,
+    FFMPEG_RS_SUBRIP_MODE_LENIENT = 1,
+} FFmpegRsSubripMode;
+
+typedef enum FFmpegRsSubripRepairKind {
+    FFMPEG_RS_SUBRIP_REPAIR_MISSING_INDEX = 0,      /* index is -1 */
+    FFMPEG_RS_SUBRIP_REPAIR_DOT_SEPARATOR = 1,      /* '.' before the milliseconds */
+    FFMPEG_RS_SUBRIP_REPAIR_SHORT_FIELD = 2,        /* e.g. single-digit hours */
+    FFMPEG_RS_SUBRIP_REPAIR_MALFORMED_ARROW = 3,    /* "->" or no spaces around it */
+    FFMPEG_RS_SUBRIP_REPAIR_NEGATIVE_TIMESTAMP = 4, /* clamped to 0 */
+    FFMPEG_RS_SUBRIP_REPAIR_BLANK_LINE_IN_CUE = 5,  /* following text kept */
+    FFMPEG_RS_SUBRIP_REPAIR_MISSING_BLANK_LINE = 6, /* new cue right after text */
+    FFMPEG_RS_SUBRIP_REPAIR_END_BEFORE_START = 7,   /* duration set to 0 */
+    FFMPEG_RS_SUBRIP_REPAIR_SKIPPED_LINE = 8,       /* text outside any cue */
+} FFmpegRsSubripRepairKind;
+
+typedef struct FFmpegRsSubripDiagnostic {
+    uint32_t kind;  /* FFmpegRsSubripRepairKind */
+    size_t line_no; /* 1-based */
+    /* The line the repair applies to, without its terminator. */
+    size_t offset;
+    size_t len;
+} FFmpegRsSubripDiagnostic;
+
+typedef struct FFmpegRsSubripParseExResult {
+    size_t n_events;
+    size_t n_diagnostics; /* including any that did not fit */
+} FFmpegRsSubripParseExResult;
+
+/* Returns 0, or -EINVAL on invalid arguments or an unknown mode. */
+int ffmpeg_rs_subrip_parse_ex(const uint8_t *text, size_t text_len, uint32_t mode,
+                              FFmpegRsSubripParseExResult *out,
+                              FFmpegRsSubripEvent *events, size_t events_cap,
+                              FFmpegRsSubripDiagnostic *diagnostics, size_t diagnostics_cap);
+
+typedef struct FFmpegRsSubripWriteParams {
+    uint64_t first_index; /* number of the first block, usually 1 */
+    int crlf;             /* end lines with CRLF instead of LF */
+} FFmpegRsSubripWriteParams;
+
+/*
+ * Writes a SubRip document from events whose payloads are spans of text.
+ * Blocks are numbered sequentially in array order; events with has_rect get
+ * the X1/X2/Y1/Y2 suffix. Payload line endings are normalised and blank
+ * payload lines dropped. dst is NUL-terminated; *out_required receives the
+ * size including the NUL, and dst == NULL queries it.
+ * Returns 0, -EINVAL on invalid arguments, spans outside text or
+ * negative times, or -ENOSPC if dst is too small.
+ */
+int ffmpeg_rs_subrip_write(const FFmpegRsSubripWriteParams *params,
+                           const uint8_t *text, size_t text_len,
+                           const FFmpegRsSubripEvent *events, size_t n_events,
+                           char *dst, size_t dst_len, size_t *out_required);
+
 #ifdef __cplusplus
 }
 #endif
-
@@ -0,0 +1,124 @@
+use core::ffi::{c_char, c_int};
+
+use crate::writer::{in_array, in_bytes, in_ref, out_buf, Writer, EINVAL};
+use crate::FFmpegRsSubripEvent;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsSubripWriteParams {
+    /// Number of the first block; srtenc starts at 1.
+    pub first_index: u64,
+    /// Terminate lines with CRLF instead of LF.
+    pub crlf: c_int,
+}
+
+fn write_document(w: &mut Writer, p: &FFmpegRsSubripWriteParams, text: &[u8], events: &[FFmpegRsSubripEvent]) -> Result<(), c_int> {
+    let eol: &[u8] = if p.crlf != 0 { b"\r\n" } else { b"\n" };
+    for (n, e) in events.iter().enumerate() {
+        let end = e.payload_offset.checked_add(e.payload_len).ok_or(EINVAL)?;
+        let payload = text.get(e.payload_offset..end).ok_or(EINVAL)?;
+        if e.duration_ms < 0 {
+            return Err(EINVAL);
+        }
+        w.uint(p.first_index.saturating_add(n as u64));
+        w.bytes(eol);
+        w.timestamp_ms(e.start_ms)?;
+        w.bytes(b" --> ");
+        w.timestamp_ms(e.start_ms.checked_add(e.duration_ms).ok_or(EINVAL)?)?;
+        if e.has_rect != 0 {
+            // Same layout as srtenc.
+            for (key, v) in [(&b"  X1:"[..], e.x1), (b" X2:", e.x2), (b" Y1:", e.y1), (b" Y2:", e.y2)] {
+                w.bytes(key);
+                w.int_padded(i64::from(v), 3);
+            }
+        }
+        w.bytes(eol);
+        // CRLF, LF and lone CR all end a line. Blank lines would end the
+        // block early, so they are dropped.
+        for line in payload.split(|&b| b == b'\n').flat_map(|l| l.split(|&b| b == b'\r')) {
+            if !line.is_empty() {
+                w.bytes(line);
+                w.bytes(eol);
+            }
+        }
+        w.bytes(eol);
+    }
+    Ok(())
+}
+
+// Writes a SubRip document from `events` whose payloads are spans of
+// `text`, numbering the blocks from `params->first_index` in array order
+// and adding the X1/X2/Y1/Y2 suffix for events with a rectangle. Fills
+// `dst` (NUL-terminated) and reports the size needed including the NUL
+// through `out_required`; dst == NULL queries the size. Returns 0, -EINVAL
+// on invalid arguments, payload spans outside `text`, or negative start
+// times or durations, and -ENOSPC if `dst` was too small.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "C" fn ffmpeg_rs_subrip_write(
+    params: *const FFmpegRsSubripWriteParams,
+    text: *const u8,
+    text_len: usize,
+    events: *const FFmpegRsSubripEvent,
+    n_events: usize,
+    dst: *mut c_char,
+    dst_len: usize,
+    out_required: *mut usize,
+) -> c_int {
+    let text = match in_bytes(text, text_len) {
+        Some(t) => t,
+        None if text_len == 0 => &[],
+        None => return EINVAL,
+    };
+    let (p, events) = match (in_ref(params), in_array(events, n_events)) {
+        (Some(p), Some(e)) => (p, e),
+        _ => return EINVAL,
+    };
+    let mut w = Writer::new(out_buf(dst, dst_len));
+    if let Err(e) = write_document(&mut w, p, text, events) {
+        return e;
+    }
+    w.finish(out_required)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::writer::ENOSPC;
+    use crate::{ffmpeg_rs_subrip_parse, FFmpegRsSubripParseResult};
+
+    #[test]
+    fn writes_numbered_blocks() {
+        let text = b"Hello\r\n\r\nthere\rfriend\nBye\n";
+        let events = [
+            FFmpegRsSubripEvent::new(1000, 1500, (0, 21), 7, None),
+            FFmpegRsSubripEvent::new(3_600_000 * 99 + 61_001, 0, (21, 4), -1, Some([100, 600, -5, 50])),
+        ];
+        let mut params = FFmpegRsSubripWriteParams { first_index: 1, crlf: 0 };
+        let mut buf = [0u8; 256];
+        let mut req = 0usize;
+        let r = ffmpeg_rs_subrip_write(&params, text.as_ptr(), text.len(), events.as_ptr(), 2, buf.as_mut_ptr() as *mut c_char, buf.len(), &mut req);
+        assert_eq!(r, 0);
+        let expected: &[u8] = b"1\n00:00:01,000 --> 00:00:02,500\nHello\nthere\nfriend\n\n\
+2\n99:01:01,001 --> 99:01:01,001  X1:100 X2:600 Y1:-05 Y2:050\nBye\n\n";
+        assert_eq!(&buf[..req - 1], expected);
+
+        // The output parses back to the same events.
+        let mut out = FFmpegRsSubripParseResult { n_events: 0 };
+        let mut back = events;
+        assert_eq!(ffmpeg_rs_subrip_parse(buf.as_ptr(), req - 1, &mut out, back.as_mut_ptr(), 2), 0);
+        assert_eq!((out.n_events, back[0].index, back[1].index, back[1].has_rect, back[1].y1), (2, 1, 2, 1, -5));
+        assert_eq!((back[1].start_ms, back[1].duration_ms), (events[1].start_ms, 0));
+
+        params.crlf = 1;
+        params.first_index = 10;
+        let r = ffmpeg_rs_subrip_write(&params, text.as_ptr(), text.len(), events.as_ptr(), 1, core::ptr::null_mut(), 0, &mut req);
+        assert_eq!((r, req), (ENOSPC, b"10\r\n00:00:01,000 --> 00:00:02,500\r\nHello\r\nthere\r\nfriend\r\n\r\n".len() + 1));
+
+        let bad = [FFmpegRsSubripEvent::new(0, -1, (0, 0), -1, None), FFmpegRsSubripEvent::new(0, 1, (20, 9), -1, None)];
+        for e in &bad {
+            let r = ffmpeg_rs_subrip_write(&params, text.as_ptr(), text.len(), e, 1, buf.as_mut_ptr() as *mut c_char, buf.len(), &mut req);
+            assert_eq!(r, EINVAL);
+        }
+    }
+}
@@ -0,0 +1,375 @@
+use core::ffi::c_int;
+
+use crate::writer::{in_bytes, out_array, out_ref, EINVAL};
+use crate::{chomp_cr, parse_index, parse_rect, parse_strict, FFmpegRsSubripEvent};
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FFmpegRsSubripMode {
+    /// ffmpeg_rs_subrip_parse() behaviour; no diagnostics.
+    Strict = 0,
+    /// Recovers cue boundaries heuristically and reports each repair.
+    Lenient = 1,
+}
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FFmpegRsSubripRepairKind {
+    /// Cue without an index line; `index` is -1.
+    MissingIndex = 0,
+    /// `.` instead of `,` before the milliseconds.
+    DotSeparator = 1,
+    /// A timestamp field with fewer digits than HH:MM:SS,mmm, such as a
+    /// single-digit hour.
+    ShortField = 2,
+    /// `->`, or an arrow without the surrounding spaces.
+    MalformedArrow = 3,
+    /// Negative timestamp, clamped to 0.
+    NegativeTimestamp = 4,
+    /// Blank line followed by more text of the same cue; the text is kept.
+    BlankLineInCue = 5,
+    /// A new cue starts without a blank line before it.
+    MissingBlankLine = 6,
+    /// End earlier than start; the duration is 0.
+    EndBeforeStart = 7,
+    /// Text outside any cue, dropped.
+    SkippedLine = 8,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FFmpegRsSubripDiagnostic {
+    /// FFmpegRsSubripRepairKind.
+    pub kind: u32,
+    /// 1-based line the repair applies to.
+    pub line_no: usize,
+    /// That line, without its terminator.
+    pub offset: usize,
+    pub len: usize,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FFmpegRsSubripParseExResult {
+    pub n_events: usize,
+    /// Total diagnostics, including any that did not fit the caller array.
+    pub n_diagnostics: usize,
+}
+
+#[derive(Copy, Clone)]
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+    line_no: usize,
+}
+
+#[derive(Copy, Clone)]
+struct Line<'a> {
+    offset: usize,
+    line_no: usize,
+    text: &'a [u8],
+}
+
+impl<'a> Cursor<'a> {
+    fn next(&mut self) -> Option<Line<'a>> {
+        if self.pos >= self.data.len() {
+            return None;
+        }
+        let rest = &self.data[self.pos..];
+        let end = rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+        let line = Line { offset: self.pos, line_no: self.line_no, text: chomp_cr(&rest[..end]) };
+        self.pos += (end + 1).min(rest.len());
+        self.line_no += 1;
+        Some(line)
+    }
+
+    fn skip_blank(&mut self) {
+        let mut la = *self;
+        while la.next().is_some_and(|l| is_blank(l.text)) {
+            *self = la;
+        }
+    }
+}
+
+fn is_blank(s: &[u8]) -> bool {
+    s.iter().all(|&b| b == b' ' || b == b'\t')
+}
+
+const DOT: u32 = 1 << 0;
+const SHORT: u32 = 1 << 1;
+const ARROW: u32 = 1 << 2;
+const NEGATIVE: u32 = 1 << 3;
+const REVERSED: u32 = 1 << 4;
+
+struct Timing {
+    start_ms: i64,
+    duration_ms: i64,
+    rect: Option<[i32; 4]>,
+    repairs: u32,
+}
+
+// One to `max` digits; fewer than `width` counts as a repair.
+fn field(s: &[u8], max: usize, width: usize, repairs: &mut u32) -> Option<(u64, usize)> {
+    let n = s.iter().take(max).take_while(|b| b.is_ascii_digit()).count();
+    if n == 0 {
+        return None;
+    }
+    if n < width {
+        *repairs |= SHORT;
+    }
+    let v = s[..n].iter().fold(0u64, |v, &d| v.saturating_mul(10).saturating_add(u64::from(d - b'0')));
+    Some((v, n))
+}
+
+// [-]H+:M{1,2}:S{1,2}[,.]m{1,3}; returns the time and the bytes consumed.
+fn parse_ts(s: &[u8], repairs: &mut u32) -> Option<(i64, usize)> {
+    let neg = s.first() == Some(&b'-');
+    let mut i = neg as usize;
+    let (hh, n) = field(&s[i..], 10, 2, repairs)?;
+    i += n;
+    let mut next = |i: &mut usize, sep: &[u8], max: usize, width: usize| {
+        if !sep.contains(s.get(*i)?) {
+            return None;
+        }
+        if s[*i] == b'.' {
+            *repairs |= DOT;
+        }
+        let (v, n) = field(&s[*i + 1..], max, width, repairs)?;
+        *i += 1 + n;
+        Some(v)
+    };
+    let mm = next(&mut i, b":", 2, 2)?;
+    let ss = next(&mut i, b":", 2, 2)?;
+    let ms = next(&mut i, b",.", 3, 3)?;
+    let total = (hh.saturating_mul(3600).saturating_add(mm * 60 + ss)).saturating_mul(1000).saturating_add(ms);
+    let total = i64::try_from(total).unwrap_or(i64::MAX);
+    if neg && total > 0 {
+        *repairs |= NEGATIVE;
+        return Some((0, i));
+    }
+    Some((total, i))
+}
+
+fn parse_timing(line: &[u8]) -> Option<Timing> {
+    let mut repairs = 0u32;
+    let s = &line[line.iter().position(|&b| b != b' ' && b != b'\t')?..];
+    let (start_ms, n) = parse_ts(s, &mut repairs)?;
+    let rest = &s[n..];
+    let ws = |s: &[u8]| s.iter().take_while(|&&b| b == b' ' || b == b'\t').count();
+    let before = ws(rest);
+    let rest = &rest[before..];
+    let arrow = if rest.starts_with(b"-->") {
+        3
+    } else if rest.starts_with(b"->") {
+        repairs |= ARROW;
+        2
+    } else {
+        return None;
+    };
+    let rest = &rest[arrow..];
+    let after = ws(rest);
+    if before == 0 || after == 0 {
+        repairs |= ARROW;
+    }
+    let (end_ms, n) = parse_ts(&rest[after..], &mut repairs)?;
+    if end_ms < start_ms {
+        repairs |= REVERSED;
+    }
+    Some(Timing {
+        start_ms,
+        duration_ms: (end_ms - start_ms).max(0),
+        rect: parse_rect(&rest[after + n..]),
+        repairs,
+    })
+}
+
+// A cue starting at `c`: an optional index line and the timing line.
+fn cue_start<'a>(mut c: Cursor<'a>) -> Option<(Option<Line<'a>>, Line<'a>, Timing, Cursor<'a>)> {
+    let first = c.next()?;
+    if let Some(t) = parse_timing(first.text) {
+        return Some((None, first, t, c));
+    }
+    if parse_index(first.text) < 0 {
+        return None;
+    }
+    let second = c.next()?;
+    let t = parse_timing(second.text)?;
+    Some((Some(first), second, t, c))
+}
+
+// Lenient parser. A number line only starts a cue when a timing line follows
+// it, so numbers at the end of dialogue stay text; a blank line only ends a
+// cue when a new cue or the end of input follows.
+fn parse_lenient(data: &[u8], mut emit: impl FnMut(FFmpegRsSubripEvent), mut diag: impl FnMut(FFmpegRsSubripRepairKind, Line)) {
+    use FFmpegRsSubripRepairKind::*;
+    let mut c = Cursor { data, pos: 0, line_no: 1 };
+    loop {
+        c.skip_blank();
+        let (index, timing_line, timing, after) = match cue_start(c) {
+            Some(s) => s,
+            None => match c.next() {
+                Some(l) => {
+                    diag(SkippedLine, l);
+                    continue;
+                }
+                None => break,
+            },
+        };
+        c = after;
+        if index.is_none() {
+            diag(MissingIndex, timing_line);
+        }
+        for (flag, kind) in [
+            (DOT, DotSeparator),
+            (SHORT, ShortField),
+            (ARROW, MalformedArrow),
+            (NEGATIVE, NegativeTimestamp),
+            (REVERSED, EndBeforeStart),
+        ] {
+            if timing.repairs & flag != 0 {
+                diag(kind, timing_line);
+            }
+        }
+
+        let payload_offset = c.pos;
+        let mut payload_end = c.pos;
+        loop {
+            let save = c;
+            let line = match c.next() {
+                Some(l) => l,
+                None => break,
+            };
+            if is_blank(line.text) {
+                let mut la = c;
+                la.skip_blank();
+                if la.pos >= data.len() || cue_start(la).is_some() {
+                    c = la;
+                    break;
+                }
+                diag(BlankLineInCue, line);
+                c = la;
+            } else if cue_start(save).is_some() {
+                diag(MissingBlankLine, line);
+                c = save;
+                break;
+            } else {
+                payload_end = line.offset + line.text.len();
+            }
+        }
+
+        let index = index.map_or(-1, |l| parse_index(l.text));
+        emit(FFmpegRsSubripEvent::new(
+            timing.start_ms,
+            timing.duration_ms,
+            (payload_offset, payload_end - payload_offset),
+            index,
+            timing.rect,
+        ));
+    }
+}
+
+// Parses SubRip in the given FFmpegRsSubripMode. Lenient mode reports each
+// repair in `diagnostics`; entries beyond `diagnostics_cap` are counted but
+// not stored. Returns 0, or -EINVAL on invalid arguments or an unknown
+// mode.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "C" fn ffmpeg_rs_subrip_parse_ex(
+    text: *const u8,
+    text_len: usize,
+    mode: u32,
+    out: *mut FFmpegRsSubripParseExResult,
+    events: *mut FFmpegRsSubripEvent,
+    events_cap: usize,
+    diagnostics: *mut FFmpegRsSubripDiagnostic,
+    diagnostics_cap: usize,
+) -> c_int {
+    let (data, out) = match (in_bytes(text, text_len), out_ref(out)) {
+        (Some(d), Some(o)) => (d, o),
+        _ => return EINVAL,
+    };
+    let events = out_array(events, events_cap);
+    let diagnostics = out_array(diagnostics, diagnostics_cap);
+    let mut res = FFmpegRsSubripParseExResult::default();
+    let mut emit = |e| {
+        if let Some(slot) = events.get_mut(res.n_events) {
+            *slot = e;
+        }
+        res.n_events += 1;
+    };
+    match mode {
+        m if m == FFmpegRsSubripMode::Strict as u32 => parse_strict(data, emit),
+        m if m == FFmpegRsSubripMode::Lenient as u32 => {
+            let mut n_diagnostics = 0usize;
+            parse_lenient(data, &mut emit, |kind, l| {
+                if let Some(slot) = diagnostics.get_mut(n_diagnostics) {
+                    *slot = FFmpegRsSubripDiagnostic { kind: kind as u32, line_no: l.line_no, offset: l.offset, len: l.text.len() };
+                }
+                n_diagnostics += 1;
+            });
+            res.n_diagnostics = n_diagnostics;
+        }
+        _ => return EINVAL,
+    }
+    *out = res;
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use FFmpegRsSubripRepairKind::*;
+
+    #[test]
+    fn recovers_damaged_file() {
+        let txt = b"junk header\n\
+1\n00:00:01,000 --> 00:00:02,000\nFirst\n\nstill first\n\n\
+0:00:03.5 -> 00:00:04,000\nNo index\n1984\n\n\
+3\n-00:00:01,000 --> 00:00:05,000\nNegative\n4\n00:00:07,000-->00:00:06,000\nReversed\n";
+        let mut out = FFmpegRsSubripParseExResult::default();
+        let mut evs = [FFmpegRsSubripEvent::new(0, 0, (0, 0), 0, None); 8];
+        let mut diags = [FFmpegRsSubripDiagnostic { kind: 0, line_no: 0, offset: 0, len: 0 }; 16];
+        let r = ffmpeg_rs_subrip_parse_ex(
+            txt.as_ptr(),
+            txt.len(),
+            FFmpegRsSubripMode::Lenient as u32,
+            &mut out,
+            evs.as_mut_ptr(),
+            evs.len(),
+            diags.as_mut_ptr(),
+            diags.len(),
+        );
+        assert_eq!(r, 0);
+        assert_eq!(out.n_events, 4);
+        let payload = |e: &FFmpegRsSubripEvent| &txt[e.payload_offset..e.payload_offset + e.payload_len];
+        assert_eq!(payload(&evs[0]), b"First\n\nstill first");
+        assert_eq!((evs[1].index, evs[1].start_ms, evs[1].duration_ms), (-1, 3005, 995));
+        assert_eq!(payload(&evs[1]), b"No index\n1984");
+        assert_eq!((evs[2].index, evs[2].start_ms, evs[2].duration_ms), (3, 0, 5000));
+        assert_eq!(payload(&evs[2]), b"Negative");
+        assert_eq!((evs[3].index, evs[3].start_ms, evs[3].duration_ms), (4, 7000, 0));
+        let got: [(u32, usize); 10] = core::array::from_fn(|i| (diags[i].kind, diags[i].line_no));
+        assert_eq!(out.n_diagnostics, 10);
+        assert_eq!(
+            got,
+            [
+                (SkippedLine as u32, 1),
+                (BlankLineInCue as u32, 5),
+                (MissingIndex as u32, 8),
+                (DotSeparator as u32, 8),
+                (ShortField as u32, 8),
+                (MalformedArrow as u32, 8),
+                (NegativeTimestamp as u32, 13),
+                (MissingBlankLine as u32, 15),
+                (MalformedArrow as u32, 16),
+                (EndBeforeStart as u32, 16),
+            ]
+        );
+        assert_eq!(&txt[diags[0].offset..diags[0].offset + diags[0].len], b"junk header");
+
+        // Strict mode is ffmpeg_rs_subrip_parse().
+        let r = ffmpeg_rs_subrip_parse_ex(txt.as_ptr(), txt.len(), 0, &mut out, evs.as_mut_ptr(), evs.len(), core::ptr::null_mut(), 0);
+        assert_eq!((r, out.n_diagnostics), (0, 0));
+        assert_eq!(ffmpeg_rs_subrip_parse_ex(txt.as_ptr(), txt.len(), 2, &mut out, core::ptr::null_mut(), 0, core::ptr::null_mut(), 0), EINVAL);
+    }
+}
@@ -2,6 +2,15 @@
 
 use core::ffi::c_int;
 
+mod encode;
+mod lenient;
+mod writer;
+
+pub use encode::*;
+pub use lenient::*;
+
+use writer::{in_bytes, out_array, out_ref};
+
 #[cfg(not(test))]
 #[panic_handler]
 fn panic_handler(_: &core::panic::PanicInfo) -> ! {
@@ -15,6 +24,15 @@ pub struct FFmpegRsSubripEvent {
     pub duration_ms: i64,
     pub payload_offset: usize,
     pub payload_len: usize,
+    /// Numeric cue index from the line before the timing line, or -1.
+    pub index: i64,
+    /// Display rectangle from a trailing `X1:.. X2:.. Y1:.. Y2:..`; the
+    /// coordinates are only set when `has_rect` is non-zero.
+    pub has_rect: c_int,
+    pub x1: i32,
+    pub x2: i32,
+    pub y1: i32,
+    pub y2: i32,
 }
 
 #[repr(C)]
@@ -29,15 +47,11 @@ fn chomp_cr(mut s: &[u8]) -> &[u8] {
     s
 }
 
-fn is_digit(b: u8) -> bool {
-    b'0' <= b && b <= b'9'
-}
-
 fn parse_u64(s: &[u8]) -> Option<u64> {
     let mut v: u64 = 0;
     let mut any = false;
     for &b in s {
-        if !is_digit(b) {
+        if !b.is_ascii_digit() {
             break;
         }
         any = true;
@@ -71,27 +85,84 @@ fn parse_ts_ms(line: &[u8]) -> Option<i64> {
     Some(total as i64)
 }
 
-fn parse_timing_line(line: &[u8]) -> Option<(i64, i64)> {
-    // "<ts> --> <ts>" (ignore trailing positions)
+fn is_ws(b: u8) -> bool {
+    b == b' ' || b == b'\t'
+}
+
+fn skip_ws(s: &[u8]) -> &[u8] {
+    &s[s.iter().position(|&b| !is_ws(b)).unwrap_or(s.len())..]
+}
+
+fn parse_i32(s: &[u8]) -> Option<(i32, &[u8])> {
+    let (neg, digits) = match s.split_first() {
+        Some((b'-', rest)) => (true, rest),
+        Some((b'+', rest)) => (false, rest),
+        _ => (false, s),
+    };
+    let n = digits.iter().take_while(|b| b.is_ascii_digit()).count();
+    let v = i64::try_from(parse_u64(&digits[..n])?).ok()?;
+    let v = i32::try_from(if neg { -v } else { v }).ok()?;
+    Some((v, &digits[n..]))
+}
+
+// "X1:100 X2:600 Y1:20 Y2:50" as [x1, x2, y1, y2]; all four are required,
+// in this order.
+fn parse_rect(mut s: &[u8]) -> Option<[i32; 4]> {
+    let mut rect = [0i32; 4];
+    for (v, key) in rect.iter_mut().zip([b"X1:", b"X2:", b"Y1:", b"Y2:"]) {
+        let (n, rest) = parse_i32(skip_ws(s).strip_prefix(key)?)?;
+        *v = n;
+        s = rest;
+    }
+    Some(rect)
+}
+
+fn parse_timing_line(line: &[u8]) -> Option<(i64, i64, Option<[i32; 4]>)> {
+    // "<ts> --> <ts> [X1:.. X2:.. Y1:.. Y2:..]"
     let arrow = line.windows(5).position(|w| w == b" --> ")?;
     let start = parse_ts_ms(&line[..arrow])?;
     let end = parse_ts_ms(&line[arrow + 5..])?;
-    Some((start, end.saturating_sub(start)))
+    let rect = parse_rect(&line[arrow + 5 + 12..]);
+    Some((start, end.saturating_sub(start), rect))
 }
 
-#[no_mangle]
-pub extern "C" fn ffmpeg_rs_subrip_parse(
-    text: *const u8,
-    text_len: usize,
-    out: *mut FFmpegRsSubripParseResult,
-    events: *mut FFmpegRsSubripEvent,
-    events_cap: usize,
-) -> c_int {
-    if text.is_null() || out.is_null() {
+// A line holding only a decimal number, surrounding blanks aside.
+fn parse_index(line: &[u8]) -> i64 {
+    let s = skip_ws(line);
+    let n = s.iter().take_while(|b| b.is_ascii_digit()).count();
+    if n == 0 || !skip_ws(&s[n..]).is_empty() {
         return -1;
     }
-    let data = unsafe { core::slice::from_raw_parts(text, text_len) };
-    let mut n_events = 0usize;
+    parse_u64(&s[..n]).and_then(|v| i64::try_from(v).ok()).unwrap_or(-1)
+}
+
+impl FFmpegRsSubripEvent {
+    fn new(start_ms: i64, duration_ms: i64, payload: (usize, usize), index: i64, rect: Option<[i32; 4]>) -> Self {
+        let [x1, x2, y1, y2] = rect.unwrap_or([0; 4]);
+        FFmpegRsSubripEvent {
+            start_ms,
+            duration_ms,
+            payload_offset: payload.0,
+            payload_len: payload.1,
+            index,
+            has_rect: rect.is_some() as c_int,
+            x1,
+            x2,
+            y1,
+            y2,
+        }
+    }
+}
+
+// Length of `s` without trailing newlines and CRs.
+fn trimmed_len(s: &[u8]) -> usize {
+    s.iter().rposition(|&b| b != b'\n' && b != b'\r').map_or(0, |p| p + 1)
+}
+
+// Strict parser: index line, timing line, payload up to the first blank
+// line. Blocks whose second line is not a timing line are skipped.
+fn parse_strict(data: &[u8], mut emit: impl FnMut(FFmpegRsSubripEvent)) {
+    let text_len = data.len();
 
     // Split into lines and parse in a simple state machine.
     let mut i = 0usize;
@@ -120,7 +191,7 @@ pub extern "C" fn ffmpeg_rs_subrip_parse(
         let timing = parse_timing_line(l2);
         i = if l2_end < text_len { l2_end + 1 } else { text_len };
 
-        let (start_ms, duration_ms) = match timing {
+        let (start_ms, duration_ms, rect) = match timing {
             Some(v) => v,
             None => {
                 // If l1 was not an index, we might have misaligned.
@@ -138,34 +209,32 @@ pub extern "C" fn ffmpeg_rs_subrip_parse(
                 break;
             }
         }
-        let payload_len = i.saturating_sub(payload_offset);
-        // Trim trailing newlines/CRs from payload.
-        let mut pl = payload_len;
-        while pl > 0 {
-            let b = data[payload_offset + pl - 1];
-            if b == b'\n' || b == b'\r' {
-                pl -= 1;
-            } else {
-                break;
-            }
-        }
+        let payload_len = trimmed_len(&data[payload_offset..i]);
+        emit(FFmpegRsSubripEvent::new(start_ms, duration_ms, (payload_offset, payload_len), parse_index(l1), rect));
+    }
+}
 
-        if n_events < events_cap {
-            unsafe {
-                *events.add(n_events) = FFmpegRsSubripEvent {
-                    start_ms,
-                    duration_ms,
-                    payload_offset,
-                    payload_len: pl,
-                };
-            }
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_subrip_parse(
+    text: *const u8,
+    text_len: usize,
+    out: *mut FFmpegRsSubripParseResult,
+    events: *mut FFmpegRsSubripEvent,
+    events_cap: usize,
+) -> c_int {
+    let (data, out) = match (in_bytes(text, text_len), out_ref(out)) {
+        (Some(d), Some(o)) => (d, o),
+        _ => return -1,
+    };
+    let events = out_array(events, events_cap);
+    let mut n_events = 0usize;
+    parse_strict(data, |e| {
+        if let Some(slot) = events.get_mut(n_events) {
+            *slot = e;
         }
         n_events += 1;
-    }
-
-    unsafe {
-        (*out).n_events = n_events;
-    }
+    });
+    out.n_events = n_events;
     0
 }
 
@@ -173,16 +242,26 @@ pub extern "C" fn ffmpeg_rs_subrip_parse(
 mod tests {
     use super::*;
 
-    #[test]
-    fn parses_simple_event() {
-        let txt = b"1\n00:00:01,000 --> 00:00:02,500\nHello\n\n";
-        let mut out = FFmpegRsSubripParseResult { n_events: 0 };
-        let mut evs = [FFmpegRsSubripEvent {
+    fn empty_event() -> FFmpegRsSubripEvent {
+        FFmpegRsSubripEvent {
             start_ms: 0,
             duration_ms: 0,
             payload_offset: 0,
             payload_len: 0,
-        }; 4];
+            index: 0,
+            has_rect: 0,
+            x1: 0,
+            x2: 0,
+            y1: 0,
+            y2: 0,
+        }
+    }
+
+    #[test]
+    fn parses_simple_event() {
+        let txt = b"1\n00:00:01,000 --> 00:00:02,500\nHello\n\n";
+        let mut out = FFmpegRsSubripParseResult { n_events: 0 };
+        let mut evs = [empty_event(); 4];
         let r = ffmpeg_rs_subrip_parse(txt.as_ptr(), txt.len(), &mut out, evs.as_mut_ptr(), evs.len());
         assert_eq!(r, 0);
         assert_eq!(out.n_events, 1);
@@ -190,5 +269,18 @@ mod tests {
         assert_eq!(evs[0].duration_ms, 1500);
         assert_eq!(&txt[evs[0].payload_offset..evs[0].payload_offset + evs[0].payload_len], b"Hello");
     }
-}
 
+    #[test]
+    fn parses_index_and_rect() {
+        let txt = b"7\r\n00:00:01,000 --> 00:00:02,000  X1:100 X2:600 Y1:-20 Y2:50\r\nBoxed\r\n\r\n\
+x\n00:00:03,000 --> 00:00:04,000 X1:1 X2:2\nPartial\n";
+        let mut out = FFmpegRsSubripParseResult { n_events: 0 };
+        let mut evs = [empty_event(); 4];
+        assert_eq!(ffmpeg_rs_subrip_parse(txt.as_ptr(), txt.len(), &mut out, evs.as_mut_ptr(), evs.len()), 0);
+        assert_eq!(out.n_events, 2);
+        let e = evs[0];
+        assert_eq!((e.index, e.has_rect, e.x1, e.x2, e.y1, e.y2), (7, 1, 100, 600, -20, 50));
+        assert_eq!(&txt[e.payload_offset..e.payload_offset + e.payload_len], b"Boxed");
+        assert_eq!((evs[1].index, evs[1].has_rect, evs[1].start_ms), (-1, 0, 3000));
+    }
+}
@@ -29,7 +29,405 @@ int ffmpeg_rs_webvtt_parse(const uint8_t *text, size_t text_len,
                            FFmpegRsWebvttParseResult *out,
                            FFmpegRsWebvttCue *cues, size_t cues_cap);
 
+/*
+ * HLS subtitle segmenting. The writers below fill `dst` (NUL-terminated)
+ * and report the size needed including the NUL through `out_required`.
+ * Pass dst == NULL to query the size. They return 0 on success, -EINVAL on
+ * invalid arguments and -ENOSPC if `dst` was too small.
+ */
+typedef struct FFmpegRsWebvttSegmentParams {
+    /* Segment interval [start_ms, end_ms) on the cue timeline. */
+    int64_t start_ms;
+    int64_t end_ms;
+    /* X-TIMESTAMP-MAP=MPEGTS:<mpegts>,LOCAL:<local_ms>; mpegts < 0 omits it. */
+    int64_t mpegts;
+    int64_t local_ms;
+    /* Clamp boundary-spanning cues to the segment instead of repeating them. */
+    int clip;
+} FFmpegRsWebvttSegmentParams;
+
+typedef struct FFmpegRsWebvttHlsSegment {
+    int64_t duration_ms;
+    const char *uri;
+    size_t uri_len;
+} FFmpegRsWebvttHlsSegment;
+
+typedef struct FFmpegRsWebvttHlsPlaylist {
+    int version;
+    int64_t target_duration; /* 0 derives it from the longest segment */
+    int64_t media_sequence;
+    uint32_t playlist_type;  /* 0 none, 1 EVENT, 2 VOD */
+    int endlist;
+} FFmpegRsWebvttHlsPlaylist;
+
+/* Writes one segment document with every cue overlapping the interval;
+ * `cues` come from ffmpeg_rs_webvtt_parse() over `text`. */
+int ffmpeg_rs_webvtt_write_segment(const uint8_t *text, size_t text_len,
+                                   const FFmpegRsWebvttCue *cues, size_t n_cues,
+                                   const FFmpegRsWebvttSegmentParams *params,
+                                   char *dst, size_t dst_len,
+                                   size_t *out_required);
+
+/* Writes the subtitle media playlist listing `segments`. */
+int ffmpeg_rs_webvtt_write_hls_playlist(const FFmpegRsWebvttHlsPlaylist *playlist,
+                                        const FFmpegRsWebvttHlsSegment *segments,
+                                        size_t n_segments,
+                                        char *dst, size_t dst_len,
+                                        size_t *out_required);
+
+/*
+ * Cue settings. FFMPEG_RS_WEBVTT_AUTO marks an absent line/position.
+ */
+#define FFMPEG_RS_WEBVTT_AUTO (-1.0)
+
+typedef struct FFmpegRsWebvttCueSettings {
+    uint32_t vertical;       /* 0 horizontal, 1 rl, 2 lr */
+    uint32_t line_kind;      /* 0 auto, 1 line number, 2 percentage */
+    double line;
+    uint32_t line_align;     /* 0 start, 1 center, 2 end */
+    double position;         /* percentage or FFMPEG_RS_WEBVTT_AUTO */
+    uint32_t position_align; /* 0 auto, 1 line-left, 2 center, 3 line-right */
+    double size;             /* percentage; 100 when absent */
+    uint32_t align;          /* 0 start, 1 center, 2 end, 3 left, 4 right */
+    /* Region identifier span, relative to the settings string. */
+    size_t region_offset;
+    size_t region_len;
+    /* Number of warnings, including any that did not fit the array. */
+    size_t n_warnings;
+} FFmpegRsWebvttCueSettings;
+
+typedef enum FFmpegRsWebvttSettingWarningKind {
+    FFMPEG_RS_WEBVTT_SETTING_MALFORMED = 0,      /* not name:value */
+    FFMPEG_RS_WEBVTT_SETTING_UNKNOWN_NAME = 1,
+    FFMPEG_RS_WEBVTT_SETTING_INVALID_VALUE = 2,
+    FFMPEG_RS_WEBVTT_SETTING_REGION_IGNORED = 3, /* vertical/line/size also set */
+} FFmpegRsWebvttSettingWarningKind;
+
+typedef struct FFmpegRsWebvttSettingWarning {
+    uint32_t kind; /* FFmpegRsWebvttSettingWarningKind */
+    /* The offending setting, relative to the settings string. */
+    size_t offset;
+    size_t len;
+} FFmpegRsWebvttSettingWarning;
+
+/* Decodes a cue settings string, e.g. the settings span of a cue. Invalid
+ * settings are ignored and reported as warnings. Returns 0, or -EINVAL on
+ * invalid arguments. */
+int ffmpeg_rs_webvtt_parse_cue_settings(const uint8_t *settings, size_t settings_len,
+                                        FFmpegRsWebvttCueSettings *out,
+                                        FFmpegRsWebvttSettingWarning *warnings,
+                                        size_t warnings_cap);
+
+/*
+ * Header blocks. Offsets are into the document text.
+ */
+typedef struct FFmpegRsWebvttRegion {
+    size_t id_offset;
+    size_t id_len;
+    double width;            /* percentage; 100 by default */
+    uint32_t lines;          /* 3 by default */
+    uint32_t scroll;         /* 0 none, 1 up */
+    /* Anchor points as percentages; (0, 100) by default. */
+    double region_anchor_x;
+    double region_anchor_y;
+    double viewport_anchor_x;
+    double viewport_anchor_y;
+} FFmpegRsWebvttRegion;
+
+typedef struct FFmpegRsWebvttStyleRule {
+    /* Argument of ::cue(...), trimmed; 0 length for a bare ::cue. */
+    size_t selector_offset;
+    size_t selector_len;
+    /* Declarations [first_decl, first_decl + n_decls) of the declaration
+     * stream; rules from one selector list share them. */
+    size_t first_decl;
+    size_t n_decls;
+} FFmpegRsWebvttStyleRule;
+
+typedef struct FFmpegRsWebvttStyleDecl {
+    size_t property_offset;
+    size_t property_len;
+    /* Value without the ';', including any !important. */
+    size_t value_offset;
+    size_t value_len;
+} FFmpegRsWebvttStyleDecl;
+
+typedef struct FFmpegRsWebvttHeaderResult {
+    /* Totals; entries past the capacities are counted but not stored. */
+    size_t n_regions;
+    size_t n_rules;
+    size_t n_decls;
+} FFmpegRsWebvttHeaderResult;
+
+/* Parses the REGION and STYLE blocks before the first cue. Only ::cue rules
+ * are reported. Returns 0, -EINVAL on invalid arguments or -2 without a
+ * WEBVTT signature. */
+int ffmpeg_rs_webvtt_parse_header(const uint8_t *text, size_t text_len,
+                                  FFmpegRsWebvttHeaderResult *out,
+                                  FFmpegRsWebvttRegion *regions, size_t regions_cap,
+                                  FFmpegRsWebvttStyleRule *rules, size_t rules_cap,
+                                  FFmpegRsWebvttStyleDecl *decls, size_t decls_cap);
+
+/*
+ * Cue text markup. The node stream is balanced: every START node has a
+ * matching END node, synthesised with `implied` set where the source
+ * omitted it.
+ */
+typedef enum FFmpegRsWebvttNodeKind {
+    FFMPEG_RS_WEBVTT_NODE_TEXT = 0,      /* literal text span */
+    FFMPEG_RS_WEBVTT_NODE_START = 1,
+    FFMPEG_RS_WEBVTT_NODE_END = 2,
+    FFMPEG_RS_WEBVTT_NODE_CHAR = 3,      /* decoded character reference */
+    FFMPEG_RS_WEBVTT_NODE_TIMESTAMP = 4, /* <hh:mm:ss.ttt> */
+} FFmpegRsWebvttNodeKind;
+
+typedef enum FFmpegRsWebvttTag {
+    FFMPEG_RS_WEBVTT_TAG_NONE = 0,
+    FFMPEG_RS_WEBVTT_TAG_C = 1,
+    FFMPEG_RS_WEBVTT_TAG_I = 2,
+    FFMPEG_RS_WEBVTT_TAG_B = 3,
+    FFMPEG_RS_WEBVTT_TAG_U = 4,
+    FFMPEG_RS_WEBVTT_TAG_RUBY = 5,
+    FFMPEG_RS_WEBVTT_TAG_RT = 6,
+    FFMPEG_RS_WEBVTT_TAG_V = 7,
+    FFMPEG_RS_WEBVTT_TAG_LANG = 8,
+} FFmpegRsWebvttTag;
+
+typedef struct FFmpegRsWebvttNode {
+    uint32_t kind; /* FFmpegRsWebvttNodeKind */
+    uint32_t tag;  /* FFmpegRsWebvttTag */
+    /* Source span of the token; 0 length for implied end tags. */
+    size_t offset;
+    size_t len;
+    /* Start tags: class list without the leading '.', e.g. "a.b". */
+    size_t classes_offset;
+    size_t classes_len;
+    /* Start tags: speaker of v, language of lang, trimmed. */
+    size_t annotation_offset;
+    size_t annotation_len;
+    uint32_t codepoint;
+    int implied;
+    int64_t timestamp_ms;
+} FFmpegRsWebvttNode;
+
+typedef struct FFmpegRsWebvttMarkupResult {
+    /* Total nodes, including any that did not fit the array. */
+    size_t n_nodes;
+    /* Unknown, misplaced, unmatched or too deeply nested tags dropped. */
+    size_t n_ignored_tags;
+} FFmpegRsWebvttMarkupResult;
+
+/* Tokenizes a cue payload. Offsets are into `payload`. Returns 0, or
+ * -EINVAL on invalid arguments. */
+int ffmpeg_rs_webvtt_tokenize_cue_text(const uint8_t *payload, size_t payload_len,
+                                       FFmpegRsWebvttMarkupResult *out,
+                                       FFmpegRsWebvttNode *nodes, size_t nodes_cap);
+
+/*
+ * Incremental parsing of a document arriving in chunks. Cues are returned
+ * once the blank line ending them arrives, or at flush. Their offsets point
+ * into the carry buffer and stay valid until the next call.
+ */
+
+/* Caller-allocated state; treat the fields as private. */
+typedef struct FFmpegRsWebvttStream {
+    uint8_t *buf;
+    size_t cap;
+    size_t len;
+    size_t consumed;
+    int header_done;
+} FFmpegRsWebvttStream;
+
+/* `buf` carries unfinished blocks between calls, so it bounds the size of
+ * one block. Returns 0, or -EINVAL on invalid arguments. */
+int ffmpeg_rs_webvtt_stream_init(FFmpegRsWebvttStream *stream, uint8_t *buf, size_t buf_cap);
+
+/* Appends `data` and returns the completed cues. Only part of `data` may be
+ * taken; *out_consumed is the number of bytes taken and the rest must be
+ * passed again. If *out_n_cues reaches cues_cap, call again with no data to
+ * drain the rest. Returns 0, -EINVAL on invalid arguments, -2 without a
+ * WEBVTT signature, or -ENOSPC if one unfinished block does not fit the
+ * carry buffer. */
+int ffmpeg_rs_webvtt_stream_push(FFmpegRsWebvttStream *stream,
+                                 const uint8_t *data, size_t len,
+                                 FFmpegRsWebvttCue *cues, size_t cues_cap,
+                                 size_t *out_n_cues, size_t *out_consumed);
+
+/* Ends the input; the last block needs no blank line. Call until
+ * *out_n_cues is 0. Same return values as ffmpeg_rs_webvtt_stream_push(). */
+int ffmpeg_rs_webvtt_stream_flush(FFmpegRsWebvttStream *stream,
+                                  FFmpegRsWebvttCue *cues, size_t cues_cap,
+                                  size_t *out_n_cues);
+
+/*
+ * Metadata/chapter mode: cues and NOTE blocks in document order.
+ */
+#define FFMPEG_RS_WEBVTT_RECORD_JSON 1 /* payload looks like a JSON object or array */
+
+typedef enum FFmpegRsWebvttRecordKind {
+    FFMPEG_RS_WEBVTT_RECORD_CUE = 0,
+    FFMPEG_RS_WEBVTT_RECORD_NOTE = 1,
+} FFmpegRsWebvttRecordKind;
+
+typedef struct FFmpegRsWebvttRecord {
+    uint32_t kind;  /* FFmpegRsWebvttRecordKind */
+    uint32_t flags; /* FFMPEG_RS_WEBVTT_RECORD_* */
+    /* Timing and spans of a cue record; zeroed for notes. */
+    FFmpegRsWebvttCue cue;
+    /* Cues: the trimmed payload. Notes: the comment text after NOTE. */
+    size_t text_offset;
+    size_t text_len;
+    /* Chapter title of a cue: the trimmed payload, or for a JSON object the
+     * top-level "title" string without its quotes (escapes left as is).
+     * 0 length for notes and JSON without a title. */
+    size_t title_offset;
+    size_t title_len;
+} FFmpegRsWebvttRecord;
+
+typedef struct FFmpegRsWebvttRecordsResult {
+    /* Totals; records past the capacity are counted but not stored. */
+    size_t n_records;
+    size_t n_cues;
+    size_t n_notes;
+} FFmpegRsWebvttRecordsResult;
+
+/* Returns 0, -EINVAL on invalid arguments or -2 without a WEBVTT signature. */
+int ffmpeg_rs_webvtt_parse_records(const uint8_t *text, size_t text_len,
+                                   FFmpegRsWebvttRecordsResult *out,
+                                   FFmpegRsWebvttRecord *records, size_t records_cap);
+
+/*
+ * Conformance checking against the W3C WebVTT file syntax.
+ */
+typedef enum FFmpegRsWebvttViolationKind {
+    FFMPEG_RS_WEBVTT_VIOLATION_BAD_SIGNATURE = 0,
+    FFMPEG_RS_WEBVTT_VIOLATION_MISSING_HEADER_BLANK = 1, /* timing line in the header block */
+    FFMPEG_RS_WEBVTT_VIOLATION_NON_MONOTONIC_START = 2,
+    FFMPEG_RS_WEBVTT_VIOLATION_END_BEFORE_START = 3,     /* end not after start */
+    FFMPEG_RS_WEBVTT_VIOLATION_ARROW_IN_PAYLOAD = 4,
+    FFMPEG_RS_WEBVTT_VIOLATION_INVALID_TIMESTAMP = 5,    /* malformed timing line or value */
+    FFMPEG_RS_WEBVTT_VIOLATION_DUPLICATE_IDENTIFIER = 6,
+    FFMPEG_RS_WEBVTT_VIOLATION_MALFORMED_BLOCK = 7,      /* not a cue, NOTE, STYLE or REGION */
+} FFmpegRsWebvttViolationKind;
+
+typedef struct FFmpegRsWebvttViolation {
+    uint32_t kind;  /* FFmpegRsWebvttViolationKind */
+    size_t line_no; /* 1-based */
+    /* The offending line, without its terminator. */
+    size_t offset;
+    size_t len;
+} FFmpegRsWebvttViolation;
+
+typedef struct FFmpegRsWebvttCheckResult {
+    /* Total violations, including any that did not fit the array. */
+    size_t n_violations;
+    size_t n_cues;
+    /* Identifiers not checked for duplicates because id_table was full. */
+    size_t n_unchecked_ids;
+} FFmpegRsWebvttCheckResult;
+
+/* Reports violations in document order. `id_table` is scratch space for
+ * finding duplicate identifiers and is filled to three quarters at most;
+ * about two slots per cue check every identifier. It may be NULL with a 0
+ * capacity. Returns 0, or -EINVAL on invalid arguments. */
+int ffmpeg_rs_webvtt_check(const uint8_t *text, size_t text_len,
+                           FFmpegRsWebvttCheckResult *out,
+                           FFmpegRsWebvttViolation *violations, size_t violations_cap,
+                           size_t *id_table, size_t id_table_cap);
+
+/*
+ * Document writer, formatted like webvttenc. Same buffer convention as the
+ * segment writers above.
+ */
+typedef struct FFmpegRsWebvttWriteParams {
+    /* Time base of the cue timestamps. */
+    int tb_num;
+    int tb_den;
+    int always_hours; /* otherwise hours only from one hour on */
+    int escape_text;  /* escape &, < and > in payloads; --> is always escaped */
+    /* Text after WEBVTT on the signature line, e.g. " - Title"; may be NULL. */
+    const char *header;
+    size_t header_len;
+    /* X-TIMESTAMP-MAP=MPEGTS:<mpegts>,LOCAL:<local_ms>; mpegts < 0 omits it. */
+    int64_t mpegts;
+    int64_t local_ms;
+} FFmpegRsWebvttWriteParams;
+
+typedef struct FFmpegRsWebvttWriteCue {
+    int64_t start; /* in tb_num/tb_den units */
+    int64_t end;
+    /* Optional strings; NULL or 0 length when absent. */
+    const char *identifier;
+    size_t identifier_len;
+    const char *settings;
+    size_t settings_len;
+    const char *payload;
+    size_t payload_len;
+} FFmpegRsWebvttWriteCue;
+
+/* Writes the cues in order. Blank payload lines are dropped. Returns 0,
+ * -EINVAL on invalid parameters, negative or reversed times, or identifiers
+ * and settings containing a newline or -->, and -ENOSPC if dst is too
+ * small. */
+int ffmpeg_rs_webvtt_write(const FFmpegRsWebvttWriteParams *params,
+                           const FFmpegRsWebvttWriteCue *cues, size_t n_cues,
+                           char *dst, size_t dst_len, size_t *out_required);
+
+/*
+ * ISO/IEC 14496-30 (WebVTT in MP4). Samples are sequences of boxes: a vttc
+ * (with iden, sttg and payl children) per active cue, or a single vtte for
+ * a gap.
+ */
+typedef struct FFmpegRsWebvttMp4Sample {
+    int64_t start_ms;
+    int64_t duration_ms;
+    /* Sample payload in the data buffer. */
+    size_t data_offset;
+    size_t data_size;
+    size_t n_cues; /* 0 for an empty (vtte) sample */
+} FFmpegRsWebvttMp4Sample;
+
+typedef struct FFmpegRsWebvttMp4EncodeResult {
+    size_t n_samples_total;
+    size_t n_samples_written;
+    int truncated;
+    size_t data_required; /* size of all sample payloads */
+} FFmpegRsWebvttMp4EncodeResult;
+
+typedef struct FFmpegRsWebvttMp4DecodeResult {
+    size_t n_cues; /* including any that did not fit the caller array */
+    int empty;     /* the sample holds a vtte box */
+    /* Payload of the first vtta box; 0 length if none. */
+    size_t additional_offset;
+    size_t additional_len;
+} FFmpegRsWebvttMp4DecodeResult;
+
+/* Converts cues with spans into text into samples covering
+ * [start_ms, end_ms); end_ms < 0 ends at the last cue end. Returns 0,
+ * -EINVAL on invalid arguments or cue spans, or -ENOSPC if data is smaller
+ * than out->data_required (the sample records are still written). */
+int ffmpeg_rs_webvtt_mp4_encode(const uint8_t *text, size_t text_len,
+                                const FFmpegRsWebvttCue *cues, size_t n_cues,
+                                int64_t start_ms, int64_t end_ms,
+                                FFmpegRsWebvttMp4Sample *samples, size_t samples_cap,
+                                uint8_t *data, size_t data_len,
+                                FFmpegRsWebvttMp4EncodeResult *out);
+
+/* Extracts the cues of one sample; spans are offsets into data. Returns 0,
+ * -EINVAL on invalid arguments or -2 on a malformed sample. */
+int ffmpeg_rs_webvtt_mp4_decode_sample(const uint8_t *data, size_t data_len,
+                                       int64_t start_ms, int64_t end_ms,
+                                       FFmpegRsWebvttMp4DecodeResult *out,
+                                       FFmpegRsWebvttCue *cues, size_t cues_cap);
+
+/* Writes a wvtt sample entry with its vttC config and an optional vlab
+ * label (may be NULL). Binary output: *out_required is the exact size, no
+ * NUL. Returns 0, -EINVAL or -ENOSPC. */
+int ffmpeg_rs_webvtt_mp4_write_sample_entry(const char *config, size_t config_len,
+                                            const char *label, size_t label_len,
+                                            uint8_t *dst, size_t dst_len,
+                                            size_t *out_required);
+
 #ifdef __cplusplus
 }
 #endif
-
@@ -0,0 +1,395 @@
+use core::ffi::c_int;
+
+use crate::writer::{in_bytes, out_array, out_ref, EINVAL};
+use crate::records::is_note;
+use crate::{blocks, chomp_cr, first_line, is_ws, parse_u64};
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FFmpegRsWebvttViolationKind {
+    /// First line is not `WEBVTT`, optionally followed by a space or tab and
+    /// free text.
+    BadSignature = 0,
+    /// A timing line inside the header block.
+    MissingHeaderBlank = 1,
+    /// Cue start earlier than the previous cue's.
+    NonMonotonicStart = 2,
+    /// Cue end not after its start.
+    EndBeforeStart = 3,
+    ArrowInPayload = 4,
+    /// Malformed timing line, or a component out of range (minutes or
+    /// seconds >= 60).
+    InvalidTimestamp = 5,
+    DuplicateIdentifier = 6,
+    /// A block that is neither a cue nor a NOTE, STYLE or REGION block.
+    MalformedBlock = 7,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FFmpegRsWebvttViolation {
+    /// FFmpegRsWebvttViolationKind.
+    pub kind: u32,
+    /// 1-based line of the offending line.
+    pub line_no: usize,
+    /// The offending line, without its terminator.
+    pub offset: usize,
+    pub len: usize,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FFmpegRsWebvttCheckResult {
+    /// Total violations, including any that did not fit the caller array.
+    pub n_violations: usize,
+    pub n_cues: usize,
+    /// Identifiers not checked for duplicates because the identifier table
+    /// was full.
+    pub n_unchecked_ids: usize,
+}
+
+fn has_arrow(s: &[u8]) -> bool {
+    s.windows(3).any(|w| w == b"-->")
+}
+
+// Exact digit count.
+fn fixed(s: &[u8], width: usize) -> Option<u64> {
+    match parse_u64(s)? {
+        (v, n) if n == width && n == s.len() => Some(v),
+        _ => None,
+    }
+}
+
+// [hh+:]mm:ss.ttt with two-digit minutes and seconds below 60.
+fn parse_ts_strict(s: &[u8]) -> Option<i64> {
+    let dot = s.iter().position(|&b| b == b'.')?;
+    let ms = fixed(&s[dot + 1..], 3)?;
+    let mut parts = s[..dot].rsplit(|&b| b == b':');
+    let ss = fixed(parts.next()?, 2)?;
+    let mm = fixed(parts.next()?, 2)?;
+    let hh = match parts.next() {
+        Some(h) => match parse_u64(h)? {
+            (v, n) if n >= 2 && n == h.len() => v,
+            _ => return None,
+        },
+        None => 0,
+    };
+    if parts.next().is_some() || mm > 59 || ss > 59 {
+        return None;
+    }
+    let secs = hh.checked_mul(3600)?.checked_add(mm * 60 + ss)?;
+    i64::try_from(secs.checked_mul(1000)?.checked_add(ms)?).ok()
+}
+
+// `start --> end [settings]`, with whitespace around the arrow.
+fn parse_timing(line: &[u8]) -> Option<(i64, i64)> {
+    let arrow = line.windows(3).position(|w| w == b"-->")?;
+    let (left, right) = (&line[..arrow], &line[arrow + 3..]);
+    let start = left.strip_suffix(b" ").or_else(|| left.strip_suffix(b"\t"))?;
+    let start = &start[..start.iter().rposition(|&b| !is_ws(b))? + 1];
+    if !right.first().is_some_and(|&b| is_ws(b)) {
+        return None;
+    }
+    let right = &right[right.iter().position(|&b| !is_ws(b))?..];
+    let end = &right[..right.iter().position(|&b| is_ws(b)).unwrap_or(right.len())];
+    Some((parse_ts_strict(start)?, parse_ts_strict(end)?))
+}
+
+// Lines of a block with their offsets relative to the block.
+fn lines(block: &[u8]) -> impl Iterator<Item = (usize, &[u8])> {
+    block.split(|&b| b == b'\n').scan(0usize, |pos, l| {
+        let start = *pos;
+        *pos += l.len() + 1;
+        Some((start, chomp_cr(l)))
+    })
+}
+
+// Identifier line of a cue block, if it has one.
+fn identifier(block: &[u8]) -> Option<&[u8]> {
+    let (line1, _) = first_line(block);
+    if has_arrow(line1) {
+        None
+    } else {
+        Some(line1)
+    }
+}
+
+fn is_header_block(line1: &[u8]) -> bool {
+    is_note(line1) || line1 == b"STYLE" || line1 == b"REGION"
+}
+
+// FNV-1a.
+fn hash(s: &[u8]) -> u64 {
+    s.iter().fold(0xcbf2_9ce4_8422_2325, |h, &b| (h ^ u64::from(b)).wrapping_mul(0x100_0000_01b3))
+}
+
+// Open-addressing set of the cue identifiers seen so far. Slots hold the
+// offset of the identifier line plus one, so 0 marks a free slot.
+struct Ids<'a> {
+    data: &'a [u8],
+    slots: &'a mut [usize],
+    len: usize,
+}
+
+impl Ids<'_> {
+    // Adds the identifier `id` at `off`. Returns whether it was already
+    // present, or None if the table is too full to take it.
+    fn insert(&mut self, off: usize, id: &[u8]) -> Option<bool> {
+        let cap = self.slots.len();
+        if cap == 0 {
+            return None;
+        }
+        let mut i = (hash(id) % cap as u64) as usize;
+        while self.slots[i] != 0 {
+            if first_line(&self.data[self.slots[i] - 1..]).0 == id {
+                return Some(true);
+            }
+            i = (i + 1) % cap;
+        }
+        // A quarter of the slots stays free so that probes stay short and
+        // always end.
+        if self.len >= cap - cap.div_ceil(4) {
+            return None;
+        }
+        self.slots[i] = off + 1;
+        self.len += 1;
+        Some(false)
+    }
+}
+
+struct Lines<'a> {
+    data: &'a [u8],
+    pos: usize,
+    line: usize,
+}
+
+impl Lines<'_> {
+    // 1-based line number of `off`; cheap for increasing offsets.
+    fn at(&mut self, off: usize) -> usize {
+        if off < self.pos {
+            self.pos = 0;
+            self.line = 1;
+        }
+        self.line += self.data[self.pos..off].iter().filter(|&&b| b == b'\n').count();
+        self.pos = off;
+        self.line
+    }
+}
+
+// Validates `data` against the W3C WebVTT file syntax, reporting violations
+// in document order. Duplicate identifiers are found through `id_slots`.
+pub(crate) fn check(data: &[u8], id_slots: &mut [usize], mut emit: impl FnMut(FFmpegRsWebvttViolation)) -> FFmpegRsWebvttCheckResult {
+    use FFmpegRsWebvttViolationKind::*;
+    let mut res = FFmpegRsWebvttCheckResult::default();
+    let mut ln = Lines { data, pos: 0, line: 1 };
+    let mut report = |kind: FFmpegRsWebvttViolationKind, offset: usize, line: &[u8], res: &mut FFmpegRsWebvttCheckResult| {
+        res.n_violations += 1;
+        emit(FFmpegRsWebvttViolation { kind: kind as u32, line_no: ln.at(offset), offset, len: line.len() });
+    };
+    id_slots.fill(0);
+    let mut ids = Ids { data, slots: id_slots, len: 0 };
+
+    let bom = if data.starts_with(&[0xEF, 0xBB, 0xBF]) { 3 } else { 0 };
+    let (sig, _) = first_line(&data[bom..]);
+    let sig_ok = match sig.strip_prefix(b"WEBVTT") {
+        Some(rest) => rest.first().is_none_or(|&b| is_ws(b)) && !has_arrow(rest),
+        None => false,
+    };
+    if !sig_ok {
+        report(BadSignature, bom, sig, &mut res);
+    }
+
+    let mut prev_start: Option<i64> = None;
+    for (i, (pos, block)) in blocks(data).enumerate() {
+        // Offset in the block where the cue lines begin.
+        let mut cue_from = 0usize;
+        if i == 0 {
+            // A timing line here means the blank line after the header is
+            // missing; the rest of the block is taken as a cue.
+            match lines(block).skip(1).find(|(_, l)| has_arrow(l)) {
+                Some((off, l)) => {
+                    report(MissingHeaderBlank, pos + off, l, &mut res);
+                    cue_from = off;
+                }
+                None => continue,
+            }
+        } else if is_header_block(first_line(block).0) {
+            continue;
+        }
+
+        let mut it = lines(block).skip_while(|&(off, _)| off < cue_from).peekable();
+        let (first_off, first) = match it.peek() {
+            Some(&l) => l,
+            None => continue,
+        };
+        if let Some(id) = identifier(&block[first_off..]) {
+            if !has_arrow(block) {
+                report(MalformedBlock, pos + first_off, first, &mut res);
+                continue;
+            }
+            match ids.insert(pos + first_off, id) {
+                Some(true) => report(DuplicateIdentifier, pos + first_off, id, &mut res),
+                Some(false) => {}
+                None => res.n_unchecked_ids += 1,
+            }
+            it.next();
+        }
+        let (timing_off, timing) = match it.next() {
+            Some(l) => l,
+            None => continue,
+        };
+        if !has_arrow(timing) {
+            report(MalformedBlock, pos + timing_off, timing, &mut res);
+            continue;
+        }
+        res.n_cues += 1;
+        match parse_timing(timing) {
+            Some((start, end)) => {
+                if prev_start.is_some_and(|p| start < p) {
+                    report(NonMonotonicStart, pos + timing_off, timing, &mut res);
+                }
+                if end <= start {
+                    report(EndBeforeStart, pos + timing_off, timing, &mut res);
+                }
+                prev_start = Some(start);
+            }
+            None => report(InvalidTimestamp, pos + timing_off, timing, &mut res),
+        }
+        for (off, l) in it {
+            if has_arrow(l) {
+                report(ArrowInPayload, pos + off, l, &mut res);
+            }
+        }
+    }
+    res
+}
+
+// Checks a WebVTT document for conformance. Violations beyond
+// `violations_cap` are counted in `out->n_violations` but not stored.
+// `id_table` is scratch space for duplicate identifier detection; it is
+// filled to three quarters at most, and identifiers past that are counted
+// in `out->n_unchecked_ids` instead of being checked. It may be NULL with a
+// 0 capacity. Returns 0, or -EINVAL on invalid arguments.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "C" fn ffmpeg_rs_webvtt_check(
+    text: *const u8,
+    text_len: usize,
+    out: *mut FFmpegRsWebvttCheckResult,
+    violations: *mut FFmpegRsWebvttViolation,
+    violations_cap: usize,
+    id_table: *mut usize,
+    id_table_cap: usize,
+) -> c_int {
+    let (data, out) = match (in_bytes(text, text_len), out_ref(out)) {
+        (Some(d), Some(o)) => (d, o),
+        _ => return EINVAL,
+    };
+    let violations = out_array(violations, violations_cap);
+    let mut n = 0usize;
+    *out = check(data, out_array(id_table, id_table_cap), |v| {
+        if let Some(slot) = violations.get_mut(n) {
+            *slot = v;
+        }
+        n += 1;
+    });
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use FFmpegRsWebvttViolationKind::*;
+
+    fn run(doc: &[u8]) -> ([(u32, usize); 10], FFmpegRsWebvttCheckResult) {
+        let mut v = [(BadSignature as u32, 0usize); 10];
+        let mut n = 0;
+        let mut ids = [0usize; 16];
+        let res = check(doc, &mut ids, |x| {
+            v[n] = (x.kind, x.line_no);
+            n += 1;
+        });
+        (v, res)
+    }
+
+    #[test]
+    fn accepts_conformant_file() {
+        let doc = b"\xEF\xBB\xBFWEBVTT - title\r\nKind: captions\r\n\r\nNOTE a comment\r\n\r\n\
+a\r\n00:00.000 --> 00:01.000 align:end\r\nHi\r\n\r\nb\r\n100:00:01.000\t-->\t100:00:02.500\r\nThere\r\n";
+        let (v, res) = run(doc);
+        assert_eq!((res.n_violations, res.n_cues), (0, 2), "{:?}", &v[..res.n_violations.min(10)]);
+    }
+
+    #[test]
+    fn reports_violations_with_lines() {
+        let doc = b"WEBVTTX\n00:00.000 --> 00:01.000\nHeaderless\n\n\
+a\n00:05.000 --> 00:04.000\nx --> y\n\n\
+a\n00:03.000 --> 00:06.000\nz\n\n\
+b\n00:61.000 --> 00:07.000\n\n\
+c\n0:00:08.000 --> 00:09.000\n\n\
+stray text\n\n";
+        let (v, res) = run(doc);
+        assert_eq!(res.n_cues, 5);
+        assert_eq!(res.n_violations, 9);
+        assert_eq!(
+            v,
+            [
+                (BadSignature as u32, 1),
+                (MissingHeaderBlank as u32, 2),
+                (EndBeforeStart as u32, 6),
+                (ArrowInPayload as u32, 7),
+                (DuplicateIdentifier as u32, 9),
+                (NonMonotonicStart as u32, 10),
+                (InvalidTimestamp as u32, 14),
+                (InvalidTimestamp as u32, 17),
+                (MalformedBlock as u32, 19),
+                (BadSignature as u32, 0),
+            ]
+        );
+
+        let mut out = FFmpegRsWebvttCheckResult::default();
+        let mut one = [FFmpegRsWebvttViolation { kind: 0, line_no: 0, offset: 0, len: 0 }; 1];
+        let doc = b"WEBVTT\n\nnonsense\n";
+        let r = ffmpeg_rs_webvtt_check(doc.as_ptr(), doc.len(), &mut out, one.as_mut_ptr(), 1, core::ptr::null_mut(), 0);
+        assert_eq!(r, 0);
+        assert_eq!((out.n_violations, one[0].kind, one[0].line_no), (1, MalformedBlock as u32, 3));
+        assert_eq!(&doc[one[0].offset..one[0].offset + one[0].len], b"nonsense");
+    }
+
+    const N_CUES: usize = 20_000;
+    const CUE: &[u8] = b"00000\n00:00.000 --> 00:01.000\n\n";
+
+    const DOC_LEN: usize = 8 + (N_CUES + 1) * CUE.len();
+
+    // WEBVTT, then N_CUES cues with identifiers 00000, 00001, ... and a last
+    // cue repeating the first identifier.
+    fn many_cues(doc: &mut [u8; DOC_LEN]) {
+        doc[..8].copy_from_slice(b"WEBVTT\n\n");
+        for (i, cue) in doc[8..].chunks_exact_mut(CUE.len()).enumerate() {
+            cue.copy_from_slice(CUE);
+            let mut id = i % N_CUES;
+            for d in cue[..5].iter_mut().rev() {
+                *d = b'0' + (id % 10) as u8;
+                id /= 10;
+            }
+        }
+    }
+
+    #[test]
+    fn finds_duplicates_in_large_files() {
+        let mut ids = [0usize; 32_768];
+        let mut v = [FFmpegRsWebvttViolation { kind: 0, line_no: 0, offset: 0, len: 0 }; 2];
+        let mut out = FFmpegRsWebvttCheckResult::default();
+        let mut doc = [0u8; DOC_LEN];
+        many_cues(&mut doc);
+        let r = ffmpeg_rs_webvtt_check(doc.as_ptr(), doc.len(), &mut out, v.as_mut_ptr(), 2, ids.as_mut_ptr(), ids.len());
+        assert_eq!(r, 0);
+        assert_eq!((out.n_cues, out.n_violations, out.n_unchecked_ids), (N_CUES + 1, 1, 0));
+        assert_eq!((v[0].kind, v[0].line_no), (DuplicateIdentifier as u32, 3 + 3 * N_CUES));
+
+        // A full table still finds repeats of the identifiers it holds; the
+        // others are counted as unchecked.
+        let r = ffmpeg_rs_webvtt_check(doc.as_ptr(), doc.len(), &mut out, v.as_mut_ptr(), 2, ids.as_mut_ptr(), 100);
+        assert_eq!((r, out.n_violations, out.n_unchecked_ids), (0, 1, N_CUES - 75));
+    }
+}
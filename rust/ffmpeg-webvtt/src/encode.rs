@@ -0,0 +1,235 @@
+use core::ffi::{c_char, c_int};
+
+use crate::writer::{in_array, in_ref, in_str, out_buf, Writer, EINVAL};
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsWebvttWriteParams {
+    /// Time base of the cue timestamps.
+    pub tb_num: c_int,
+    pub tb_den: c_int,
+    /// Write the hours of every timestamp instead of only from one hour on.
+    pub always_hours: c_int,
+    /// Escape `&`, `<` and `>` in payloads, for plain text without markup.
+    /// `-->` is escaped either way.
+    pub escape_text: c_int,
+    /// Text after `WEBVTT` on the signature line, e.g. ` - Title`; may be
+    /// NULL.
+    pub header: *const c_char,
+    pub header_len: usize,
+    /// `X-TIMESTAMP-MAP=MPEGTS:<mpegts>,LOCAL:<local_ms>`; negative `mpegts`
+    /// omits the header.
+    pub mpegts: i64,
+    pub local_ms: i64,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsWebvttWriteCue {
+    /// In `tb_num/tb_den` units.
+    pub start: i64,
+    pub end: i64,
+    /// Optional strings; NULL or 0 length when absent.
+    pub identifier: *const c_char,
+    pub identifier_len: usize,
+    pub settings: *const c_char,
+    pub settings_len: usize,
+    pub payload: *const c_char,
+    pub payload_len: usize,
+}
+
+// Rescales to milliseconds, rounding to nearest with ties away from zero.
+fn to_ms(v: i64, num: c_int, den: c_int) -> Option<i64> {
+    let n = v as i128 * num as i128 * 1000;
+    let d = den as i128;
+    let q = (n.abs() + d / 2) / d;
+    i64::try_from(if n < 0 { -q } else { q }).ok()
+}
+
+fn has_arrow(s: &[u8]) -> bool {
+    s.windows(3).any(|w| w == b"-->")
+}
+
+// One payload line. A `>` preceded by `--` is escaped so the line cannot
+// read as a timing line.
+fn write_line(w: &mut Writer, line: &[u8], escape_text: bool) {
+    for (i, &b) in line.iter().enumerate() {
+        match b {
+            b'&' if escape_text => w.bytes(b"&amp;"),
+            b'<' if escape_text => w.bytes(b"&lt;"),
+            b'>' if escape_text || line[..i].ends_with(b"--") => w.bytes(b"&gt;"),
+            _ => w.byte(b),
+        }
+    }
+}
+
+fn write_document(w: &mut Writer, p: &FFmpegRsWebvttWriteParams, cues: &[FFmpegRsWebvttWriteCue]) -> Result<(), c_int> {
+    if p.tb_num <= 0 || p.tb_den <= 0 {
+        return Err(EINVAL);
+    }
+    let header = in_str(p.header, p.header_len).unwrap_or(b"");
+    if header.iter().any(|&b| b == b'\n' || b == b'\r') || has_arrow(header) || !header.first().is_none_or(|&b| b == b' ' || b == b'\t') {
+        return Err(EINVAL);
+    }
+    w.bytes(b"WEBVTT");
+    w.bytes(header);
+    w.byte(b'\n');
+    if p.mpegts >= 0 {
+        w.bytes(b"X-TIMESTAMP-MAP=MPEGTS:");
+        w.uint(p.mpegts as u64);
+        w.bytes(b",LOCAL:");
+        w.timestamp_ms(p.local_ms)?;
+        w.byte(b'\n');
+    }
+    for cue in cues {
+        let start = to_ms(cue.start, p.tb_num, p.tb_den).ok_or(EINVAL)?;
+        let end = to_ms(cue.end, p.tb_num, p.tb_den).ok_or(EINVAL)?;
+        if end < start {
+            return Err(EINVAL);
+        }
+        let id = in_str(cue.identifier, cue.identifier_len).unwrap_or(b"");
+        let settings = in_str(cue.settings, cue.settings_len).unwrap_or(b"");
+        for s in [id, settings] {
+            if s.iter().any(|&b| b == b'\n' || b == b'\r') || has_arrow(s) {
+                return Err(EINVAL);
+            }
+        }
+        w.byte(b'\n');
+        if !id.is_empty() {
+            w.bytes(id);
+            w.byte(b'\n');
+        }
+        if p.always_hours != 0 {
+            w.timestamp_ms(start)?;
+            w.bytes(b" --> ");
+            w.timestamp_ms(end)?;
+        } else {
+            w.timestamp_ms_short(start)?;
+            w.bytes(b" --> ");
+            w.timestamp_ms_short(end)?;
+        }
+        if !settings.is_empty() {
+            w.byte(b' ');
+            w.bytes(settings);
+        }
+        w.byte(b'\n');
+        // Blank lines would end the cue early, so they are dropped; CRLF
+        // becomes LF.
+        let payload = in_str(cue.payload, cue.payload_len).unwrap_or(b"");
+        for line in payload.split(|&b| b == b'\n') {
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            if !line.is_empty() {
+                write_line(w, line, p.escape_text != 0);
+                w.byte(b'\n');
+            }
+        }
+    }
+    Ok(())
+}
+
+// Writes a WebVTT document with `cues` in the given order, formatted like
+// webvttenc. Fills `dst` (NUL-terminated) and reports the size needed
+// including the NUL through `out_required`; dst == NULL queries the size.
+// Returns 0, -EINVAL on invalid parameters, negative or reversed cue times,
+// or identifiers/settings containing a newline or `-->`, and -ENOSPC if
+// `dst` was too small.
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_webvtt_write(
+    params: *const FFmpegRsWebvttWriteParams,
+    cues: *const FFmpegRsWebvttWriteCue,
+    n_cues: usize,
+    dst: *mut c_char,
+    dst_len: usize,
+    out_required: *mut usize,
+) -> c_int {
+    let (p, cues) = match (in_ref(params), in_array(cues, n_cues)) {
+        (Some(p), Some(c)) => (p, c),
+        _ => return EINVAL,
+    };
+    let mut w = Writer::new(out_buf(dst, dst_len));
+    if let Err(e) = write_document(&mut w, p, cues) {
+        return e;
+    }
+    w.finish(out_required)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::writer::ENOSPC;
+
+    fn cue(start: i64, end: i64, id: &[u8], settings: &[u8], payload: &[u8]) -> FFmpegRsWebvttWriteCue {
+        FFmpegRsWebvttWriteCue {
+            start,
+            end,
+            identifier: id.as_ptr() as *const c_char,
+            identifier_len: id.len(),
+            settings: settings.as_ptr() as *const c_char,
+            settings_len: settings.len(),
+            payload: payload.as_ptr() as *const c_char,
+            payload_len: payload.len(),
+        }
+    }
+
+    fn params() -> FFmpegRsWebvttWriteParams {
+        FFmpegRsWebvttWriteParams {
+            tb_num: 1,
+            tb_den: 90000,
+            always_hours: 0,
+            escape_text: 0,
+            header: core::ptr::null(),
+            header_len: 0,
+            mpegts: -1,
+            local_ms: 0,
+        }
+    }
+
+    fn write(p: &FFmpegRsWebvttWriteParams, cues: &[FFmpegRsWebvttWriteCue], buf: &mut [u8]) -> (c_int, usize) {
+        let mut req = 0usize;
+        let r = ffmpeg_rs_webvtt_write(p, cues.as_ptr(), cues.len(), buf.as_mut_ptr() as *mut c_char, buf.len(), &mut req);
+        (r, req)
+    }
+
+    #[test]
+    fn writes_documents() {
+        let cues = [
+            cue(45, 90_045, b"1", b"align:end", b"<i>Hi</i> -->\r\n\r\nthere\n"),
+            cue(3600 * 90_000, 3601 * 90_000 + 45, b"", b"", b"a -> b"),
+        ];
+        let mut buf = [0u8; 256];
+        let (r, req) = write(&params(), &cues, &mut buf);
+        let expected = b"WEBVTT\n\n1\n00:00.001 --> 00:01.001 align:end\n<i>Hi</i> --&gt;\nthere\n\n\
+01:00:00.000 --> 01:00:01.001\na -> b\n";
+        assert_eq!((r, &buf[..req - 1]), (0, &expected[..]));
+        let (r, req2) = write(&params(), &cues, &mut buf[..10]);
+        assert_eq!((r, req2), (ENOSPC, req));
+
+        let header = b" - Chapters";
+        let p = FFmpegRsWebvttWriteParams {
+            tb_num: 1,
+            tb_den: 1000,
+            always_hours: 1,
+            escape_text: 1,
+            header: header.as_ptr() as *const c_char,
+            header_len: header.len(),
+            mpegts: 900_000,
+            local_ms: 0,
+        };
+        let cues = [cue(0, 1500, b"", b"", b"Tom & Jerry <3 >")];
+        let (r, req) = write(&p, &cues, &mut buf);
+        let expected = b"WEBVTT - Chapters\nX-TIMESTAMP-MAP=MPEGTS:900000,LOCAL:00:00:00.000\n\n\
+00:00:00.000 --> 00:00:01.500\nTom &amp; Jerry &lt;3 &gt;\n";
+        assert_eq!((r, &buf[..req - 1]), (0, &expected[..]));
+    }
+
+    #[test]
+    fn rejects_invalid_cues() {
+        let mut buf = [0u8; 128];
+        for c in [cue(90_000, 45_000, b"", b"", b"x"), cue(-90, 5, b"", b"", b"x"), cue(0, 5, b"a-->b", b"", b"x"), cue(0, 5, b"", b"a\nb", b"x")] {
+            assert_eq!(write(&params(), &[c], &mut buf).0, EINVAL);
+        }
+        let mut p = params();
+        p.tb_den = 0;
+        assert_eq!(write(&p, &[], &mut buf).0, EINVAL);
+    }
+}
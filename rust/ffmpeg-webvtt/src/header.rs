@@ -0,0 +1,435 @@
+use core::ffi::c_int;
+
+use crate::settings::{parse_percentage, split_comma};
+use crate::text::{is_space, trim_span};
+use crate::writer::{in_bytes, out_array, out_ref, EINVAL};
+use crate::{blocks, first_line, has_signature, parse_u64};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FFmpegRsWebvttRegion {
+    pub id_offset: usize,
+    pub id_len: usize,
+    /// Percentage of the viewport width; 100 by default.
+    pub width: f64,
+    /// Height in lines; 3 by default.
+    pub lines: u32,
+    /// 0 = none, 1 = up.
+    pub scroll: u32,
+    /// Anchor points as percentages; (0, 100) by default.
+    pub region_anchor_x: f64,
+    pub region_anchor_y: f64,
+    pub viewport_anchor_x: f64,
+    pub viewport_anchor_y: f64,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FFmpegRsWebvttStyleRule {
+    /// Argument of `::cue(...)`, trimmed; 0 length for a bare `::cue`.
+    pub selector_offset: usize,
+    pub selector_len: usize,
+    /// Declarations `[first_decl, first_decl + n_decls)`, indexing the
+    /// declaration stream. Rules sharing a selector list share them.
+    pub first_decl: usize,
+    pub n_decls: usize,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FFmpegRsWebvttStyleDecl {
+    pub property_offset: usize,
+    pub property_len: usize,
+    /// Value without the terminating `;`, including any `!important`.
+    pub value_offset: usize,
+    pub value_len: usize,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FFmpegRsWebvttHeaderResult {
+    /// Totals; entries past the caller capacities are counted, not stored.
+    pub n_regions: usize,
+    pub n_rules: usize,
+    pub n_decls: usize,
+}
+
+fn anchor(v: &[u8]) -> Option<(f64, f64)> {
+    match split_comma(v) {
+        (x, Some(y)) => Some((parse_percentage(x)?, parse_percentage(y)?)),
+        _ => None,
+    }
+}
+
+// Settings of a REGION block; `base` is the offset of `s` in the document.
+// Invalid settings are ignored.
+fn parse_region(s: &[u8], base: usize) -> FFmpegRsWebvttRegion {
+    let mut r = FFmpegRsWebvttRegion {
+        id_offset: 0,
+        id_len: 0,
+        width: 100.0,
+        lines: 3,
+        scroll: 0,
+        region_anchor_x: 0.0,
+        region_anchor_y: 100.0,
+        viewport_anchor_x: 0.0,
+        viewport_anchor_y: 100.0,
+    };
+    for (start, token) in s.split(|&b| is_space(b)).scan(0usize, |pos, t| {
+        let start = *pos;
+        *pos += t.len() + 1;
+        Some((start, t))
+    }) {
+        let colon = match token.iter().position(|&b| b == b':') {
+            Some(c) if c > 0 && c + 1 < token.len() => c,
+            _ => continue,
+        };
+        let (name, value) = (&token[..colon], &token[colon + 1..]);
+        match name {
+            b"id" if !value.windows(3).any(|w| w == b"-->") => {
+                r.id_offset = base + start + colon + 1;
+                r.id_len = value.len();
+            }
+            b"width" => r.width = parse_percentage(value).unwrap_or(r.width),
+            b"lines" => {
+                if let Some((n, len)) = parse_u64(value) {
+                    if len == value.len() {
+                        r.lines = n.min(u32::MAX as u64) as u32;
+                    }
+                }
+            }
+            b"regionanchor" => {
+                if let Some((x, y)) = anchor(value) {
+                    (r.region_anchor_x, r.region_anchor_y) = (x, y);
+                }
+            }
+            b"viewportanchor" => {
+                if let Some((x, y)) = anchor(value) {
+                    (r.viewport_anchor_x, r.viewport_anchor_y) = (x, y);
+                }
+            }
+            b"scroll" if value == b"up" => r.scroll = 1,
+            _ => {}
+        }
+    }
+    r
+}
+
+struct Css<'a> {
+    s: &'a [u8],
+    pos: usize,
+}
+
+impl Css<'_> {
+    fn skip_comment(&mut self) -> bool {
+        if self.s[self.pos..].starts_with(b"/*") {
+            self.pos = match self.s[self.pos + 2..].windows(2).position(|w| w == b"*/") {
+                Some(i) => self.pos + 2 + i + 2,
+                None => self.s.len(),
+            };
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_string(&mut self) {
+        let quote = self.s[self.pos];
+        self.pos += 1;
+        while self.pos < self.s.len() && self.s[self.pos] != quote {
+            self.pos += if self.s[self.pos] == b'\\' { 2 } else { 1 };
+        }
+        self.pos = (self.pos + 1).min(self.s.len());
+    }
+
+    // Advances to the first of `stops` outside strings, comments, brackets
+    // and nested blocks, or to the end of input.
+    fn scan_to(&mut self, stops: &[u8]) {
+        let mut depth = 0usize;
+        // `{` of unsupported constructs, counted rather than recursed into
+        // so the nesting of the input cannot exhaust the stack.
+        let mut nested = 0usize;
+        while self.pos < self.s.len() {
+            let b = self.s[self.pos];
+            if self.skip_comment() {
+                continue;
+            }
+            match b {
+                b'"' | b'\'' => {
+                    self.skip_string();
+                    continue;
+                }
+                b'\\' => {
+                    self.pos += 2;
+                    continue;
+                }
+                b'(' | b'[' => depth += 1,
+                b')' | b']' => depth = depth.saturating_sub(1),
+                _ if depth == 0 && nested == 0 && stops.contains(&b) => return,
+                b'{' => nested += 1,
+                b'}' => nested = nested.saturating_sub(1),
+                _ => {}
+            }
+            self.pos += 1;
+        }
+        self.pos = self.s.len();
+    }
+}
+
+// `::cue` selector of a rule prelude: Some(None) for a bare `::cue`,
+// Some(Some(span)) for `::cue(...)`, None for anything else.
+fn cue_selector(s: &[u8], start: usize, end: usize) -> Option<Option<(usize, usize)>> {
+    let sel = &s[start..end];
+    let rest = sel.strip_prefix(b"::cue")?;
+    if rest.is_empty() {
+        return Some(None);
+    }
+    if rest.first() == Some(&b'(') && rest.last() == Some(&b')') {
+        return Some(Some(trim_span(s, start + 6, end - 1)));
+    }
+    None
+}
+
+// Rules of a STYLE block body `s[start..]`. Only `::cue` rules are reported;
+// other rules and at-rules are skipped. Declarations are numbered from
+// `next_decl`, the number reported before this block.
+fn parse_style(
+    s: &[u8],
+    start: usize,
+    mut next_decl: usize,
+    mut rule: impl FnMut(FFmpegRsWebvttStyleRule),
+    mut decl: impl FnMut(FFmpegRsWebvttStyleDecl),
+) {
+    let mut css = Css { s, pos: start };
+    while css.pos < s.len() {
+        if is_space(s[css.pos]) {
+            css.pos += 1;
+            continue;
+        }
+        if css.skip_comment() {
+            continue;
+        }
+        let prelude_start = css.pos;
+        css.scan_to(b"{;");
+        let prelude_end = css.pos;
+        if css.pos >= s.len() {
+            return;
+        }
+        css.pos += 1;
+        if s[prelude_end] == b';' {
+            // Statement at-rule such as @import.
+            continue;
+        }
+
+        // Collect the ::cue selectors of the comma-separated prelude; lists
+        // longer than `sels` are cut short.
+        let mut sels = [None; 16];
+        let mut n_sels = 0usize;
+        let mut any_cue = false;
+        let mut p = Css { s: &s[..prelude_end], pos: prelude_start };
+        while p.pos < prelude_end {
+            let sel_start = p.pos;
+            p.scan_to(b",");
+            let (a, b) = trim_span(s, sel_start, p.pos);
+            if let Some(sel) = cue_selector(s, a, b) {
+                any_cue = true;
+                if n_sels < sels.len() {
+                    sels[n_sels] = Some(sel);
+                    n_sels += 1;
+                }
+            }
+            p.pos += 1;
+        }
+
+        let first_decl = next_decl;
+        loop {
+            while css.pos < s.len() && (is_space(s[css.pos]) || s[css.pos] == b';') {
+                css.pos += 1;
+            }
+            if css.pos >= s.len() || s[css.pos] == b'}' {
+                css.pos += 1;
+                break;
+            }
+            let decl_start = css.pos;
+            css.scan_to(b";}");
+            let decl_end = css.pos;
+            let item = &s[decl_start..decl_end];
+            if !any_cue {
+                continue;
+            }
+            if let Some(colon) = item.iter().position(|&b| b == b':') {
+                let (pa, pb) = trim_span(s, decl_start, decl_start + colon);
+                let (va, vb) = trim_span(s, decl_start + colon + 1, decl_end);
+                if pa < pb && va < vb {
+                    decl(FFmpegRsWebvttStyleDecl {
+                        property_offset: pa,
+                        property_len: pb - pa,
+                        value_offset: va,
+                        value_len: vb - va,
+                    });
+                    next_decl += 1;
+                }
+            }
+        }
+        for sel in sels.iter().take(n_sels).flatten() {
+            let (selector_offset, selector_len) = match *sel {
+                Some((a, b)) => (a, b - a),
+                None => (0, 0),
+            };
+            rule(FFmpegRsWebvttStyleRule {
+                selector_offset,
+                selector_len,
+                first_decl,
+                n_decls: next_decl - first_decl,
+            });
+        }
+    }
+}
+
+// Parses the REGION and STYLE blocks of the header, i.e. those before the
+// first cue; later ones are ignored as the spec requires. Offsets are into
+// `text`. Returns 0, -EINVAL on invalid arguments or -2 without a WEBVTT
+// signature.
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_webvtt_parse_header(
+    text: *const u8,
+    text_len: usize,
+    out: *mut FFmpegRsWebvttHeaderResult,
+    regions: *mut FFmpegRsWebvttRegion,
+    regions_cap: usize,
+    rules: *mut FFmpegRsWebvttStyleRule,
+    rules_cap: usize,
+    decls: *mut FFmpegRsWebvttStyleDecl,
+    decls_cap: usize,
+) -> c_int {
+    let (data, out) = match (in_bytes(text, text_len), out_ref(out)) {
+        (Some(d), Some(o)) => (d, o),
+        _ => return EINVAL,
+    };
+    if !has_signature(data) {
+        return -2;
+    }
+    let regions = out_array(regions, regions_cap);
+    let rules = out_array(rules, rules_cap);
+    let decls = out_array(decls, decls_cap);
+    let mut res = FFmpegRsWebvttHeaderResult::default();
+
+    for (pos, block) in blocks(data).skip(1) {
+        let (line1, rest) = first_line(block);
+        if block.windows(3).any(|w| w == b"-->") {
+            break;
+        }
+        match line1 {
+            b"REGION" => {
+                if let Some(slot) = regions.get_mut(res.n_regions) {
+                    *slot = parse_region(&block[rest..], pos + rest);
+                }
+                res.n_regions += 1;
+            }
+            b"STYLE" => {
+                let block_end = pos + block.len();
+                parse_style(
+                    &data[..block_end],
+                    pos + rest,
+                    res.n_decls,
+                    |r| {
+                        if let Some(slot) = rules.get_mut(res.n_rules) {
+                            *slot = r;
+                        }
+                        res.n_rules += 1;
+                    },
+                    |d| {
+                        if let Some(slot) = decls.get_mut(res.n_decls) {
+                            *slot = d;
+                        }
+                        res.n_decls += 1;
+                    },
+                );
+            }
+            _ => {}
+        }
+    }
+    *out = res;
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &[u8] = b"WEBVTT\n\nREGION\nid:fred width:40% lines:3\nregionanchor:0%,100% viewportanchor:10%,90% scroll:up\n\n\
+REGION\nid:bill width:abc lines:-1 scroll:down\n\n\
+STYLE\n/* comment */\n::cue {\n  color: white; background-color: rgba(0,0,0,0.8)\n}\n\
+::cue(.yellow), ::cue(v[voice=\"A, B\"]) { color: yellow !important; }\n\
+video { color: red }\n::cue-region { color: blue }\n::cue(b) { }\n\n\
+00:00.000 --> 00:01.000\nHi\n\nREGION\nid:late\n\n";
+
+    fn span(s: &[u8], off: usize, len: usize) -> &[u8] {
+        &s[off..off + len]
+    }
+
+    #[test]
+    fn parses_regions_and_styles() {
+        let empty_region = parse_region(b"", 0);
+        let mut regions = [empty_region; 4];
+        let mut rules = [FFmpegRsWebvttStyleRule { selector_offset: 0, selector_len: 0, first_decl: 0, n_decls: 0 }; 8];
+        let mut decls = [FFmpegRsWebvttStyleDecl { property_offset: 0, property_len: 0, value_offset: 0, value_len: 0 }; 8];
+        let mut out = FFmpegRsWebvttHeaderResult::default();
+        let r = ffmpeg_rs_webvtt_parse_header(
+            DOC.as_ptr(),
+            DOC.len(),
+            &mut out,
+            regions.as_mut_ptr(),
+            regions.len(),
+            rules.as_mut_ptr(),
+            rules.len(),
+            decls.as_mut_ptr(),
+            decls.len(),
+        );
+        assert_eq!(r, 0);
+        assert_eq!((out.n_regions, out.n_rules, out.n_decls), (2, 4, 3));
+
+        let fred = regions[0];
+        assert_eq!(span(DOC, fred.id_offset, fred.id_len), b"fred");
+        assert_eq!((fred.width, fred.lines, fred.scroll), (40.0, 3, 1));
+        assert_eq!((fred.region_anchor_x, fred.region_anchor_y), (0.0, 100.0));
+        assert_eq!((fred.viewport_anchor_x, fred.viewport_anchor_y), (10.0, 90.0));
+        let bill = regions[1];
+        assert_eq!(span(DOC, bill.id_offset, bill.id_len), b"bill");
+        assert_eq!((bill.width, bill.lines, bill.scroll), (100.0, 3, 0));
+
+        assert_eq!((rules[0].selector_len, rules[0].first_decl, rules[0].n_decls), (0, 0, 2));
+        assert_eq!(span(DOC, decls[1].property_offset, decls[1].property_len), b"background-color");
+        assert_eq!(span(DOC, decls[1].value_offset, decls[1].value_len), b"rgba(0,0,0,0.8)");
+        assert_eq!(span(DOC, rules[1].selector_offset, rules[1].selector_len), b".yellow");
+        assert_eq!(span(DOC, rules[2].selector_offset, rules[2].selector_len), b"v[voice=\"A, B\"]");
+        assert_eq!((rules[2].first_decl, rules[2].n_decls), (2, 1));
+        assert_eq!(span(DOC, decls[2].value_offset, decls[2].value_len), b"yellow !important");
+        assert_eq!(span(DOC, rules[3].selector_offset, rules[3].selector_len), b"b");
+        assert_eq!((rules[3].first_decl, rules[3].n_decls), (3, 0));
+
+        let bad = b"WEBVTX\n\n";
+        assert_eq!(
+            ffmpeg_rs_webvtt_parse_header(bad.as_ptr(), bad.len(), &mut out, regions.as_mut_ptr(), 0, rules.as_mut_ptr(), 0, decls.as_mut_ptr(), 0),
+            -2
+        );
+    }
+
+    #[test]
+    fn skips_deeply_nested_blocks() {
+        let mut doc = [b'{'; 200_000];
+        let head = b"WEBVTT\n\nSTYLE\n::cue { color: ";
+        doc[..head.len()].copy_from_slice(head);
+        let mut out = FFmpegRsWebvttHeaderResult::default();
+        let null = core::ptr::null_mut();
+        let r = ffmpeg_rs_webvtt_parse_header(doc.as_ptr(), doc.len(), &mut out, null, 0, null.cast(), 0, null.cast(), 0);
+        assert_eq!((r, out.n_rules, out.n_decls), (0, 1, 1));
+
+        // The closing brace of a nested block does not end the rule.
+        let doc = b"WEBVTT\n\nSTYLE\n::cue { a: {x;}; b: c }\n";
+        let mut decls = [FFmpegRsWebvttStyleDecl { property_offset: 0, property_len: 0, value_offset: 0, value_len: 0 }; 2];
+        let r = ffmpeg_rs_webvtt_parse_header(doc.as_ptr(), doc.len(), &mut out, null, 0, null.cast(), 0, decls.as_mut_ptr(), 2);
+        assert_eq!((r, out.n_rules, out.n_decls), (0, 1, 2));
+        assert_eq!(span(doc, decls[0].value_offset, decls[0].value_len), b"{x;}");
+    }
+}
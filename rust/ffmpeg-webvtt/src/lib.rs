@@ -2,6 +2,30 @@
 
 use core::ffi::c_int;
 
+mod check;
+mod encode;
+mod header;
+mod markup;
+mod mp4;
+mod records;
+mod segment;
+mod settings;
+mod stream;
+mod text;
+mod writer;
+
+pub use check::*;
+pub use encode::*;
+pub use header::*;
+pub use markup::*;
+pub use mp4::*;
+pub use records::*;
+pub use segment::*;
+pub use settings::*;
+pub use stream::*;
+
+use writer::{in_bytes, out_array, out_ref};
+
 #[cfg(not(test))]
 #[panic_handler]
 fn panic_handler(_: &core::panic::PanicInfo) -> ! {
@@ -37,12 +61,12 @@ fn is_ws(b: u8) -> bool {
     b == b' ' || b == b'\t'
 }
 
-fn parse_u64(mut s: &[u8]) -> Option<(u64, usize)> {
+fn parse_u64(s: &[u8]) -> Option<(u64, usize)> {
     let mut v: u64 = 0;
     let mut n = 0usize;
     while n < s.len() {
         let b = s[n];
-        if b < b'0' || b > b'9' {
+        if !b.is_ascii_digit() {
             break;
         }
         v = v.saturating_mul(10).saturating_add((b - b'0') as u64);
@@ -58,17 +82,16 @@ fn parse_u64(mut s: &[u8]) -> Option<(u64, usize)> {
 fn parse_ts_ms(s: &[u8]) -> Option<i64> {
     // Supports HH:MM:SS.mmm or MM:SS.mmm
     // Minimal strict parsing.
-    let s = s;
     // Split at '.'
     let dot = s.iter().position(|&b| b == b'.')?;
     let (lhs, rhs) = (&s[..dot], &s[dot + 1..]);
     let mut ms = 0u64;
-    if rhs.len() < 1 {
+    if rhs.is_empty() {
         return None;
     }
     let mut frac = 0u64;
     let mut digits = 0usize;
-    for &b in rhs.iter().take_while(|&&b| b >= b'0' && b <= b'9') {
+    for &b in rhs.iter().take_while(|&&b| b.is_ascii_digit()) {
         if digits < 3 {
             frac = frac * 10 + (b - b'0') as u64;
             digits += 1;
@@ -116,157 +139,162 @@ fn parse_ts_ms(s: &[u8]) -> Option<i64> {
     Some(total_ms as i64)
 }
 
-#[no_mangle]
-pub extern "C" fn ffmpeg_rs_webvtt_parse(
-    text: *const u8,
-    text_len: usize,
-    out: *mut FFmpegRsWebvttParseResult,
-    cues: *mut FFmpegRsWebvttCue,
-    cues_cap: usize,
-) -> c_int {
-    if text.is_null() || out.is_null() {
-        return -1;
-    }
-    let data = unsafe { core::slice::from_raw_parts(text, text_len) };
-    let mut n_cues = 0usize;
+// Checks the WEBVTT signature, skipping a UTF-8 BOM.
+pub(crate) fn has_signature(data: &[u8]) -> bool {
+    let data = data.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(data);
+    data.starts_with(b"WEBVTT")
+}
 
-    // Skip UTF-8 BOM if present.
-    let mut i = 0usize;
-    if text_len >= 3 && data[0..3] == [0xEF, 0xBB, 0xBF] {
-        i = 3;
+// Finds the block starting at or after `pos`: its start and end offsets.
+// `end` is `data.len()` when no blank line terminates the block.
+pub(crate) fn next_block(data: &[u8], mut pos: usize) -> Option<(usize, usize)> {
+    let text_len = data.len();
+    // Find next block start: skip leading newlines.
+    while pos < text_len && (data[pos] == b'\n' || data[pos] == b'\r') {
+        pos += 1;
     }
-
-    // Require "WEBVTT" at start.
-    if i + 6 > text_len || &data[i..i + 6] != b"WEBVTT" {
-        return -2;
+    if pos >= text_len {
+        return None;
     }
-
-    // Split by blank lines into blocks.
-    // We'll do a simple scan for blocks separated by two newlines.
-    let mut pos = 0usize;
-    while pos < text_len {
-        // Find next block start: skip leading newlines.
-        while pos < text_len && (data[pos] == b'\n' || data[pos] == b'\r') {
-            pos += 1;
-        }
-        if pos >= text_len {
-            break;
-        }
-        // Find block end.
-        let mut end = pos;
-        while end < text_len {
-            if data[end] == b'\n' {
-                // check for blank line (\n\n or \n\r\n)
-                if end + 1 < text_len && data[end + 1] == b'\n' {
-                    break;
-                }
-                if end + 2 < text_len && data[end + 1] == b'\r' && data[end + 2] == b'\n' {
-                    break;
-                }
+    // Find block end.
+    let mut end = pos;
+    while end < text_len {
+        if data[end] == b'\n' {
+            // check for blank line (\n\n or \n\r\n)
+            if end + 1 < text_len && data[end + 1] == b'\n' {
+                break;
+            }
+            if end + 2 < text_len && data[end + 1] == b'\r' && data[end + 2] == b'\n' {
+                break;
             }
-            end += 1;
         }
-        let block = &data[pos..end];
+        end += 1;
+    }
+    Some((pos, end))
+}
 
-        // Header-style blocks to ignore: WEBVTT/STYLE/REGION/NOTE.
-        let first_line_end = block.iter().position(|&b| b == b'\n').unwrap_or(block.len());
-        let first_line = chomp_cr(&block[..first_line_end]);
-        if first_line.starts_with(b"WEBVTT") || first_line == b"STYLE" || first_line == b"REGION" || first_line == b"NOTE" {
-            pos = end + 1;
-            continue;
-        }
+// Splits the document into blank-line separated blocks, yielding each
+// block's offset and bytes (without the terminating newline).
+pub(crate) fn blocks(data: &[u8]) -> impl Iterator<Item = (usize, &[u8])> {
+    let mut pos = 0usize;
+    core::iter::from_fn(move || {
+        let (start, end) = next_block(data, pos)?;
+        pos = end + 1;
+        Some((start, &data[start..end]))
+    })
+}
 
-        // Parse optional identifier line if the first line does not contain "-->".
-        let mut line1 = first_line;
-        let mut rest_start = first_line_end;
+// First line of a block without its line terminator, and the offset of the
+// line after it.
+pub(crate) fn first_line(block: &[u8]) -> (&[u8], usize) {
+    let end = block.iter().position(|&b| b == b'\n').unwrap_or(block.len());
+    (chomp_cr(&block[..end]), (end + 1).min(block.len()))
+}
+
+// Parses one block at offset `pos`; None for header-style blocks and
+// malformed cues.
+pub(crate) fn parse_cue_block(pos: usize, block: &[u8]) -> Option<FFmpegRsWebvttCue> {
+    // Header-style blocks to ignore: WEBVTT/STYLE/REGION/NOTE.
+    let (line1, mut rest_start) = first_line(block);
+    if line1.starts_with(b"WEBVTT") || line1 == b"STYLE" || line1 == b"REGION" || line1 == b"NOTE" {
+        return None;
+    }
+
+    // Parse optional identifier line if the first line does not contain "-->".
+    let mut identifier_offset = 0usize;
+    let mut identifier_len = 0usize;
+    let mut ts_line = line1;
+    let mut ts_line_offset = pos;
+
+    if !line1.windows(3).any(|w| w == b"-->") {
+        identifier_offset = pos;
+        identifier_len = line1.len();
+        // Next line is timestamp line.
+        let next_end = block[rest_start..].iter().position(|&b| b == b'\n').map(|x| rest_start + x).unwrap_or(block.len());
+        ts_line = chomp_cr(&block[rest_start..next_end]);
+        ts_line_offset = pos + rest_start;
+        rest_start = next_end;
         if rest_start < block.len() && block[rest_start] == b'\n' {
             rest_start += 1;
         }
+    }
 
-        let mut identifier_offset = 0usize;
-        let mut identifier_len = 0usize;
-        let mut ts_line = line1;
-        let mut ts_line_offset = pos;
-
-        if !line1.windows(3).any(|w| w == b"-->") {
-            identifier_offset = pos;
-            identifier_len = line1.len();
-            // Next line is timestamp line.
-            let next_end = block[rest_start..].iter().position(|&b| b == b'\n').map(|x| rest_start + x).unwrap_or(block.len());
-            ts_line = chomp_cr(&block[rest_start..next_end]);
-            ts_line_offset = pos + rest_start;
-            rest_start = next_end;
-            if rest_start < block.len() && block[rest_start] == b'\n' {
-                rest_start += 1;
-            }
-        }
+    // Parse timestamp line: "<ts> --> <ts> [settings]"
+    let arrow = ts_line.windows(3).position(|w| w == b"-->")?;
+    let left = chomp_cr(&ts_line[..arrow]);
+    let mut right = &ts_line[arrow + 3..];
+    while !right.is_empty() && is_ws(right[0]) {
+        right = &right[1..];
+    }
 
-        // Parse timestamp line: "<ts> --> <ts> [settings]"
-        let arrow = ts_line.windows(3).position(|w| w == b"-->");
-        let arrow = match arrow {
-            Some(a) => a,
-            None => {
-                pos = end + 1;
-                continue;
-            }
-        };
-        let left = chomp_cr(&ts_line[..arrow]);
-        let mut right = &ts_line[arrow + 3..];
-        while !right.is_empty() && is_ws(right[0]) {
-            right = &right[1..];
-        }
+    // Right side may contain settings after whitespace.
+    let right_end_ts = right.iter().position(|&b| is_ws(b)).unwrap_or(right.len());
+    let right_ts = &right[..right_end_ts];
+    let mut settings = &right[right_end_ts..];
+    while !settings.is_empty() && is_ws(settings[0]) {
+        settings = &settings[1..];
+    }
 
-        // Right side may contain settings after whitespace.
-        let right_end_ts = right.iter().position(|&b| is_ws(b)).unwrap_or(right.len());
-        let right_ts = &right[..right_end_ts];
-        let mut settings = &right[right_end_ts..];
-        while !settings.is_empty() && is_ws(settings[0]) {
-            settings = &settings[1..];
-        }
+    let start_ms = parse_ts_ms(left)?;
+    let end_ms = parse_ts_ms(right_ts)?;
 
-        let start_ms = match parse_ts_ms(left) {
-            Some(v) => v,
-            None => {
-                pos = end + 1;
-                continue;
-            }
-        };
-        let end_ms = match parse_ts_ms(right_ts) {
-            Some(v) => v,
-            None => {
-                pos = end + 1;
-                continue;
-            }
-        };
+    // Payload is rest of block after the timestamp line.
+    let payload_offset = pos + rest_start;
+    let payload_len = if rest_start <= block.len() { block.len() - rest_start } else { 0 };
 
-        // Payload is rest of block after the timestamp line.
-        let payload_offset = pos + rest_start;
-        let payload_len = if rest_start <= block.len() { block.len() - rest_start } else { 0 };
+    let settings_offset = ts_line_offset + (ts_line.len() - settings.len());
+    let settings_len = settings.len();
 
-        let settings_offset = (ts_line_offset + (ts_line.len() - settings.len())) as usize;
-        let settings_len = settings.len();
+    Some(FFmpegRsWebvttCue {
+        start_ms,
+        end_ms,
+        payload_offset,
+        payload_len,
+        identifier_offset,
+        identifier_len,
+        settings_offset,
+        settings_len,
+    })
+}
 
-        if n_cues < cues_cap {
-            unsafe {
-                *cues.add(n_cues) = FFmpegRsWebvttCue {
-                    start_ms,
-                    end_ms,
-                    payload_offset,
-                    payload_len,
-                    identifier_offset,
-                    identifier_len,
-                    settings_offset,
-                    settings_len,
-                };
-            }
+// Walks the cue blocks of a WebVTT document, calling `emit` for each cue in
+// document order. Returns -2 if the WEBVTT signature is missing.
+pub(crate) fn parse_document(data: &[u8], mut emit: impl FnMut(FFmpegRsWebvttCue)) -> Result<(), c_int> {
+    if !has_signature(data) {
+        return Err(-2);
+    }
+    for (pos, block) in blocks(data) {
+        if let Some(cue) = parse_cue_block(pos, block) {
+            emit(cue);
         }
-        n_cues += 1;
-        pos = end + 1;
     }
+    Ok(())
+}
 
-    unsafe {
-        (*out).n_cues = n_cues;
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_webvtt_parse(
+    text: *const u8,
+    text_len: usize,
+    out: *mut FFmpegRsWebvttParseResult,
+    cues: *mut FFmpegRsWebvttCue,
+    cues_cap: usize,
+) -> c_int {
+    let (data, out) = match (in_bytes(text, text_len), out_ref(out)) {
+        (Some(d), Some(o)) => (d, o),
+        _ => return -1,
+    };
+    let cues = out_array(cues, cues_cap);
+    let mut n_cues = 0usize;
+    let r = parse_document(data, |cue| {
+        if n_cues < cues.len() {
+            cues[n_cues] = cue;
+        }
+        n_cues += 1;
+    });
+    if let Err(e) = r {
+        return e;
     }
+    out.n_cues = n_cues;
     0
 }
 
@@ -0,0 +1,348 @@
+use core::ffi::c_int;
+
+use crate::parse_ts_ms;
+use crate::text::{is_space, trim_span};
+use crate::writer::{in_bytes, out_array, out_ref, EINVAL};
+
+/// Open tags tracked for balancing; deeper start tags are ignored.
+const MAX_DEPTH: usize = 16;
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FFmpegRsWebvttNodeKind {
+    /// Literal text, `offset`/`len` in the payload.
+    Text = 0,
+    Start = 1,
+    End = 2,
+    /// A decoded character reference; see `codepoint`.
+    Char = 3,
+    /// `<hh:mm:ss.ttt>`; see `timestamp_ms`.
+    Timestamp = 4,
+}
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FFmpegRsWebvttTag {
+    None = 0,
+    C = 1,
+    I = 2,
+    B = 3,
+    U = 4,
+    Ruby = 5,
+    Rt = 6,
+    V = 7,
+    Lang = 8,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FFmpegRsWebvttNode {
+    /// FFmpegRsWebvttNodeKind.
+    pub kind: u32,
+    /// FFmpegRsWebvttTag.
+    pub tag: u32,
+    /// Source span of the token; 0 length for implied end tags.
+    pub offset: usize,
+    pub len: usize,
+    /// Start tags: class list without the leading `.`, e.g. `a.b`.
+    pub classes_offset: usize,
+    pub classes_len: usize,
+    /// Start tags: speaker of `v`, language of `lang`, trimmed.
+    pub annotation_offset: usize,
+    pub annotation_len: usize,
+    pub codepoint: u32,
+    /// End tags closed implicitly by an outer end tag or end of payload.
+    pub implied: c_int,
+    pub timestamp_ms: i64,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FFmpegRsWebvttMarkupResult {
+    /// Total nodes, including any that did not fit the caller array.
+    pub n_nodes: usize,
+    /// Unknown, misplaced, unmatched or too deeply nested tags dropped.
+    pub n_ignored_tags: usize,
+}
+
+impl FFmpegRsWebvttNode {
+    fn new(kind: FFmpegRsWebvttNodeKind, offset: usize, len: usize) -> Self {
+        FFmpegRsWebvttNode {
+            kind: kind as u32,
+            tag: FFmpegRsWebvttTag::None as u32,
+            offset,
+            len,
+            classes_offset: 0,
+            classes_len: 0,
+            annotation_offset: 0,
+            annotation_len: 0,
+            codepoint: 0,
+            implied: 0,
+            timestamp_ms: 0,
+        }
+    }
+}
+
+fn tag_of(name: &[u8]) -> Option<FFmpegRsWebvttTag> {
+    use FFmpegRsWebvttTag as T;
+    Some(match name {
+        b"c" => T::C,
+        b"i" => T::I,
+        b"b" => T::B,
+        b"u" => T::U,
+        b"ruby" => T::Ruby,
+        b"rt" => T::Rt,
+        b"v" => T::V,
+        b"lang" => T::Lang,
+        _ => return None,
+    })
+}
+
+// Character reference at the start of `s` (which begins with `&`): the
+// code point and the reference length, `;` included.
+fn char_ref(s: &[u8]) -> Option<(u32, usize)> {
+    let semi = s.iter().take(12).position(|&b| b == b';')?;
+    let name = &s[1..semi];
+    let cp = match name {
+        b"amp" => '&' as u32,
+        b"lt" => '<' as u32,
+        b"gt" => '>' as u32,
+        b"quot" => '"' as u32,
+        b"apos" => '\'' as u32,
+        b"nbsp" => 0xA0,
+        b"lrm" => 0x200E,
+        b"rlm" => 0x200F,
+        _ => {
+            let (digits, radix) = match name {
+                [b'#', b'x' | b'X', rest @ ..] => (rest, 16),
+                [b'#', rest @ ..] => (rest, 10),
+                _ => return None,
+            };
+            if digits.is_empty() {
+                return None;
+            }
+            let mut v = 0u32;
+            for &b in digits {
+                v = v.checked_mul(radix)?.checked_add((b as char).to_digit(radix)?)?;
+            }
+            char::from_u32(v).filter(|&c| c != '\0')? as u32
+        }
+    };
+    Some((cp, semi + 1))
+}
+
+// Tokenizes cue text into a balanced node stream: every Start node is
+// matched by an End node. End tags close any tags opened inside them;
+// unmatched end tags, unknown tags and `rt` outside `ruby` are dropped.
+pub(crate) fn tokenize(s: &[u8], mut emit: impl FnMut(FFmpegRsWebvttNode)) -> FFmpegRsWebvttMarkupResult {
+    use FFmpegRsWebvttNodeKind::*;
+    let mut res = FFmpegRsWebvttMarkupResult::default();
+    let mut out = |n: FFmpegRsWebvttNode, res: &mut FFmpegRsWebvttMarkupResult| {
+        res.n_nodes += 1;
+        emit(n);
+    };
+    let mut stack = [FFmpegRsWebvttTag::None; MAX_DEPTH];
+    let mut depth = 0usize;
+    let mut pos = 0usize;
+    while pos < s.len() {
+        if s[pos] != b'<' {
+            if s[pos] == b'&' {
+                if let Some((cp, len)) = char_ref(&s[pos..]) {
+                    let mut n = FFmpegRsWebvttNode::new(Char, pos, len);
+                    n.codepoint = cp;
+                    out(n, &mut res);
+                    pos += len;
+                    continue;
+                }
+            }
+            let start = pos;
+            pos += 1;
+            while pos < s.len() && s[pos] != b'<' && !(s[pos] == b'&' && char_ref(&s[pos..]).is_some()) {
+                pos += 1;
+            }
+            out(FFmpegRsWebvttNode::new(Text, start, pos - start), &mut res);
+            continue;
+        }
+
+        // A tag runs to `>` or, unterminated, to the end of the payload.
+        let start = pos;
+        let close = s[pos..].iter().position(|&b| b == b'>').map_or(s.len(), |i| pos + i);
+        let body_start = pos + 1;
+        pos = (close + 1).min(s.len());
+        let body = &s[body_start..close];
+
+        if let Some(name) = body.strip_prefix(b"/") {
+            let name = &name[..name.iter().position(|&b| is_space(b)).unwrap_or(name.len())];
+            let tag = tag_of(name);
+            match stack[..depth].iter().rposition(|&t| Some(t) == tag) {
+                Some(i) => {
+                    while depth > i + 1 {
+                        depth -= 1;
+                        let mut n = FFmpegRsWebvttNode::new(End, start, 0);
+                        n.tag = stack[depth] as u32;
+                        n.implied = 1;
+                        out(n, &mut res);
+                    }
+                    depth -= 1;
+                    let mut n = FFmpegRsWebvttNode::new(End, start, pos - start);
+                    n.tag = stack[depth] as u32;
+                    out(n, &mut res);
+                }
+                None => res.n_ignored_tags += 1,
+            }
+            continue;
+        }
+
+        if body.first().is_some_and(u8::is_ascii_digit) {
+            match parse_ts_ms(body).filter(|_| body.iter().all(|&b| b.is_ascii_digit() || b == b':' || b == b'.')) {
+                Some(ms) => {
+                    let mut n = FFmpegRsWebvttNode::new(Timestamp, start, pos - start);
+                    n.timestamp_ms = ms;
+                    out(n, &mut res);
+                }
+                None => res.n_ignored_tags += 1,
+            }
+            continue;
+        }
+
+        let name_end = body.iter().position(|&b| b == b'.' || is_space(b)).unwrap_or(body.len());
+        let classes_end = body[name_end..].iter().position(|&b| is_space(b)).map_or(body.len(), |i| name_end + i);
+        let tag = match tag_of(&body[..name_end]) {
+            Some(FFmpegRsWebvttTag::Rt) if depth == 0 || stack[depth - 1] != FFmpegRsWebvttTag::Ruby => None,
+            Some(_) if depth == MAX_DEPTH => None,
+            t => t,
+        };
+        let tag = match tag {
+            Some(t) => t,
+            None => {
+                res.n_ignored_tags += 1;
+                continue;
+            }
+        };
+        let mut n = FFmpegRsWebvttNode::new(Start, start, pos - start);
+        n.tag = tag as u32;
+        if classes_end > name_end + 1 {
+            n.classes_offset = body_start + name_end + 1;
+            n.classes_len = classes_end - name_end - 1;
+        }
+        let (a, b) = trim_span(body, classes_end, body.len());
+        if matches!(tag, FFmpegRsWebvttTag::V | FFmpegRsWebvttTag::Lang) && a < b {
+            n.annotation_offset = body_start + a;
+            n.annotation_len = b - a;
+        }
+        stack[depth] = tag;
+        depth += 1;
+        out(n, &mut res);
+    }
+    while depth > 0 {
+        depth -= 1;
+        let mut n = FFmpegRsWebvttNode::new(End, s.len(), 0);
+        n.tag = stack[depth] as u32;
+        n.implied = 1;
+        out(n, &mut res);
+    }
+    res
+}
+
+// Tokenizes a cue payload into `nodes`. Offsets are into `payload`. Returns
+// 0, or -EINVAL on invalid arguments.
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_webvtt_tokenize_cue_text(
+    payload: *const u8,
+    payload_len: usize,
+    out: *mut FFmpegRsWebvttMarkupResult,
+    nodes: *mut FFmpegRsWebvttNode,
+    nodes_cap: usize,
+) -> c_int {
+    let (s, out) = match (in_bytes(payload, payload_len), out_ref(out)) {
+        (Some(s), Some(o)) => (s, o),
+        _ => return EINVAL,
+    };
+    let nodes = out_array(nodes, nodes_cap);
+    let mut n = 0usize;
+    *out = tokenize(s, |node| {
+        if let Some(slot) = nodes.get_mut(n) {
+            *slot = node;
+        }
+        n += 1;
+    });
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use FFmpegRsWebvttNodeKind::*;
+    use FFmpegRsWebvttTag as T;
+
+    fn run(s: &[u8]) -> ([FFmpegRsWebvttNode; 24], FFmpegRsWebvttMarkupResult) {
+        let mut nodes = [FFmpegRsWebvttNode::new(Text, 0, 0); 24];
+        let mut out = FFmpegRsWebvttMarkupResult::default();
+        assert_eq!(ffmpeg_rs_webvtt_tokenize_cue_text(s.as_ptr(), s.len(), &mut out, nodes.as_mut_ptr(), nodes.len()), 0);
+        (nodes, out)
+    }
+
+    fn span(s: &[u8], off: usize, len: usize) -> &[u8] {
+        &s[off..off + len]
+    }
+
+    #[test]
+    fn tokenizes_markup() {
+        let s = b"<v.loud Esme Sr>Hi &amp; <c.a.b>bye</c>&#x263A;<00:01.500><ruby>X<rt>y</rt></ruby> &bogus; <lang en-GB>ok</lang></v>";
+        let (n, out) = run(s);
+        assert_eq!((out.n_nodes, out.n_ignored_tags), (20, 0));
+        assert_eq!((n[0].kind, n[0].tag), (Start as u32, T::V as u32));
+        assert_eq!(span(s, n[0].classes_offset, n[0].classes_len), b"loud");
+        assert_eq!(span(s, n[0].annotation_offset, n[0].annotation_len), b"Esme Sr");
+        assert_eq!(span(s, n[1].offset, n[1].len), b"Hi ");
+        assert_eq!((n[2].kind, n[2].codepoint), (Char as u32, '&' as u32));
+        assert_eq!((n[4].kind, n[4].tag), (Start as u32, T::C as u32));
+        assert_eq!(span(s, n[4].classes_offset, n[4].classes_len), b"a.b");
+        assert_eq!((n[6].kind, n[6].tag, n[6].implied), (End as u32, T::C as u32, 0));
+        assert_eq!((n[7].kind, n[7].codepoint), (Char as u32, 0x263A));
+        assert_eq!((n[8].kind, n[8].timestamp_ms), (Timestamp as u32, 1500));
+        assert_eq!((n[11].kind, n[11].tag), (Start as u32, T::Rt as u32));
+        assert_eq!(span(s, n[15].offset, n[15].len), b" &bogus; ");
+        assert_eq!(span(s, n[16].annotation_offset, n[16].annotation_len), b"en-GB");
+        assert_eq!((n[19].kind, n[19].tag), (End as u32, T::V as u32));
+    }
+
+    #[test]
+    fn recovers_from_unbalanced_tags() {
+        let s = b"<b>a<i>b</b>c</i></u><rt>d<x>e<b>f";
+        let (n, out) = run(s);
+        assert_eq!(out.n_ignored_tags, 4);
+        let kinds: [(u32, u32, c_int); 11] = core::array::from_fn(|i| (n[i].kind, n[i].tag, n[i].implied));
+        assert_eq!(
+            kinds,
+            [
+                (Start as u32, T::B as u32, 0),
+                (Text as u32, T::None as u32, 0),
+                (Start as u32, T::I as u32, 0),
+                (Text as u32, T::None as u32, 0),
+                (End as u32, T::I as u32, 1),
+                (End as u32, T::B as u32, 0),
+                (Text as u32, T::None as u32, 0),
+                (Text as u32, T::None as u32, 0),
+                (Text as u32, T::None as u32, 0),
+                (Start as u32, T::B as u32, 0),
+                (Text as u32, T::None as u32, 0),
+            ]
+        );
+        assert_eq!((n[11].kind, n[11].tag, n[11].implied, n[11].offset), (End as u32, T::B as u32, 1, s.len()));
+        assert_eq!(out.n_nodes, 12);
+
+        let (n, out) = run(b"a <b");
+        assert_eq!((out.n_nodes, n[1].kind, n[1].len, n[2].implied), (3, Start as u32, 2, 1));
+    }
+
+    #[test]
+    fn implied_end_tags_keep_offsets_in_order() {
+        let s = b"<b><i>x</b>";
+        let (n, out) = run(s);
+        assert_eq!(out.n_nodes, 5);
+        assert_eq!((n[3].kind, n[3].tag, n[3].implied, n[3].offset, n[3].len), (End as u32, T::I as u32, 1, 7, 0));
+        assert_eq!(span(s, n[4].offset, n[4].len), b"</b>");
+        assert!(n[..5].windows(2).all(|w| w[0].offset <= w[1].offset));
+    }
+}
@@ -0,0 +1,405 @@
+use core::ffi::{c_char, c_int};
+
+use crate::writer::{in_array, in_bytes, in_str, out_array, out_ref, Writer, EINVAL};
+use crate::FFmpegRsWebvttCue;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FFmpegRsWebvttMp4Sample {
+    pub start_ms: i64,
+    pub duration_ms: i64,
+    /// Sample payload in the data buffer.
+    pub data_offset: usize,
+    pub data_size: usize,
+    /// `vttc` boxes in the sample; 0 for an empty (`vtte`) sample.
+    pub n_cues: usize,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FFmpegRsWebvttMp4EncodeResult {
+    pub n_samples_total: usize,
+    pub n_samples_written: usize,
+    pub truncated: c_int,
+    /// Size of all sample payloads.
+    pub data_required: usize,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FFmpegRsWebvttMp4DecodeResult {
+    /// `vttc` boxes, including any that did not fit the caller array.
+    pub n_cues: usize,
+    /// The sample holds a `vtte` box.
+    pub empty: c_int,
+    /// Payload of the first `vtta` box; 0 length if none.
+    pub additional_offset: usize,
+    pub additional_len: usize,
+}
+
+fn span(text: &[u8], off: usize, len: usize) -> Result<&[u8], c_int> {
+    let end = off.checked_add(len).ok_or(EINVAL)?;
+    text.get(off..end).ok_or(EINVAL)
+}
+
+fn box_header(w: &mut Writer, size: usize, kind: &[u8; 4]) -> Result<(), c_int> {
+    w.bytes(&u32::try_from(size).map_err(|_| EINVAL)?.to_be_bytes());
+    w.bytes(kind);
+    Ok(())
+}
+
+fn leaf_box(w: &mut Writer, kind: &[u8; 4], payload: &[u8]) -> Result<(), c_int> {
+    box_header(w, 8 + payload.len(), kind)?;
+    w.bytes(payload);
+    Ok(())
+}
+
+fn write_vttc(w: &mut Writer, text: &[u8], cue: &FFmpegRsWebvttCue) -> Result<(), c_int> {
+    let id = span(text, cue.identifier_offset, cue.identifier_len)?;
+    let settings = span(text, cue.settings_offset, cue.settings_len)?;
+    let mut payload = span(text, cue.payload_offset, cue.payload_len)?;
+    while let Some((b'\n' | b'\r', rest)) = payload.split_last() {
+        payload = rest;
+    }
+    let child = |s: &[u8]| if s.is_empty() { 0 } else { 8 + s.len() };
+    box_header(w, 8 + child(id) + child(settings) + 8 + payload.len(), b"vttc")?;
+    if !id.is_empty() {
+        leaf_box(w, b"iden", id)?;
+    }
+    if !settings.is_empty() {
+        leaf_box(w, b"sttg", settings)?;
+    }
+    leaf_box(w, b"payl", payload)
+}
+
+// Splits `[start_ms, end_ms)` at every cue boundary. Each interval becomes a
+// sample holding a `vttc` per active cue, or a `vtte` if none is active.
+// Each boundary scans all cues, so this is quadratic in the cue count.
+fn encode(
+    w: &mut Writer,
+    text: &[u8],
+    cues: &[FFmpegRsWebvttCue],
+    start_ms: i64,
+    end_ms: i64,
+    mut emit: impl FnMut(FFmpegRsWebvttMp4Sample),
+) -> Result<(), c_int> {
+    if cues.iter().any(|c| c.end_ms < c.start_ms) {
+        return Err(EINVAL);
+    }
+    let end_ms = if end_ms < 0 { cues.iter().map(|c| c.end_ms).max().unwrap_or(start_ms) } else { end_ms };
+    let active = |c: &&FFmpegRsWebvttCue, t: i64| c.start_ms <= t && t < c.end_ms;
+    let mut t = start_ms;
+    while t < end_ms {
+        let mut next = end_ms;
+        for c in cues {
+            if c.start_ms > t {
+                next = next.min(c.start_ms);
+            } else if c.end_ms > t {
+                next = next.min(c.end_ms);
+            }
+        }
+        let data_offset = w.len();
+        let mut n_cues = 0usize;
+        for c in cues.iter().filter(|c| active(c, t)) {
+            write_vttc(w, text, c)?;
+            n_cues += 1;
+        }
+        if n_cues == 0 {
+            box_header(w, 8, b"vtte")?;
+        }
+        emit(FFmpegRsWebvttMp4Sample {
+            start_ms: t,
+            duration_ms: next - t,
+            data_offset,
+            data_size: w.len() - data_offset,
+            n_cues,
+        });
+        t = next;
+    }
+    Ok(())
+}
+
+// Boxes of `data`: type and payload offset/length relative to `data`.
+fn boxes(data: &[u8]) -> impl Iterator<Item = Result<([u8; 4], usize, usize), c_int>> + '_ {
+    let mut pos = 0usize;
+    core::iter::from_fn(move || {
+        if pos >= data.len() {
+            return None;
+        }
+        let hdr = match data.get(pos..pos + 8) {
+            Some(h) => h,
+            None => {
+                pos = data.len();
+                return Some(Err(-2));
+            }
+        };
+        let size = u32::from_be_bytes([hdr[0], hdr[1], hdr[2], hdr[3]]) as usize;
+        let kind = [hdr[4], hdr[5], hdr[6], hdr[7]];
+        if size < 8 || size > data.len() - pos {
+            pos = data.len();
+            return Some(Err(-2));
+        }
+        let item = (kind, pos + 8, size - 8);
+        pos += size;
+        Some(Ok(item))
+    })
+}
+
+fn decode(data: &[u8], start_ms: i64, end_ms: i64, mut emit: impl FnMut(FFmpegRsWebvttCue)) -> Result<FFmpegRsWebvttMp4DecodeResult, c_int> {
+    let mut res = FFmpegRsWebvttMp4DecodeResult::default();
+    for b in boxes(data) {
+        let (kind, off, len) = b?;
+        match &kind {
+            b"vtte" => res.empty = 1,
+            b"vtta" if res.additional_len == 0 => {
+                res.additional_offset = off;
+                res.additional_len = len;
+            }
+            b"vttc" => {
+                let mut cue = FFmpegRsWebvttCue {
+                    start_ms,
+                    end_ms,
+                    payload_offset: 0,
+                    payload_len: 0,
+                    identifier_offset: 0,
+                    identifier_len: 0,
+                    settings_offset: 0,
+                    settings_len: 0,
+                };
+                let mut has_payl = false;
+                for child in boxes(&data[off..off + len]) {
+                    let (kind, coff, clen) = child?;
+                    let coff = off + coff;
+                    match &kind {
+                        b"payl" => {
+                            (cue.payload_offset, cue.payload_len) = (coff, clen);
+                            has_payl = true;
+                        }
+                        b"iden" => (cue.identifier_offset, cue.identifier_len) = (coff, clen),
+                        b"sttg" => (cue.settings_offset, cue.settings_len) = (coff, clen),
+                        // vsid, ctim and unknown boxes.
+                        _ => {}
+                    }
+                }
+                if !has_payl {
+                    return Err(-2);
+                }
+                res.n_cues += 1;
+                emit(cue);
+            }
+            _ => {}
+        }
+    }
+    Ok(res)
+}
+
+// Converts cues (e.g. from ffmpeg_rs_webvtt_parse() over `text`) into
+// ISO/IEC 14496-30 samples covering `[start_ms, end_ms)`; a negative
+// `end_ms` ends at the last cue end. Overlapping cues share samples and
+// gaps get empty `vtte` samples. Up to `samples_cap` records are written
+// (NULL/0 for a count-only pass) and their payloads stored in `data`.
+// Returns 0, -EINVAL on invalid arguments or cue spans, or -ENOSPC if
+// `data` is smaller than `out->data_required` (records are still written).
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "C" fn ffmpeg_rs_webvtt_mp4_encode(
+    text: *const u8,
+    text_len: usize,
+    cues: *const FFmpegRsWebvttCue,
+    n_cues: usize,
+    start_ms: i64,
+    end_ms: i64,
+    samples: *mut FFmpegRsWebvttMp4Sample,
+    samples_cap: usize,
+    data: *mut u8,
+    data_len: usize,
+    out: *mut FFmpegRsWebvttMp4EncodeResult,
+) -> c_int {
+    let (text, cues, out) = match (in_bytes(text, text_len), in_array(cues, n_cues), out_ref(out)) {
+        (Some(t), Some(c), Some(o)) => (t, c, o),
+        _ => return EINVAL,
+    };
+    let samples = out_array(samples, samples_cap);
+    let mut w = Writer::new(out_array(data, data_len));
+    let mut res = FFmpegRsWebvttMp4EncodeResult::default();
+    let r = encode(&mut w, text, cues, start_ms, end_ms, |s| {
+        if let Some(slot) = samples.get_mut(res.n_samples_total) {
+            *slot = s;
+            res.n_samples_written += 1;
+        }
+        res.n_samples_total += 1;
+    });
+    if let Err(e) = r {
+        return e;
+    }
+    res.truncated = (res.n_samples_total > res.n_samples_written) as c_int;
+    res.data_required = w.len();
+    *out = res;
+    w.finish_bytes(core::ptr::null_mut())
+}
+
+// Extracts the cues of one sample spanning `[start_ms, end_ms)`. Cue spans
+// are offsets into `data`. Returns 0, -EINVAL on invalid arguments or -2 on a
+// malformed sample.
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_webvtt_mp4_decode_sample(
+    data: *const u8,
+    data_len: usize,
+    start_ms: i64,
+    end_ms: i64,
+    out: *mut FFmpegRsWebvttMp4DecodeResult,
+    cues: *mut FFmpegRsWebvttCue,
+    cues_cap: usize,
+) -> c_int {
+    let (data, out) = match (in_bytes(data, data_len), out_ref(out)) {
+        (Some(d), Some(o)) => (d, o),
+        _ => return EINVAL,
+    };
+    let cues = out_array(cues, cues_cap);
+    let mut n = 0usize;
+    match decode(data, start_ms, end_ms, |c| {
+        if let Some(slot) = cues.get_mut(n) {
+            *slot = c;
+        }
+        n += 1;
+    }) {
+        Ok(res) => {
+            *out = res;
+            0
+        }
+        Err(e) => e,
+    }
+}
+
+// Writes a `wvtt` sample entry with its `vttC` configuration (the WebVTT
+// header, e.g. "WEBVTT\n") and an optional `vlab` source label. Binary
+// output: `*out_required` is the exact entry size. Returns 0, -EINVAL or
+// -ENOSPC.
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_webvtt_mp4_write_sample_entry(
+    config: *const c_char,
+    config_len: usize,
+    label: *const c_char,
+    label_len: usize,
+    dst: *mut u8,
+    dst_len: usize,
+    out_required: *mut usize,
+) -> c_int {
+    let config = match in_str(config, config_len) {
+        Some(c) => c,
+        None => return EINVAL,
+    };
+    let label = in_str(label, label_len).unwrap_or(b"");
+    let mut w = Writer::new(out_array(dst, dst_len));
+    let vlab = if label.is_empty() { 0 } else { 8 + label.len() };
+    let r = (|| {
+        box_header(&mut w, 8 + 8 + 8 + config.len() + vlab, b"wvtt")?;
+        // SampleEntry: reserved, data_reference_index = 1.
+        w.bytes(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        leaf_box(&mut w, b"vttC", config)?;
+        if !label.is_empty() {
+            leaf_box(&mut w, b"vlab", label)?;
+        }
+        Ok(())
+    })();
+    if let Err(e) = r {
+        return e;
+    }
+    w.finish_bytes(out_required)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::writer::ENOSPC;
+
+    fn cue(text: &[u8], start_ms: i64, end_ms: i64, id: &[u8], payload: &[u8]) -> FFmpegRsWebvttCue {
+        let find = |s: &[u8]| if s.is_empty() { 0 } else { text.windows(s.len()).position(|w| w == s).unwrap_or(0) };
+        FFmpegRsWebvttCue {
+            start_ms,
+            end_ms,
+            payload_offset: find(payload),
+            payload_len: payload.len(),
+            identifier_offset: find(id),
+            identifier_len: id.len(),
+            settings_offset: 0,
+            settings_len: 0,
+        }
+    }
+
+    #[test]
+    fn encodes_and_decodes_samples() {
+        let text = b"one\nHello\n\nWorld\n";
+        let cues = [cue(text, 1000, 3000, b"one", b"Hello"), cue(text, 2000, 4000, b"", b"World\n")];
+        let mut samples = [FFmpegRsWebvttMp4Sample { start_ms: 0, duration_ms: 0, data_offset: 0, data_size: 0, n_cues: 0 }; 8];
+        let mut data = [0u8; 256];
+        let mut out = FFmpegRsWebvttMp4EncodeResult::default();
+        let r = ffmpeg_rs_webvtt_mp4_encode(
+            text.as_ptr(),
+            text.len(),
+            cues.as_ptr(),
+            cues.len(),
+            0,
+            5000,
+            samples.as_mut_ptr(),
+            samples.len(),
+            data.as_mut_ptr(),
+            data.len(),
+            &mut out,
+        );
+        assert_eq!(r, 0);
+        assert_eq!((out.n_samples_total, out.truncated), (5, 0));
+        let timing: [(i64, i64, usize); 5] = core::array::from_fn(|i| (samples[i].start_ms, samples[i].duration_ms, samples[i].n_cues));
+        assert_eq!(timing, [(0, 1000, 0), (1000, 1000, 1), (2000, 1000, 2), (3000, 1000, 1), (4000, 1000, 0)]);
+        assert_eq!(&data[..8], b"\0\0\0\x08vtte");
+        let s1 = samples[1];
+        assert_eq!(
+            &data[s1.data_offset..s1.data_offset + s1.data_size],
+            b"\0\0\0\x20vttc\0\0\0\x0bidenone\0\0\0\x0dpaylHello"
+        );
+        assert_eq!(out.data_required, samples[4].data_offset + 8);
+
+        let s2 = samples[2];
+        let sample = &data[s2.data_offset..s2.data_offset + s2.data_size];
+        let mut dec = FFmpegRsWebvttMp4DecodeResult::default();
+        let mut got = [cues[0]; 4];
+        assert_eq!(ffmpeg_rs_webvtt_mp4_decode_sample(sample.as_ptr(), sample.len(), 2000, 3000, &mut dec, got.as_mut_ptr(), 4), 0);
+        assert_eq!((dec.n_cues, dec.empty), (2, 0));
+        assert_eq!(&sample[got[0].identifier_offset..][..got[0].identifier_len], b"one");
+        assert_eq!(&sample[got[1].payload_offset..][..got[1].payload_len], b"World");
+        assert_eq!((got[1].start_ms, got[1].end_ms, got[1].identifier_len), (2000, 3000, 0));
+
+        assert_eq!(ffmpeg_rs_webvtt_mp4_decode_sample(data.as_ptr(), 8, 0, 1000, &mut dec, got.as_mut_ptr(), 4), 0);
+        assert_eq!((dec.n_cues, dec.empty), (0, 1));
+        for bad in [&b"\0\0\0\x09vtte"[..], b"\0\0\0\x08vttc", b"\0\0\0\x07vtte"] {
+            assert_eq!(ffmpeg_rs_webvtt_mp4_decode_sample(bad.as_ptr(), bad.len(), 0, 1, &mut dec, got.as_mut_ptr(), 4), -2);
+        }
+
+        let r = ffmpeg_rs_webvtt_mp4_encode(
+            text.as_ptr(),
+            text.len(),
+            cues.as_ptr(),
+            cues.len(),
+            1000,
+            -1,
+            samples.as_mut_ptr(),
+            1,
+            data.as_mut_ptr(),
+            16,
+            &mut out,
+        );
+        assert_eq!((r, out.n_samples_total, out.n_samples_written, out.truncated), (ENOSPC, 3, 1, 1));
+    }
+
+    #[test]
+    fn writes_sample_entry() {
+        let mut buf = [0u8; 64];
+        let mut req = 0usize;
+        let cfg = b"WEBVTT\n";
+        let r = ffmpeg_rs_webvtt_mp4_write_sample_entry(cfg.as_ptr() as *const c_char, cfg.len(), b"en".as_ptr() as *const c_char, 2, buf.as_mut_ptr(), buf.len(), &mut req);
+        assert_eq!((r, req), (0, 16 + 15 + 10));
+        assert_eq!(&buf[..req], b"\0\0\0\x29wvtt\0\0\0\0\0\0\0\x01\0\0\0\x0fvttCWEBVTT\n\0\0\0\x0avlaben");
+        let r = ffmpeg_rs_webvtt_mp4_write_sample_entry(cfg.as_ptr() as *const c_char, cfg.len(), core::ptr::null(), 0, buf.as_mut_ptr(), 4, &mut req);
+        assert_eq!((r, req), (ENOSPC, 31));
+    }
+}
@@ -0,0 +1,284 @@
+use core::ffi::c_int;
+
+use crate::text::{is_space, trim_span};
+use crate::writer::{in_bytes, out_array, out_ref, EINVAL};
+use crate::{blocks, first_line, has_signature, is_ws, parse_cue_block, FFmpegRsWebvttCue};
+
+/// The cue payload looks like a JSON object or array.
+pub const FFMPEG_RS_WEBVTT_RECORD_JSON: u32 = 1;
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FFmpegRsWebvttRecordKind {
+    Cue = 0,
+    Note = 1,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsWebvttRecord {
+    /// FFmpegRsWebvttRecordKind.
+    pub kind: u32,
+    /// FFMPEG_RS_WEBVTT_RECORD_* flags.
+    pub flags: u32,
+    /// Timing and spans of a Cue record; zeroed for notes.
+    pub cue: FFmpegRsWebvttCue,
+    /// Cues: the payload trimmed of surrounding whitespace. Notes: the
+    /// comment text after `NOTE`.
+    pub text_offset: usize,
+    pub text_len: usize,
+    /// Chapter title of a cue: the trimmed payload, or for a JSON object
+    /// the top-level `"title"` string without its quotes (escapes are left
+    /// as is). 0 length for notes and JSON without a title.
+    pub title_offset: usize,
+    pub title_len: usize,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FFmpegRsWebvttRecordsResult {
+    /// Totals; records past the capacity are counted, not stored.
+    pub n_records: usize,
+    pub n_cues: usize,
+    pub n_notes: usize,
+}
+
+// Span of `s[start..end]` without surrounding whitespace, as offset and
+// length.
+fn trimmed(s: &[u8], start: usize, end: usize) -> (usize, usize) {
+    let (start, end) = trim_span(s, start, end);
+    (start, end - start)
+}
+
+fn is_json_like(s: &[u8]) -> bool {
+    matches!((s.first(), s.last()), (Some(b'{'), Some(b'}')) | (Some(b'['), Some(b']')))
+}
+
+fn skip_space(s: &[u8], mut i: usize) -> usize {
+    while i < s.len() && is_space(s[i]) {
+        i += 1;
+    }
+    i
+}
+
+// JSON string whose opening quote is `s[i]`: the bounds of its content and
+// the index past the closing quote.
+fn json_string(s: &[u8], i: usize) -> Option<(usize, usize, usize)> {
+    let mut j = i + 1;
+    while j < s.len() {
+        match s[j] {
+            b'"' => return Some((i + 1, j, j + 1)),
+            b'\\' => j += 2,
+            _ => j += 1,
+        }
+    }
+    None
+}
+
+// Index of the `,` or closing bracket ending the JSON value at `s[i]`.
+// Nesting is counted rather than recursed into.
+fn skip_json_value(s: &[u8], mut i: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    while i < s.len() {
+        match s[i] {
+            b'"' => {
+                i = json_string(s, i)?.2;
+                continue;
+            }
+            b'{' | b'[' => depth += 1,
+            b',' | b'}' | b']' if depth == 0 => return Some(i),
+            b'}' | b']' => depth -= 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+// Content bounds of the top-level `"title"` string of the JSON object `s`.
+// Other members are skipped; malformed objects have no title.
+fn json_title(s: &[u8]) -> Option<(usize, usize)> {
+    if s.first() != Some(&b'{') {
+        return None;
+    }
+    let mut i = 1;
+    loop {
+        i = skip_space(s, i);
+        if s.get(i) != Some(&b'"') {
+            return None;
+        }
+        let (key_start, key_end, next) = json_string(s, i)?;
+        i = skip_space(s, next);
+        if s.get(i) != Some(&b':') {
+            return None;
+        }
+        i = skip_space(s, i + 1);
+        if &s[key_start..key_end] == b"title" && s.get(i) == Some(&b'"') {
+            let (start, end, _) = json_string(s, i)?;
+            return Some((start, end));
+        }
+        i = skip_json_value(s, i)?;
+        if s[i] != b',' {
+            return None;
+        }
+        i += 1;
+    }
+}
+
+// A comment block starts with "NOTE" followed by whitespace or a newline.
+pub(crate) fn is_note(line1: &[u8]) -> bool {
+    match line1.strip_prefix(b"NOTE") {
+        Some(rest) => rest.first().is_none_or(|&b| is_ws(b)),
+        None => false,
+    }
+}
+
+// Walks cue and NOTE blocks in document order. Other header blocks and
+// malformed cues are skipped as in parse_document().
+pub(crate) fn parse_records(data: &[u8], mut emit: impl FnMut(FFmpegRsWebvttRecord)) -> Result<(), c_int> {
+    if !has_signature(data) {
+        return Err(-2);
+    }
+    for (pos, block) in blocks(data).skip(1) {
+        let (line1, _) = first_line(block);
+        if is_note(line1) {
+            let (text_offset, text_len) = trimmed(data, pos + 4, pos + block.len());
+            emit(FFmpegRsWebvttRecord {
+                kind: FFmpegRsWebvttRecordKind::Note as u32,
+                flags: 0,
+                cue: FFmpegRsWebvttCue {
+                    start_ms: 0,
+                    end_ms: 0,
+                    payload_offset: 0,
+                    payload_len: 0,
+                    identifier_offset: 0,
+                    identifier_len: 0,
+                    settings_offset: 0,
+                    settings_len: 0,
+                },
+                text_offset,
+                text_len,
+                title_offset: 0,
+                title_len: 0,
+            });
+            continue;
+        }
+        if let Some(cue) = parse_cue_block(pos, block) {
+            let (text_offset, text_len) = trimmed(data, cue.payload_offset, cue.payload_offset + cue.payload_len);
+            let text = &data[text_offset..text_offset + text_len];
+            let json = is_json_like(text);
+            let (title_offset, title_len) = match json_title(text) {
+                Some((a, b)) => (text_offset + a, b - a),
+                None if json => (0, 0),
+                None => (text_offset, text_len),
+            };
+            emit(FFmpegRsWebvttRecord {
+                kind: FFmpegRsWebvttRecordKind::Cue as u32,
+                flags: if json { FFMPEG_RS_WEBVTT_RECORD_JSON } else { 0 },
+                cue,
+                text_offset,
+                text_len,
+                title_offset,
+                title_len,
+            });
+        }
+    }
+    Ok(())
+}
+
+// Metadata/chapter mode of ffmpeg_rs_webvtt_parse(): returns NOTE blocks as
+// records alongside cues, flags JSON payloads and exposes the title of a
+// `kind=chapters` cue, plain or from a JSON object. Returns 0, -EINVAL on
+// invalid arguments or -2 without a WEBVTT signature.
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_webvtt_parse_records(
+    text: *const u8,
+    text_len: usize,
+    out: *mut FFmpegRsWebvttRecordsResult,
+    records: *mut FFmpegRsWebvttRecord,
+    records_cap: usize,
+) -> c_int {
+    let (data, out) = match (in_bytes(text, text_len), out_ref(out)) {
+        (Some(d), Some(o)) => (d, o),
+        _ => return EINVAL,
+    };
+    let records = out_array(records, records_cap);
+    let mut res = FFmpegRsWebvttRecordsResult::default();
+    let r = parse_records(data, |rec| {
+        if let Some(slot) = records.get_mut(res.n_records) {
+            *slot = rec;
+        }
+        res.n_records += 1;
+        if rec.kind == FFmpegRsWebvttRecordKind::Note as u32 {
+            res.n_notes += 1;
+        } else {
+            res.n_cues += 1;
+        }
+    });
+    if let Err(e) = r {
+        return e;
+    }
+    *out = res;
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_notes_and_flags_json() {
+        let doc = b"WEBVTT - chapters\n\nNOTE\nexported by packager\nv2\n\n\
+chapter-1\n00:00.000 --> 01:00.000\n  Opening titles \n\n\
+NOTE keep me\n\n\
+01:00.000 --> 02:00.000\n{\"title\": \"Act 1\",\n \"id\": 7}\n\n";
+        let mut recs = [FFmpegRsWebvttRecord {
+            kind: 0,
+            flags: 0,
+            cue: FFmpegRsWebvttCue {
+                start_ms: 0,
+                end_ms: 0,
+                payload_offset: 0,
+                payload_len: 0,
+                identifier_offset: 0,
+                identifier_len: 0,
+                settings_offset: 0,
+                settings_len: 0,
+            },
+            text_offset: 0,
+            text_len: 0,
+            title_offset: 0,
+            title_len: 0,
+        }; 4];
+        let mut out = FFmpegRsWebvttRecordsResult::default();
+        assert_eq!(ffmpeg_rs_webvtt_parse_records(doc.as_ptr(), doc.len(), &mut out, recs.as_mut_ptr(), recs.len()), 0);
+        assert_eq!((out.n_records, out.n_cues, out.n_notes), (4, 2, 2));
+        let text = |r: &FFmpegRsWebvttRecord| &doc[r.text_offset..r.text_offset + r.text_len];
+        let title = |r: &FFmpegRsWebvttRecord| &doc[r.title_offset..r.title_offset + r.title_len];
+
+        assert_eq!(recs[0].kind, FFmpegRsWebvttRecordKind::Note as u32);
+        assert_eq!((text(&recs[0]), recs[0].title_len), (&b"exported by packager\nv2"[..], 0));
+        assert_eq!((recs[1].kind, recs[1].flags, recs[1].cue.end_ms), (FFmpegRsWebvttRecordKind::Cue as u32, 0, 60_000));
+        assert_eq!((text(&recs[1]), title(&recs[1])), (&b"Opening titles"[..], &b"Opening titles"[..]));
+        assert_eq!(text(&recs[2]), b"keep me");
+        assert_eq!((recs[3].flags, recs[3].cue.start_ms), (FFMPEG_RS_WEBVTT_RECORD_JSON, 60_000));
+        assert_eq!(text(&recs[3]), b"{\"title\": \"Act 1\",\n \"id\": 7}");
+        assert_eq!(title(&recs[3]), b"Act 1");
+
+        // "NOTEBOOK" is not a comment.
+        let doc = b"WEBVTT\n\nNOTEBOOK\n00:01.000 --> 00:02.000\nx\n";
+        assert_eq!(ffmpeg_rs_webvtt_parse_records(doc.as_ptr(), doc.len(), &mut out, recs.as_mut_ptr(), recs.len()), 0);
+        assert_eq!((out.n_cues, out.n_notes), (1, 0));
+    }
+
+    #[test]
+    fn finds_top_level_json_title() {
+        let title = |s: &'static [u8]| json_title(s).map(|(a, b)| &s[a..b]);
+        assert_eq!(title(b"{\"title\":\"A\"}"), Some(&b"A"[..]));
+        assert_eq!(title(b"{ \"meta\": {\"title\": \"x\"}, \"tags\": [\"}\", 1], \"title\" : \"Act \\\"2\\\"\" }"), Some(&b"Act \\\"2\\\""[..]));
+        assert_eq!(title(b"{\"title\": 7}"), None);
+        assert_eq!(title(b"{\"id\": 1}"), None);
+        assert_eq!(title(b"[\"title\", \"x\"]"), None);
+        assert_eq!(title(b"{\"a\" 1, \"title\": \"x\"}"), None);
+    }
+}
@@ -0,0 +1,283 @@
+use core::ffi::{c_char, c_int};
+
+use crate::writer::{in_array, in_bytes, in_ref, in_str, out_buf, Writer, EINVAL};
+use crate::FFmpegRsWebvttCue;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsWebvttSegmentParams {
+    /// Segment interval `[start_ms, end_ms)` on the cue timeline.
+    pub start_ms: i64,
+    pub end_ms: i64,
+    /// `X-TIMESTAMP-MAP=MPEGTS:<mpegts>,LOCAL:<local_ms>`; negative `mpegts`
+    /// omits the header.
+    pub mpegts: i64,
+    pub local_ms: i64,
+    /// Clamp the timing of boundary-spanning cues to the segment instead of
+    /// repeating their full timing.
+    pub clip: c_int,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsWebvttHlsSegment {
+    pub duration_ms: i64,
+    pub uri: *const c_char,
+    pub uri_len: usize,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FFmpegRsWebvttHlsPlaylist {
+    pub version: c_int,
+    /// 0 to derive it from the longest segment.
+    pub target_duration: i64,
+    pub media_sequence: i64,
+    /// 0 = none, 1 = EVENT, 2 = VOD.
+    pub playlist_type: u32,
+    pub endlist: c_int,
+}
+
+fn span(text: &[u8], off: usize, len: usize) -> Result<&[u8], c_int> {
+    let end = off.checked_add(len).ok_or(EINVAL)?;
+    text.get(off..end).ok_or(EINVAL)
+}
+
+// A cue belongs to every segment it overlaps; zero-length cues belong to the
+// segment containing their start.
+fn overlaps(cue: &FFmpegRsWebvttCue, start_ms: i64, end_ms: i64) -> bool {
+    if cue.start_ms == cue.end_ms {
+        return start_ms <= cue.start_ms && cue.start_ms < end_ms;
+    }
+    cue.start_ms < end_ms && cue.end_ms > start_ms
+}
+
+fn write_segment(
+    w: &mut Writer,
+    text: &[u8],
+    cues: &[FFmpegRsWebvttCue],
+    p: &FFmpegRsWebvttSegmentParams,
+) -> Result<(), c_int> {
+    if p.end_ms < p.start_ms || p.start_ms < 0 {
+        return Err(EINVAL);
+    }
+    w.bytes(b"WEBVTT\n");
+    if p.mpegts >= 0 {
+        w.bytes(b"X-TIMESTAMP-MAP=MPEGTS:");
+        w.uint(p.mpegts as u64);
+        w.bytes(b",LOCAL:");
+        w.timestamp_ms(p.local_ms)?;
+        w.byte(b'\n');
+    }
+    for cue in cues.iter().filter(|c| overlaps(c, p.start_ms, p.end_ms)) {
+        if cue.end_ms < cue.start_ms {
+            return Err(EINVAL);
+        }
+        let (start, end) = if p.clip != 0 {
+            (cue.start_ms.max(p.start_ms), cue.end_ms.min(p.end_ms))
+        } else {
+            (cue.start_ms, cue.end_ms)
+        };
+        w.byte(b'\n');
+        let id = span(text, cue.identifier_offset, cue.identifier_len)?;
+        if !id.is_empty() {
+            w.bytes(id);
+            w.byte(b'\n');
+        }
+        w.timestamp_ms(start)?;
+        w.bytes(b" --> ");
+        w.timestamp_ms(end)?;
+        let settings = span(text, cue.settings_offset, cue.settings_len)?;
+        if !settings.is_empty() {
+            w.byte(b' ');
+            w.bytes(settings);
+        }
+        w.byte(b'\n');
+        let mut payload = span(text, cue.payload_offset, cue.payload_len)?;
+        while let Some((b'\n' | b'\r', rest)) = payload.split_last() {
+            payload = rest;
+        }
+        if !payload.is_empty() {
+            w.bytes(payload);
+            w.byte(b'\n');
+        }
+    }
+    Ok(())
+}
+
+fn write_playlist(
+    w: &mut Writer,
+    pl: &FFmpegRsWebvttHlsPlaylist,
+    segs: &[FFmpegRsWebvttHlsSegment],
+) -> Result<(), c_int> {
+    if pl.version < 3 || pl.media_sequence < 0 || pl.target_duration < 0 || pl.playlist_type > 2 {
+        return Err(EINVAL);
+    }
+    let mut target = pl.target_duration as u64;
+    if target == 0 {
+        for s in segs {
+            if s.duration_ms < 0 {
+                return Err(EINVAL);
+            }
+            target = target.max((s.duration_ms as u64).div_ceil(1000));
+        }
+    }
+
+    w.bytes(b"#EXTM3U\n#EXT-X-VERSION:");
+    w.uint(pl.version as u64);
+    w.bytes(b"\n#EXT-X-TARGETDURATION:");
+    w.uint(target);
+    w.bytes(b"\n#EXT-X-MEDIA-SEQUENCE:");
+    w.uint(pl.media_sequence as u64);
+    w.byte(b'\n');
+    match pl.playlist_type {
+        1 => w.bytes(b"#EXT-X-PLAYLIST-TYPE:EVENT\n"),
+        2 => w.bytes(b"#EXT-X-PLAYLIST-TYPE:VOD\n"),
+        _ => {}
+    }
+    for s in segs {
+        let uri = in_str(s.uri, s.uri_len).ok_or(EINVAL)?;
+        if s.duration_ms < 0 || uri.is_empty() || uri.contains(&b'\n') {
+            return Err(EINVAL);
+        }
+        w.bytes(b"#EXTINF:");
+        w.seconds_fixed6_ms(s.duration_ms);
+        w.bytes(b",\n");
+        w.bytes(uri);
+        w.byte(b'\n');
+    }
+    if pl.endlist != 0 {
+        w.bytes(b"#EXT-X-ENDLIST\n");
+    }
+    Ok(())
+}
+
+// Writes the WebVTT document for one HLS subtitle segment: the header with
+// X-TIMESTAMP-MAP followed by every cue overlapping the segment. Cues
+// spanning a boundary are repeated in each segment they touch. `cues` come
+// from ffmpeg_rs_webvtt_parse() over `text`.
+// Returns 0 on success, -EINVAL on bad arguments, -ENOSPC if `dst_len` is
+// smaller than `*out_required` (size including NUL).
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_webvtt_write_segment(
+    text: *const u8,
+    text_len: usize,
+    cues: *const FFmpegRsWebvttCue,
+    n_cues: usize,
+    params: *const FFmpegRsWebvttSegmentParams,
+    dst: *mut c_char,
+    dst_len: usize,
+    out_required: *mut usize,
+) -> c_int {
+    let (text, cues, p) = match (in_bytes(text, text_len), in_array(cues, n_cues), in_ref(params)) {
+        (Some(t), Some(c), Some(p)) => (t, c, p),
+        _ => return EINVAL,
+    };
+    let mut w = Writer::new(out_buf(dst, dst_len));
+    if let Err(e) = write_segment(&mut w, text, cues, p) {
+        return e;
+    }
+    w.finish(out_required)
+}
+
+// Writes the subtitle media playlist listing the segments. Same return
+// convention as ffmpeg_rs_webvtt_write_segment().
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_webvtt_write_hls_playlist(
+    playlist: *const FFmpegRsWebvttHlsPlaylist,
+    segments: *const FFmpegRsWebvttHlsSegment,
+    n_segments: usize,
+    dst: *mut c_char,
+    dst_len: usize,
+    out_required: *mut usize,
+) -> c_int {
+    let (pl, segs) = match (in_ref(playlist), in_array(segments, n_segments)) {
+        (Some(pl), Some(s)) => (pl, s),
+        _ => return EINVAL,
+    };
+    let mut w = Writer::new(out_buf(dst, dst_len));
+    if let Err(e) = write_playlist(&mut w, pl, segs) {
+        return e;
+    }
+    w.finish(out_required)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ffmpeg_rs_webvtt_parse, FFmpegRsWebvttParseResult};
+
+    const DOC: &[u8] = b"WEBVTT\n\nintro\n00:00:01.000 --> 00:00:03.000 align:start\nHello\n\n00:00:05.500 --> 00:00:07.000\nSpans\nboundary\n\n00:00:08.000 --> 00:00:09.000\nLater\n";
+
+    fn cues() -> [FFmpegRsWebvttCue; 3] {
+        let mut out = FFmpegRsWebvttParseResult { n_cues: 0 };
+        let mut cues = [FFmpegRsWebvttCue {
+            start_ms: 0,
+            end_ms: 0,
+            payload_offset: 0,
+            payload_len: 0,
+            identifier_offset: 0,
+            identifier_len: 0,
+            settings_offset: 0,
+            settings_len: 0,
+        }; 3];
+        assert_eq!(ffmpeg_rs_webvtt_parse(DOC.as_ptr(), DOC.len(), &mut out, cues.as_mut_ptr(), 3), 0);
+        assert_eq!(out.n_cues, 3);
+        cues
+    }
+
+    fn segment<'a>(cues: &[FFmpegRsWebvttCue], p: &FFmpegRsWebvttSegmentParams, buf: &'a mut [i8]) -> &'a [u8] {
+        let mut req = 0usize;
+        let r = ffmpeg_rs_webvtt_write_segment(DOC.as_ptr(), DOC.len(), cues.as_ptr(), cues.len(), p, buf.as_mut_ptr(), buf.len(), &mut req);
+        assert_eq!(r, 0);
+        unsafe { core::slice::from_raw_parts(buf.as_ptr() as *const u8, req - 1) }
+    }
+
+    #[test]
+    fn splits_and_duplicates_cues() {
+        let cues = cues();
+        let mut buf = [0i8; 512];
+        let p = FFmpegRsWebvttSegmentParams { start_ms: 0, end_ms: 6000, mpegts: 900000, local_ms: 0, clip: 0 };
+        assert_eq!(
+            segment(&cues, &p, &mut buf),
+            &b"WEBVTT\nX-TIMESTAMP-MAP=MPEGTS:900000,LOCAL:00:00:00.000\n\
+\nintro\n00:00:01.000 --> 00:00:03.000 align:start\nHello\n\
+\n00:00:05.500 --> 00:00:07.000\nSpans\nboundary\n"[..]
+        );
+
+        let p = FFmpegRsWebvttSegmentParams { start_ms: 6000, end_ms: 12000, mpegts: 900000, local_ms: 0, clip: 1 };
+        assert_eq!(
+            segment(&cues, &p, &mut buf),
+            &b"WEBVTT\nX-TIMESTAMP-MAP=MPEGTS:900000,LOCAL:00:00:00.000\n\
+\n00:00:06.000 --> 00:00:07.000\nSpans\nboundary\n\
+\n00:00:08.000 --> 00:00:09.000\nLater\n"[..]
+        );
+
+        let p = FFmpegRsWebvttSegmentParams { start_ms: 12000, end_ms: 18000, mpegts: -1, local_ms: 0, clip: 0 };
+        assert_eq!(segment(&cues, &p, &mut buf), b"WEBVTT\n");
+    }
+
+    #[test]
+    fn writes_subtitle_playlist() {
+        let a = b"sub_0.vtt";
+        let b = b"sub_1.vtt";
+        let segs = [
+            FFmpegRsWebvttHlsSegment { duration_ms: 6000, uri: a.as_ptr() as *const c_char, uri_len: a.len() },
+            FFmpegRsWebvttHlsSegment { duration_ms: 4500, uri: b.as_ptr() as *const c_char, uri_len: b.len() },
+        ];
+        let pl = FFmpegRsWebvttHlsPlaylist { version: 3, target_duration: 0, media_sequence: 0, playlist_type: 2, endlist: 1 };
+        let mut buf = [0i8; 256];
+        let mut req = 0usize;
+        let r = ffmpeg_rs_webvtt_write_hls_playlist(&pl, segs.as_ptr(), segs.len(), buf.as_mut_ptr(), buf.len(), &mut req);
+        assert_eq!(r, 0);
+        let s = unsafe { core::slice::from_raw_parts(buf.as_ptr() as *const u8, req - 1) };
+        assert_eq!(
+            s,
+            &b"#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:6\n#EXT-X-MEDIA-SEQUENCE:0\n#EXT-X-PLAYLIST-TYPE:VOD\n\
+#EXTINF:6.000000,\nsub_0.vtt\n#EXTINF:4.500000,\nsub_1.vtt\n#EXT-X-ENDLIST\n"[..]
+        );
+
+        let r = ffmpeg_rs_webvtt_write_hls_playlist(&pl, segs.as_ptr(), segs.len(), core::ptr::null_mut(), 0, &mut req);
+        assert_eq!(r, crate::writer::ENOSPC);
+    }
+}
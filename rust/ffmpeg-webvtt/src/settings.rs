@@ -0,0 +1,299 @@
+use core::ffi::c_int;
+
+use crate::is_ws;
+use crate::writer::{in_bytes, out_array, out_ref, EINVAL};
+
+/// Value of `line`, `position` or `size` when the setting is absent.
+pub const FFMPEG_RS_WEBVTT_AUTO: f64 = -1.0;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FFmpegRsWebvttCueSettings {
+    /// 0 = horizontal, 1 = `rl`, 2 = `lr`.
+    pub vertical: u32,
+    /// `line`: 0 = auto, 1 = line number (may be negative), 2 = percentage.
+    pub line_kind: u32,
+    pub line: f64,
+    /// 0 = start, 1 = center, 2 = end.
+    pub line_align: u32,
+    /// Percentage or FFMPEG_RS_WEBVTT_AUTO.
+    pub position: f64,
+    /// 0 = auto, 1 = line-left, 2 = center, 3 = line-right.
+    pub position_align: u32,
+    /// Percentage; 100 when absent.
+    pub size: f64,
+    /// 0 = start, 1 = center, 2 = end, 3 = left, 4 = right.
+    pub align: u32,
+    /// Region identifier span, relative to the settings string; 0 length if
+    /// none.
+    pub region_offset: usize,
+    pub region_len: usize,
+    /// Number of warnings, including any that did not fit the caller array.
+    pub n_warnings: usize,
+}
+
+impl Default for FFmpegRsWebvttCueSettings {
+    fn default() -> Self {
+        FFmpegRsWebvttCueSettings {
+            vertical: 0,
+            line_kind: 0,
+            line: FFMPEG_RS_WEBVTT_AUTO,
+            line_align: 0,
+            position: FFMPEG_RS_WEBVTT_AUTO,
+            position_align: 0,
+            size: 100.0,
+            align: 1,
+            region_offset: 0,
+            region_len: 0,
+            n_warnings: 0,
+        }
+    }
+}
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FFmpegRsWebvttSettingWarningKind {
+    /// Not of the form `name:value`.
+    Malformed = 0,
+    UnknownName = 1,
+    InvalidValue = 2,
+    /// `region` dropped because `vertical`, `line` or `size` is also set.
+    RegionIgnored = 3,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FFmpegRsWebvttSettingWarning {
+    /// FFmpegRsWebvttSettingWarningKind.
+    pub kind: u32,
+    /// The offending setting, relative to the settings string.
+    pub offset: usize,
+    pub len: usize,
+}
+
+fn keyword(v: &[u8], words: &[&[u8]]) -> Option<u32> {
+    words.iter().position(|w| *w == v).map(|i| i as u32)
+}
+
+// Digits with an optional fraction, as f64. `-` is only allowed when
+// `signed`.
+fn parse_real(s: &[u8], signed: bool) -> Option<f64> {
+    let (neg, s) = match s.split_first() {
+        Some((b'-', rest)) if signed => (true, rest),
+        _ => (false, s),
+    };
+    let (int, frac) = match s.iter().position(|&b| b == b'.') {
+        Some(dot) => (&s[..dot], Some(&s[dot + 1..])),
+        None => (s, None),
+    };
+    if int.is_empty() || !int.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    let mut v = 0f64;
+    for &b in int {
+        v = v * 10.0 + (b - b'0') as f64;
+    }
+    if let Some(frac) = frac {
+        if frac.is_empty() || !frac.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        let mut scale = 1f64;
+        for &b in frac {
+            scale /= 10.0;
+            v += (b - b'0') as f64 * scale;
+        }
+    }
+    Some(if neg { -v } else { v })
+}
+
+// `N%` with 0 <= N <= 100.
+pub(crate) fn parse_percentage(s: &[u8]) -> Option<f64> {
+    let v = parse_real(s.strip_suffix(b"%")?, false)?;
+    if v > 100.0 {
+        return None;
+    }
+    Some(v)
+}
+
+pub(crate) fn split_comma(v: &[u8]) -> (&[u8], Option<&[u8]>) {
+    match v.iter().position(|&b| b == b',') {
+        Some(i) => (&v[..i], Some(&v[i + 1..])),
+        None => (v, None),
+    }
+}
+
+// Applies one `name:value` setting. Fails without touching `out` if the
+// value is invalid, so later valid settings still win and earlier ones stay.
+fn apply(out: &mut FFmpegRsWebvttCueSettings, name: &[u8], value: &[u8], value_offset: usize) -> Result<(), FFmpegRsWebvttSettingWarningKind> {
+    use FFmpegRsWebvttSettingWarningKind::*;
+    match name {
+        b"vertical" => out.vertical = keyword(value, &[b"rl", b"lr"]).ok_or(InvalidValue)? + 1,
+        b"line" => {
+            let (pos, align) = split_comma(value);
+            let align = match align {
+                Some(a) => keyword(a, &[b"start", b"center", b"end"]).ok_or(InvalidValue)?,
+                None => 0,
+            };
+            let (kind, line) = if pos.last() == Some(&b'%') {
+                (2, parse_percentage(pos).ok_or(InvalidValue)?)
+            } else {
+                (1, parse_real(pos, true).ok_or(InvalidValue)?)
+            };
+            out.line_kind = kind;
+            out.line = line;
+            out.line_align = align;
+        }
+        b"position" => {
+            let (pos, align) = split_comma(value);
+            let align = match align {
+                Some(a) => keyword(a, &[b"line-left", b"center", b"line-right"]).ok_or(InvalidValue)? + 1,
+                None => 0,
+            };
+            out.position = parse_percentage(pos).ok_or(InvalidValue)?;
+            out.position_align = align;
+        }
+        b"size" => out.size = parse_percentage(value).ok_or(InvalidValue)?,
+        b"align" => out.align = keyword(value, &[b"start", b"center", b"end", b"left", b"right"]).ok_or(InvalidValue)?,
+        b"region" => {
+            out.region_offset = value_offset;
+            out.region_len = value.len();
+        }
+        _ => return Err(UnknownName),
+    }
+    Ok(())
+}
+
+// Parses a cue settings string per the WebVTT "parse the WebVTT cue
+// settings" algorithm. Invalid settings are skipped; `warn` receives each.
+pub(crate) fn parse_settings(s: &[u8], mut warn: impl FnMut(FFmpegRsWebvttSettingWarning)) -> FFmpegRsWebvttCueSettings {
+    let mut out = FFmpegRsWebvttCueSettings::default();
+    let mut region_setting = (0usize, 0usize);
+    let mut pos = 0usize;
+    while pos < s.len() {
+        if is_ws(s[pos]) || s[pos] == b'\n' || s[pos] == b'\r' {
+            pos += 1;
+            continue;
+        }
+        let start = pos;
+        while pos < s.len() && !(is_ws(s[pos]) || s[pos] == b'\n' || s[pos] == b'\r') {
+            pos += 1;
+        }
+        let token = &s[start..pos];
+        let res = match token.iter().position(|&b| b == b':') {
+            Some(colon) if colon > 0 && colon + 1 < token.len() => {
+                let (name, value) = (&token[..colon], &token[colon + 1..]);
+                if name == b"region" {
+                    region_setting = (start, token.len());
+                }
+                apply(&mut out, name, value, start + colon + 1)
+            }
+            _ => Err(FFmpegRsWebvttSettingWarningKind::Malformed),
+        };
+        if let Err(kind) = res {
+            out.n_warnings += 1;
+            warn(FFmpegRsWebvttSettingWarning { kind: kind as u32, offset: start, len: token.len() });
+        }
+    }
+    // A cue with its own writing direction, line or size cannot be placed in
+    // a region.
+    if out.region_len != 0 && (out.vertical != 0 || out.line_kind != 0 || out.size != 100.0) {
+        out.region_offset = 0;
+        out.region_len = 0;
+        out.n_warnings += 1;
+        warn(FFmpegRsWebvttSettingWarning {
+            kind: FFmpegRsWebvttSettingWarningKind::RegionIgnored as u32,
+            offset: region_setting.0,
+            len: region_setting.1,
+        });
+    }
+    out
+}
+
+// Decodes a cue settings string such as the `settings_offset`/`settings_len`
+// span of FFmpegRsWebvttCue. Warnings beyond `warnings_cap` are counted in
+// `out->n_warnings` but not stored. Returns 0, or -EINVAL on invalid
+// arguments.
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_webvtt_parse_cue_settings(
+    settings: *const u8,
+    settings_len: usize,
+    out: *mut FFmpegRsWebvttCueSettings,
+    warnings: *mut FFmpegRsWebvttSettingWarning,
+    warnings_cap: usize,
+) -> c_int {
+    let (s, out) = match (in_bytes(settings, settings_len), out_ref(out)) {
+        (Some(s), Some(o)) => (s, o),
+        _ => return EINVAL,
+    };
+    let warnings = out_array(warnings, warnings_cap);
+    let mut n = 0usize;
+    *out = parse_settings(s, |w| {
+        if let Some(slot) = warnings.get_mut(n) {
+            *slot = w;
+        }
+        n += 1;
+    });
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use FFmpegRsWebvttSettingWarningKind::*;
+
+    fn parse(s: &[u8]) -> (FFmpegRsWebvttCueSettings, [Option<FFmpegRsWebvttSettingWarning>; 8]) {
+        let mut w = [None; 8];
+        let mut n = 0;
+        let out = parse_settings(s, |x| {
+            w[n] = Some(x);
+            n += 1;
+        });
+        (out, w)
+    }
+
+    #[test]
+    fn parses_settings() {
+        let s = b"vertical:lr line:-2,end position:12.5%,line-right size:40% align:left";
+        let (out, w) = parse(s);
+        assert_eq!(w[0], None);
+        assert_eq!((out.vertical, out.line_kind, out.line, out.line_align), (2, 1, -2.0, 2));
+        assert_eq!((out.position, out.position_align, out.size, out.align), (12.5, 3, 40.0, 3));
+
+        let s = b"line:50%\tregion:r1";
+        let (out, _) = parse(s);
+        assert_eq!((out.line_kind, out.line, out.line_align), (2, 50.0, 0));
+        assert_eq!(out.region_len, 0);
+
+        let s = b"region:r1 align:end";
+        let (out, w) = parse(s);
+        assert_eq!(&s[out.region_offset..][..out.region_len], b"r1");
+        assert_eq!((out.align, out.n_warnings, w[0]), (2, 0, None));
+
+        let (out, _) = parse(b"");
+        assert_eq!(out, FFmpegRsWebvttCueSettings::default());
+    }
+
+    #[test]
+    fn warns_on_invalid_settings() {
+        let s = b"size:50% size:101% line:1.5.0 align:middle foo:bar nocolon position:5%,center :x";
+        let (out, w) = parse(s);
+        assert_eq!(out.n_warnings, 6);
+        assert_eq!(out.size, 50.0);
+        assert_eq!(out.line_kind, 0);
+        assert_eq!(out.align, 1);
+        assert_eq!((out.position, out.position_align), (5.0, 2));
+        let w0 = w[0].unwrap();
+        assert_eq!((w0.kind, &s[w0.offset..][..w0.len]), (InvalidValue as u32, &b"size:101%"[..]));
+        assert_eq!(w[1].unwrap().kind, InvalidValue as u32);
+        assert_eq!(w[2].unwrap().kind, InvalidValue as u32);
+        assert_eq!(w[3].unwrap().kind, UnknownName as u32);
+        assert_eq!((w[4].unwrap().kind, w[5].unwrap().kind, w[6]), (Malformed as u32, Malformed as u32, None));
+
+        let s = b"region:r1 size:10%";
+        let mut warnings = [FFmpegRsWebvttSettingWarning { kind: 0, offset: 0, len: 0 }; 1];
+        let mut out = FFmpegRsWebvttCueSettings::default();
+        assert_eq!(ffmpeg_rs_webvtt_parse_cue_settings(s.as_ptr(), s.len(), &mut out, warnings.as_mut_ptr(), 1), 0);
+        assert_eq!((out.region_len, out.n_warnings), (0, 1));
+        assert_eq!((warnings[0].kind, warnings[0].offset, warnings[0].len), (RegionIgnored as u32, 0, 9));
+    }
+}
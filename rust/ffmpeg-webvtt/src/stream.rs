@@ -0,0 +1,261 @@
+use core::ffi::c_int;
+
+use crate::writer::{in_bytes, out_array, out_ref, EINVAL, ENOSPC};
+use crate::{has_signature, next_block, parse_cue_block, FFmpegRsWebvttCue};
+
+/// Incremental parser state. The caller allocates it and the carry buffer
+/// passed to ffmpeg_rs_webvtt_stream_init(); the fields are private.
+#[repr(C)]
+pub struct FFmpegRsWebvttStream {
+    pub buf: *mut u8,
+    pub cap: usize,
+    pub len: usize,
+    /// Bytes at the start of `buf` already returned as cues.
+    pub consumed: usize,
+    pub header_done: c_int,
+}
+
+impl FFmpegRsWebvttStream {
+    // Returns up to `cues.len()` cues from the complete blocks in the
+    // buffer; at end of input the trailing block counts as complete. Other
+    // blocks are consumed even when `cues` is full.
+    fn drain(&mut self, eof: bool, cues: &mut [FFmpegRsWebvttCue]) -> Result<usize, c_int> {
+        let data: &[u8] = &out_array(self.buf, self.cap)[..self.len];
+        if self.header_done == 0 && data.len() >= 9 && !has_signature(data) {
+            return Err(-2);
+        }
+        let mut n = 0usize;
+        loop {
+            let (start, end) = match next_block(data, self.consumed) {
+                Some(b) => b,
+                None => {
+                    // Only separators left.
+                    self.consumed = data.len();
+                    break;
+                }
+            };
+            if end == data.len() && !eof {
+                self.consumed = start;
+                break;
+            }
+            let block = &data[start..end];
+            if self.header_done == 0 {
+                if !has_signature(block) {
+                    return Err(-2);
+                }
+                self.header_done = 1;
+            } else if let Some(cue) = parse_cue_block(start, block) {
+                if n == cues.len() {
+                    self.consumed = start;
+                    break;
+                }
+                cues[n] = cue;
+                n += 1;
+            }
+            self.consumed = (end + 1).min(data.len());
+        }
+        Ok(n)
+    }
+
+    // Moves the unreturned bytes to the start of the buffer.
+    fn compact(&mut self) {
+        out_array(self.buf, self.cap).copy_within(self.consumed..self.len, 0);
+        self.len -= self.consumed;
+        self.consumed = 0;
+    }
+
+    // Takes as much of `chunk` as fits, draining the blocks that are not
+    // cues to make room, and stops at the first cues so their offsets stay
+    // valid. `taken` is set even on error.
+    fn push(&mut self, chunk: &[u8], cues: &mut [FFmpegRsWebvttCue], taken: &mut usize) -> Result<usize, c_int> {
+        *taken = 0;
+        loop {
+            self.compact();
+            let k = (chunk.len() - *taken).min(self.cap - self.len);
+            out_array(self.buf, self.cap)[self.len..self.len + k].copy_from_slice(&chunk[*taken..*taken + k]);
+            self.len += k;
+            *taken += k;
+            let n = self.drain(false, cues)?;
+            if n > 0 || cues.is_empty() || *taken == chunk.len() {
+                return Ok(n);
+            }
+            if self.consumed == 0 {
+                // One unfinished block fills the whole buffer.
+                return Err(ENOSPC);
+            }
+        }
+    }
+}
+
+fn finish(r: Result<usize, c_int>, out_n_cues: &mut usize) -> c_int {
+    match r {
+        Ok(n) => {
+            *out_n_cues = n;
+            0
+        }
+        Err(e) => e,
+    }
+}
+
+// Prepares `stream` to parse a document arriving in chunks. `buf` carries
+// unfinished blocks between calls, so it bounds the size of one block.
+// Returns 0, or -EINVAL on invalid arguments.
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_webvtt_stream_init(stream: *mut FFmpegRsWebvttStream, buf: *mut u8, buf_cap: usize) -> c_int {
+    let stream = match out_ref(stream) {
+        Some(s) if !buf.is_null() && buf_cap > 0 => s,
+        _ => return EINVAL,
+    };
+    *stream = FFmpegRsWebvttStream { buf, cap: buf_cap, len: 0, consumed: 0, header_done: 0 };
+    0
+}
+
+// Appends `data` and writes the cues completed by a blank line so far to
+// `cues`. Cue offsets are into the carry buffer and stay valid until the
+// next call. Only part of `data` may be taken, e.g. when it is larger than
+// the carry buffer; `*out_consumed` is the number of bytes taken, and the
+// rest must be passed again. When `*out_n_cues == cues_cap` more cues may be
+// ready: call again with no data to drain them. Returns 0, -EINVAL on
+// invalid arguments, -2 without a WEBVTT signature, or -ENOSPC if a single
+// unfinished block does not fit the carry buffer.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "C" fn ffmpeg_rs_webvtt_stream_push(
+    stream: *mut FFmpegRsWebvttStream,
+    data: *const u8,
+    len: usize,
+    cues: *mut FFmpegRsWebvttCue,
+    cues_cap: usize,
+    out_n_cues: *mut usize,
+    out_consumed: *mut usize,
+) -> c_int {
+    let (stream, chunk, n, taken) = match (out_ref(stream), in_bytes(data, len), out_ref(out_n_cues), out_ref(out_consumed)) {
+        (Some(s), Some(c), Some(n), Some(t)) if !s.buf.is_null() => (s, c, n, t),
+        _ => return EINVAL,
+    };
+    finish(stream.push(chunk, out_array(cues, cues_cap), taken), n)
+}
+
+// Signals end of input: the last block no longer needs a blank line. Call
+// until `*out_n_cues` is 0. Same return values as the push call.
+#[no_mangle]
+pub extern "C" fn ffmpeg_rs_webvtt_stream_flush(
+    stream: *mut FFmpegRsWebvttStream,
+    cues: *mut FFmpegRsWebvttCue,
+    cues_cap: usize,
+    out_n_cues: *mut usize,
+) -> c_int {
+    let (stream, n) = match (out_ref(stream), out_ref(out_n_cues)) {
+        (Some(s), Some(n)) if !s.buf.is_null() => (s, n),
+        _ => return EINVAL,
+    };
+    if stream.header_done == 0 && !has_signature(&out_array(stream.buf, stream.cap)[..stream.len]) {
+        return -2;
+    }
+    finish(stream.drain(true, out_array(cues, cues_cap)), n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &[u8] = b"WEBVTT\r\n\r\nNOTE hi\r\n\r\n1\r\n00:00.000 --> 00:01.000 align:end\r\nHello\r\nthere\r\n\r\n00:01.000 --> 00:02.000\r\nBye";
+
+    fn empty_cue() -> FFmpegRsWebvttCue {
+        FFmpegRsWebvttCue {
+            start_ms: 0,
+            end_ms: 0,
+            payload_offset: 0,
+            payload_len: 0,
+            identifier_offset: 0,
+            identifier_len: 0,
+            settings_offset: 0,
+            settings_len: 0,
+        }
+    }
+
+    fn take(st: &FFmpegRsWebvttStream, cues: &[FFmpegRsWebvttCue], got: &mut [(i64, [u8; 16], usize)], n_got: &mut usize) {
+        for c in cues {
+            let payload = &out_array(st.buf, st.cap)[c.payload_offset..c.payload_offset + c.payload_len];
+            let g = &mut got[*n_got];
+            g.0 = c.start_ms;
+            g.1[..payload.len()].copy_from_slice(payload);
+            g.2 = payload.len();
+            *n_got += 1;
+        }
+    }
+
+    #[test]
+    fn emits_cues_across_chunk_boundaries() {
+        // Every split point must yield the same cues.
+        for split in 0..DOC.len() {
+            let mut buf = [0u8; 128];
+            let mut st = FFmpegRsWebvttStream { buf: core::ptr::null_mut(), cap: 0, len: 0, consumed: 0, header_done: 0 };
+            assert_eq!(ffmpeg_rs_webvtt_stream_init(&mut st, buf.as_mut_ptr(), buf.len()), 0);
+            let mut cues = [empty_cue(); 4];
+            let mut n = 0usize;
+            // (start_ms, copied payload, payload length) per cue.
+            let mut got = [(0i64, [0u8; 16], 0usize); 2];
+            let mut n_got = 0usize;
+            for chunk in [&DOC[..split], &DOC[split..]] {
+                let mut taken = 0usize;
+                assert_eq!(ffmpeg_rs_webvtt_stream_push(&mut st, chunk.as_ptr(), chunk.len(), cues.as_mut_ptr(), 4, &mut n, &mut taken), 0);
+                assert_eq!(taken, chunk.len());
+                take(&st, &cues[..n], &mut got, &mut n_got);
+            }
+            assert_eq!(n_got, 1, "split {}", split);
+            assert_eq!(ffmpeg_rs_webvtt_stream_flush(&mut st, cues.as_mut_ptr(), 4, &mut n), 0);
+            take(&st, &cues[..n], &mut got, &mut n_got);
+            assert_eq!(n_got, 2);
+            // Like ffmpeg_rs_webvtt_parse(), the CR of the blank line stays.
+            assert_eq!((got[0].0, &got[0].1[..got[0].2]), (0, &b"Hello\r\nthere\r"[..]));
+            assert_eq!((got[1].0, &got[1].1[..got[1].2]), (1000, &b"Bye"[..]));
+        }
+    }
+
+    #[test]
+    fn takes_chunks_larger_than_the_buffer() {
+        // The whole document at once through a buffer that holds one block.
+        let mut buf = [0u8; 64];
+        let mut st = FFmpegRsWebvttStream { buf: core::ptr::null_mut(), cap: 0, len: 0, consumed: 0, header_done: 0 };
+        assert_eq!(ffmpeg_rs_webvtt_stream_init(&mut st, buf.as_mut_ptr(), buf.len()), 0);
+        let mut cues = [empty_cue(); 1];
+        let (mut n, mut taken) = (0usize, 0usize);
+        let mut got = [(0i64, [0u8; 16], 0usize); 2];
+        let mut n_got = 0usize;
+        let mut rest = DOC;
+        while !rest.is_empty() {
+            assert_eq!(ffmpeg_rs_webvtt_stream_push(&mut st, rest.as_ptr(), rest.len(), cues.as_mut_ptr(), 1, &mut n, &mut taken), 0);
+            take(&st, &cues[..n], &mut got, &mut n_got);
+            rest = &rest[taken..];
+        }
+        assert_eq!(ffmpeg_rs_webvtt_stream_flush(&mut st, cues.as_mut_ptr(), 1, &mut n), 0);
+        take(&st, &cues[..n], &mut got, &mut n_got);
+        assert_eq!(n_got, 2);
+        assert_eq!((got[0].0, &got[0].1[..got[0].2]), (0, &b"Hello\r\nthere\r"[..]));
+        assert_eq!((got[1].0, &got[1].1[..got[1].2]), (1000, &b"Bye"[..]));
+    }
+
+    #[test]
+    fn reports_errors() {
+        let mut buf = [0u8; 16];
+        let mut st = FFmpegRsWebvttStream { buf: core::ptr::null_mut(), cap: 0, len: 0, consumed: 0, header_done: 0 };
+        let mut cues = [empty_cue(); 1];
+        let (mut n, mut taken) = (0usize, 0usize);
+        assert_eq!(ffmpeg_rs_webvtt_stream_init(&mut st, buf.as_mut_ptr(), buf.len()), 0);
+        // The header is drained to make room, but the cue block alone is
+        // larger than the buffer.
+        let chunk = b"WEBVTT\n\n00:00.000 --> 00:01.000\n";
+        assert_eq!(ffmpeg_rs_webvtt_stream_push(&mut st, chunk.as_ptr(), chunk.len(), cues.as_mut_ptr(), 1, &mut n, &mut taken), ENOSPC);
+        assert_eq!(taken, 24);
+        assert_eq!(ffmpeg_rs_webvtt_stream_push(&mut st, chunk.as_ptr(), 8, cues.as_mut_ptr(), 1, &mut n, core::ptr::null_mut()), EINVAL);
+
+        assert_eq!(ffmpeg_rs_webvtt_stream_init(&mut st, buf.as_mut_ptr(), buf.len()), 0);
+        let chunk = b"WEBVT";
+        assert_eq!(ffmpeg_rs_webvtt_stream_push(&mut st, chunk.as_ptr(), chunk.len(), core::ptr::null_mut(), 0, &mut n, &mut taken), 0);
+        assert_eq!((n, taken), (0, 5));
+        assert_eq!(ffmpeg_rs_webvtt_stream_flush(&mut st, core::ptr::null_mut(), 0, &mut n), -2);
+        let chunk = b"SRT\n\n1\n";
+        assert_eq!(ffmpeg_rs_webvtt_stream_push(&mut st, chunk.as_ptr(), chunk.len(), core::ptr::null_mut(), 0, &mut n, &mut taken), -2);
+    }
+}
@@ -0,0 +1,16 @@
+// Whitespace as the WebVTT and CSS parsers define it: space, tab, line
+// feed, carriage return and form feed.
+pub(crate) fn is_space(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | b'\r' | b'\x0c')
+}
+
+// Bounds of `s[start..end]` without surrounding whitespace.
+pub(crate) fn trim_span(s: &[u8], mut start: usize, mut end: usize) -> (usize, usize) {
+    while start < end && is_space(s[start]) {
+        start += 1;
+    }
+    while end > start && is_space(s[end - 1]) {
+        end -= 1;
+    }
+    (start, end)
+}
@@ -256,7 +256,25 @@ $(RUST_FFMPEG_UTIL_PARSE_LIB):
 clean::
 	$(Q)cd $(RUST_FFMPEG_UTIL_PARSE_DIR) && $(CARGO) -q clean || true
 
+RUST_FFMPEG_DASH_DIR := $(SRC_PATH)/rust/ffmpeg-dash
+RUST_FFMPEG_DASH_LIB := $(RUST_FFMPEG_DASH_DIR)/target/$(if $(RUST_TARGET),$(RUST_TARGET),)/$(RUST_ARTIFACT_SUBDIR)/libffmpeg_dash.a
+
+$(RUST_FFMPEG_DASH_LIB):
+	$(M)cd $(RUST_FFMPEG_DASH_DIR) && $(CARGO) -q build $(RUST_CARGO_PROFILE) $(RUST_CARGO_TARGET_ARG)
+
+clean::
+	$(Q)cd $(RUST_FFMPEG_DASH_DIR) && $(CARGO) -q clean || true
+
+RUST_FFMPEG_HTMLSUBTITLES_DIR := $(SRC_PATH)/rust/ffmpeg-htmlsubtitles
+RUST_FFMPEG_HTMLSUBTITLES_LIB := $(RUST_FFMPEG_HTMLSUBTITLES_DIR)/target/$(if $(RUST_TARGET),$(RUST_TARGET),)/$(RUST_ARTIFACT_SUBDIR)/libffmpeg_htmlsubtitles.a
+
+$(RUST_FFMPEG_HTMLSUBTITLES_LIB):
+	$(M)cd $(RUST_FFMPEG_HTMLSUBTITLES_DIR) && $(CARGO) -q build $(RUST_CARGO_PROFILE) $(RUST_CARGO_TARGET_ARG)
+
+clean::
+	$(Q)cd $(RUST_FFMPEG_HTMLSUBTITLES_DIR) && $(CARGO) -q clean || true
+
 # Build all known Rust crates.
-rust-libs: $(RUST_FFMPEG_FFI_LIB) $(RUST_FFMPEG_HLSWRITER_LIB) $(RUST_FFMPEG_HLSPARSER_LIB) $(RUST_FFMPEG_WEBVTT_LIB) $(RUST_FFMPEG_SUBRIP_LIB) $(RUST_FFMPEG_MICRODVD_LIB) $(RUST_FFMPEG_TTML_LIB) $(RUST_FFMPEG_MPL2_LIB) $(RUST_FFMPEG_VPLAYER_LIB) $(RUST_FFMPEG_JACOSUB_LIB) $(RUST_FFMPEG_SUBVIEWER_LIB) $(RUST_FFMPEG_SCC_LIB) $(RUST_FFMPEG_STL_LIB) $(RUST_FFMPEG_LRC_LIB) $(RUST_FFMPEG_MPSUB_LIB) $(RUST_FFMPEG_PJS_LIB) $(RUST_FFMPEG_REALTEXT_LIB) $(RUST_FFMPEG_AQTITLE_LIB) $(RUST_FFMPEG_SAMI_LIB) $(RUST_FFMPEG_ASS_LIB) $(RUST_FFMPEG_MCC_LIB) $(RUST_FFMPEG_ID3V2_LIB) $(RUST_FFMPEG_CONCAT_LIB) $(RUST_FFMPEG_DATA_URI_LIB) $(RUST_FFMPEG_FFMETADATA_LIB) $(RUST_FFMPEG_UTIL_PARSE_LIB)
+rust-libs: $(RUST_FFMPEG_FFI_LIB) $(RUST_FFMPEG_HLSWRITER_LIB) $(RUST_FFMPEG_HLSPARSER_LIB) $(RUST_FFMPEG_WEBVTT_LIB) $(RUST_FFMPEG_SUBRIP_LIB) $(RUST_FFMPEG_MICRODVD_LIB) $(RUST_FFMPEG_TTML_LIB) $(RUST_FFMPEG_MPL2_LIB) $(RUST_FFMPEG_VPLAYER_LIB) $(RUST_FFMPEG_JACOSUB_LIB) $(RUST_FFMPEG_SUBVIEWER_LIB) $(RUST_FFMPEG_SCC_LIB) $(RUST_FFMPEG_STL_LIB) $(RUST_FFMPEG_LRC_LIB) $(RUST_FFMPEG_MPSUB_LIB) $(RUST_FFMPEG_PJS_LIB) $(RUST_FFMPEG_REALTEXT_LIB) $(RUST_FFMPEG_AQTITLE_LIB) $(RUST_FFMPEG_SAMI_LIB) $(RUST_FFMPEG_ASS_LIB) $(RUST_FFMPEG_MCC_LIB) $(RUST_FFMPEG_ID3V2_LIB) $(RUST_FFMPEG_CONCAT_LIB) $(RUST_FFMPEG_DATA_URI_LIB) $(RUST_FFMPEG_FFMETADATA_LIB) $(RUST_FFMPEG_UTIL_PARSE_LIB) $(RUST_FFMPEG_DASH_LIB) $(RUST_FFMPEG_HTMLSUBTITLES_LIB)
 
 endif # FFMPEG_RUST_MAK_INCLUDED
@@ -0,0 +1,42 @@
+/*
+ * Fuzzer target for the DASH MPD event parser.
+ *
+ * This exercises the Rust-backed XML tokenizer/MPD event parser when
+ * configured with:
+ *   ./configure --enable-rust-dash ...
+ */
+
+#include "config.h"
+
+#if defined(HAVE_FFMPEG_RUST) && defined(CONFIG_RUST_DASH)
+
+#include <stddef.h>
+#include <stdint.h>
+
+#include "../rust/ffmpeg-dash/include/ffmpeg_rs_dash.h"
+
+int LLVMFuzzerTestOneInput(const uint8_t *data, size_t size);
+
+int LLVMFuzzerTestOneInput(const uint8_t *data, size_t size)
+{
+    FFmpegRsDashParseEventsResult out;
+    FFmpegRsDashEvent events[512];
+    (void)ffmpeg_rs_dash_parse_events(data, size, &out, events, 512);
+    return 0;
+}
+
+#else
+
+ #include <stddef.h>
+ #include <stdint.h>
+
+int LLVMFuzzerTestOneInput(const uint8_t *data, size_t size);
+
+int LLVMFuzzerTestOneInput(const uint8_t *data, size_t size)
+{
+    (void)data;
+    (void)size;
+    return 0;
+}
+
+#endif
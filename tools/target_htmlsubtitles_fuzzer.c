@@ -0,0 +1,41 @@
+/*
+ * Fuzzer target for the HTML subtitle markup to ASS converter.
+ *
+ * This exercises the Rust-backed converter when configured with:
+ *   ./configure --enable-rust-htmlsubtitles ...
+ */
+
+#include "config.h"
+
+#if defined(HAVE_FFMPEG_RUST) && defined(CONFIG_RUST_HTMLSUBTITLES)
+
+#include <stddef.h>
+#include <stdint.h>
+
+#include "../rust/ffmpeg-htmlsubtitles/include/ffmpeg_rs_htmlsubtitles.h"
+
+int LLVMFuzzerTestOneInput(const uint8_t *data, size_t size);
+
+int LLVMFuzzerTestOneInput(const uint8_t *data, size_t size)
+{
+    char dst[4096];
+    size_t required = 0;
+    (void)ffmpeg_rs_htmlmarkup_to_ass((const char *)data, size, dst, sizeof(dst), &required);
+    return 0;
+}
+
+#else
+
+ #include <stddef.h>
+ #include <stdint.h>
+
+int LLVMFuzzerTestOneInput(const uint8_t *data, size_t size);
+
+int LLVMFuzzerTestOneInput(const uint8_t *data, size_t size)
+{
+    (void)data;
+    (void)size;
+    return 0;
+}
+
+#endif
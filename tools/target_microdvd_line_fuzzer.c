@@ -19,7 +19,13 @@ int LLVMFuzzerTestOneInput(const uint8_t *data, size_t size);
 int LLVMFuzzerTestOneInput(const uint8_t *data, size_t size)
 {
     FFmpegRsMicrodvdEvent ev;
+    FFmpegRsMicrodvdToken tokens[32];
+    FFmpegRsMicrodvdTokenizeResult res;
+    FFmpegRsMicrodvdFrameRate rate;
+
     (void)ffmpeg_rs_microdvd_parse_line(data, size, &ev);
+    (void)ffmpeg_rs_microdvd_tokenize(data, size, &res, tokens, 32);
+    (void)ffmpeg_rs_microdvd_parse_framerate(data, size, &rate);
     return 0;
 }
 
@@ -19,7 +19,13 @@ int LLVMFuzzerTestOneInput(const uint8_t *data, size_t size);
 int LLVMFuzzerTestOneInput(const uint8_t *data, size_t size)
 {
     FFmpegRsMpl2Event out;
+    FFmpegRsMpl2Cue cue;
+    FFmpegRsMpl2Line lines[16];
+    FFmpegRsMpl2LinesResult res;
+
     (void)ffmpeg_rs_mpl2_parse_line(data, size, &out);
+    (void)ffmpeg_rs_mpl2_parse_line_ex(data, size, 1, 90000, &cue, lines, 16);
+    (void)ffmpeg_rs_mpl2_split_payload(data, size, &res, lines, 16);
     return 0;
 }
 